@@ -0,0 +1,10 @@
+-- 发表文章需要 author 角色。升级前的账户都可以发表文章，统一改为 author；
+-- 列的默认值也改为与 DEFAULT_USER_ROLE 的默认值一致。SQLite 不能直接修改列的默认值，
+-- 这里新增一列替换原来的 role 列，避免在迁移事务中重建被外键引用的 users 表
+ALTER TABLE users ADD COLUMN role_new TEXT NOT NULL DEFAULT 'author';
+
+UPDATE users SET role_new = CASE WHEN role = 'user' THEN 'author' ELSE role END;
+
+ALTER TABLE users DROP COLUMN role;
+
+ALTER TABLE users RENAME COLUMN role_new TO role;
@@ -0,0 +1,24 @@
+-- 刷新令牌表，只保存令牌的 SHA-256 摘要
+CREATE TABLE IF NOT EXISTS refresh_tokens
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT   NOT NULL,
+    user_id     INTEGER                             NOT NULL,
+    token_hash  TEXT UNIQUE                         NOT NULL,
+    access_jti  TEXT                                NOT NULL,
+    expires_at  TIMESTAMP                           NOT NULL,
+    created_at  TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    revoked_at  TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user_id ON refresh_tokens (user_id);
+
+-- 已吊销的访问令牌 (jti 黑名单)，过期后即可清理
+CREATE TABLE IF NOT EXISTS revoked_tokens
+(
+    jti        TEXT PRIMARY KEY NOT NULL,
+    expires_at TIMESTAMP        NOT NULL
+);
+
+-- 注销全部会话、修改或重置密码的时间，在此之前签发的访问令牌一律失效
+ALTER TABLE users ADD COLUMN tokens_valid_after TIMESTAMP;
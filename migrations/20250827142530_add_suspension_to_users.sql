@@ -0,0 +1,9 @@
+-- 为 users 表添加停用信息，suspended_until 为空表示永久封禁
+ALTER TABLE users
+    ADD COLUMN suspended_at TIMESTAMP;
+
+ALTER TABLE users
+    ADD COLUMN suspended_until TIMESTAMP;
+
+ALTER TABLE users
+    ADD COLUMN suspension_reason TEXT;
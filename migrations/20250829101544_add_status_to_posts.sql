@@ -0,0 +1,12 @@
+-- 为 posts 表添加发布状态，已有文章视为已发布
+ALTER TABLE posts
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'published';
+
+-- 已发布文章的发布时间，定时发布的文章为计划发布时间
+ALTER TABLE posts
+    ADD COLUMN published_at TIMESTAMP;
+
+UPDATE posts
+SET published_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', created_at);
+
+CREATE INDEX IF NOT EXISTS idx_posts_status_published_at ON posts (status, published_at);
@@ -0,0 +1,22 @@
+-- 文章修订历史，每次创建或修改文章都会保存一份完整快照
+CREATE TABLE IF NOT EXISTS post_revisions
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT   NOT NULL,
+    post_id         INTEGER                             NOT NULL,
+    revision_number INTEGER                             NOT NULL,
+    editor_id       INTEGER,
+    title           TEXT                                NOT NULL,
+    content         TEXT                                NOT NULL,
+    tags            TEXT                                NOT NULL,
+    copyright       TEXT                                NOT NULL,
+    restored_from   INTEGER,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (post_id, revision_number),
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE,
+    FOREIGN KEY (editor_id) REFERENCES users (id) ON DELETE SET NULL
+);
+
+-- 为已有文章补充初始修订
+INSERT INTO post_revisions (post_id, revision_number, editor_id, title, content, tags, copyright, created_at)
+SELECT id, 1, author_id, title, content, tags, copyright, created_at
+FROM posts;
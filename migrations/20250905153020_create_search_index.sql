@@ -0,0 +1,62 @@
+-- 文章和评论的全文索引 (外部内容表)，通过触发器与原表保持同步
+CREATE VIRTUAL TABLE IF NOT EXISTS posts_fts USING fts5
+(
+    title,
+    content,
+    content = 'posts',
+    content_rowid = 'id'
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS comments_fts USING fts5
+(
+    content,
+    content = 'comments',
+    content_rowid = 'comment_id'
+);
+
+INSERT INTO posts_fts(posts_fts) VALUES ('rebuild');
+INSERT INTO comments_fts(comments_fts) VALUES ('rebuild');
+
+CREATE TRIGGER IF NOT EXISTS posts_fts_after_insert
+    AFTER INSERT
+    ON posts
+BEGIN
+    INSERT INTO posts_fts(rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS posts_fts_after_delete
+    AFTER DELETE
+    ON posts
+BEGIN
+    INSERT INTO posts_fts(posts_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS posts_fts_after_update
+    AFTER UPDATE OF title, content
+    ON posts
+BEGIN
+    INSERT INTO posts_fts(posts_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+    INSERT INTO posts_fts(rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS comments_fts_after_insert
+    AFTER INSERT
+    ON comments
+BEGIN
+    INSERT INTO comments_fts(rowid, content) VALUES (new.comment_id, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS comments_fts_after_delete
+    AFTER DELETE
+    ON comments
+BEGIN
+    INSERT INTO comments_fts(comments_fts, rowid, content) VALUES ('delete', old.comment_id, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS comments_fts_after_update
+    AFTER UPDATE OF content
+    ON comments
+BEGIN
+    INSERT INTO comments_fts(comments_fts, rowid, content) VALUES ('delete', old.comment_id, old.content);
+    INSERT INTO comments_fts(rowid, content) VALUES (new.comment_id, new.content);
+END;
@@ -0,0 +1,36 @@
+-- 中文等 CJK 文字需要在应用中分词后再写入索引，因此改为独立存储分词结果的 FTS 表，
+-- 新增和修改由应用维护，删除仍通过触发器同步；旧数据在应用启动时重建索引
+DROP TRIGGER IF EXISTS posts_fts_after_insert;
+DROP TRIGGER IF EXISTS posts_fts_after_delete;
+DROP TRIGGER IF EXISTS posts_fts_after_update;
+DROP TRIGGER IF EXISTS comments_fts_after_insert;
+DROP TRIGGER IF EXISTS comments_fts_after_delete;
+DROP TRIGGER IF EXISTS comments_fts_after_update;
+
+DROP TABLE IF EXISTS posts_fts;
+DROP TABLE IF EXISTS comments_fts;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS posts_fts USING fts5
+(
+    title,
+    content
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS comments_fts USING fts5
+(
+    content
+);
+
+CREATE TRIGGER IF NOT EXISTS posts_fts_after_delete
+    AFTER DELETE
+    ON posts
+BEGIN
+    DELETE FROM posts_fts WHERE rowid = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS comments_fts_after_delete
+    AFTER DELETE
+    ON comments
+BEGIN
+    DELETE FROM comments_fts WHERE rowid = old.comment_id;
+END;
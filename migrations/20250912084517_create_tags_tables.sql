@@ -0,0 +1,88 @@
+-- 标签与文章的多对多关系，取代 posts.tags 中以逗号分隔的字符串
+CREATE TABLE IF NOT EXISTS tags
+(
+    id         INTEGER PRIMARY KEY AUTOINCREMENT   NOT NULL,
+    name       TEXT                                NOT NULL,
+    slug       TEXT                                NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS post_tags
+(
+    post_id INTEGER NOT NULL,
+    tag_id  INTEGER NOT NULL,
+    PRIMARY KEY (post_id, tag_id),
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_post_tags_tag_id ON post_tags (tag_id);
+
+-- 拆分已有的标签字符串
+CREATE TEMP TABLE legacy_post_tags AS
+WITH RECURSIVE split(post_id, name, rest) AS (
+    SELECT id, '', tags || ',' FROM posts
+    UNION ALL
+    SELECT post_id,
+           trim(substr(rest, 1, instr(rest, ',') - 1)),
+           substr(rest, instr(rest, ',') + 1)
+    FROM split
+    WHERE rest <> ''
+)
+SELECT DISTINCT post_id, name
+FROM split
+WHERE name <> '';
+
+-- 逐个字符计算 slug，与 utils::slugify 相同：字母和数字转为小写保留，其他字符合并为一个连字符。
+-- SQLite 无法判断 Unicode 字符的类别，非 ASCII 字符除常见的全角标点和空白外都视为文字；
+-- 名称中没有字母或数字的标签沿用小写的名称
+CREATE TEMP TABLE legacy_tag_slugs AS
+WITH RECURSIVE walk(name, position, slug) AS (
+    SELECT DISTINCT name, 1, '' FROM legacy_post_tags
+    UNION ALL
+    SELECT name,
+           position + 1,
+           CASE
+               WHEN lower(substr(name, position, 1)) GLOB '[a-z0-9]'
+                   OR (unicode(substr(name, position, 1)) > 127
+                       AND instr('　 ，。、；：？！“”‘’（）【】《》〈〉「」『』·—…～', substr(name, position, 1)) = 0)
+                   THEN slug || lower(substr(name, position, 1))
+               WHEN slug <> '' AND substr(slug, -1) <> '-' THEN slug || '-'
+               ELSE slug
+           END
+    FROM walk
+    WHERE position <= length(name)
+)
+SELECT name, coalesce(nullif(rtrim(slug, '-'), ''), lower(name)) AS slug
+FROM walk
+WHERE position = length(name) + 1;
+
+-- slug 相同的标签视为同一个
+INSERT INTO tags (name, slug)
+SELECT min(name), slug
+FROM legacy_tag_slugs
+GROUP BY slug;
+
+INSERT OR IGNORE INTO post_tags (post_id, tag_id)
+SELECT legacy_post_tags.post_id, tags.id
+FROM legacy_post_tags
+         JOIN legacy_tag_slugs ON legacy_tag_slugs.name = legacy_post_tags.name
+         JOIN tags ON tags.slug = legacy_tag_slugs.slug;
+
+DROP TABLE legacy_post_tags;
+DROP TABLE legacy_tag_slugs;
+
+ALTER TABLE posts
+    DROP COLUMN tags;
+
+-- 修订中的标签改为 JSON 数组保存，标签名称中可能含有逗号；已有的快照只能按逗号拆分
+UPDATE post_revisions
+SET tags = (WITH RECURSIVE split(name, rest) AS (SELECT '', post_revisions.tags || ','
+                                                 UNION ALL
+                                                 SELECT trim(substr(rest, 1, instr(rest, ',') - 1)),
+                                                        substr(rest, instr(rest, ',') + 1)
+                                                 FROM split
+                                                 WHERE rest <> '')
+            SELECT json_group_array(name)
+            FROM split
+            WHERE name <> '');
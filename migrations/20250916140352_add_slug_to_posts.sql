@@ -0,0 +1,14 @@
+-- 文章的 slug，已有文章的 slug 在应用启动时根据标题生成
+ALTER TABLE posts
+    ADD COLUMN slug TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_posts_slug ON posts (slug);
+
+-- 修改标题后旧的 slug 仍然指向原文章
+CREATE TABLE IF NOT EXISTS post_slug_redirects
+(
+    slug       TEXT PRIMARY KEY                    NOT NULL,
+    post_id    INTEGER                             NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE
+);
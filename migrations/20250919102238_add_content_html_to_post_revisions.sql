@@ -0,0 +1,3 @@
+-- 每个修订渲染后的 HTML，已有修订在应用启动时渲染
+ALTER TABLE post_revisions
+    ADD COLUMN content_html TEXT;
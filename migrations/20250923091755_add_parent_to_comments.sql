@@ -0,0 +1,8 @@
+-- 评论的回复关系，depth 为嵌套层数，顶层评论为 0
+ALTER TABLE comments
+    ADD COLUMN parent_id INTEGER REFERENCES comments (comment_id) ON DELETE CASCADE;
+
+ALTER TABLE comments
+    ADD COLUMN depth INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_comments_post_id_parent_id ON comments (post_id, parent_id);
@@ -0,0 +1,11 @@
+-- 评论的审核状态，已有评论视为已通过
+ALTER TABLE comments
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'approved'
+        CHECK (status IN ('pending', 'approved', 'rejected', 'spam'));
+
+CREATE INDEX IF NOT EXISTS idx_comments_status ON comments (status);
+
+-- 文章单独设置的评论审核方式，为空表示沿用站点设置
+ALTER TABLE posts
+    ADD COLUMN comment_moderation TEXT
+        CHECK (comment_moderation IN ('open', 'first_time', 'all'));
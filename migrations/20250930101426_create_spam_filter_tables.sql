@@ -0,0 +1,43 @@
+-- 用户的注册时间，升级前注册的用户为空，垃圾评论检测时视为老用户
+ALTER TABLE users
+    ADD COLUMN created_at TIMESTAMP;
+
+-- 评论作为训练样本时使用的标签，审核结果变化时据此撤销之前的训练
+ALTER TABLE comments
+    ADD COLUMN spam_label TEXT CHECK (spam_label IN ('spam', 'ham'));
+
+-- 规范化内容 (去掉首尾空白并转为小写) 的 SHA-256 摘要，用于重复内容检测。
+-- 升级前的评论为空，不参与重复检测
+ALTER TABLE comments
+    ADD COLUMN content_hash TEXT;
+
+CREATE INDEX idx_comments_content_hash ON comments (content_hash, created_at);
+
+-- 屏蔽词和屏蔽域名
+CREATE TABLE IF NOT EXISTS spam_blocklist
+(
+    id         INTEGER PRIMARY KEY                 NOT NULL,
+    kind       TEXT                                NOT NULL CHECK (kind IN ('word', 'domain')),
+    value      TEXT                                NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (kind, value)
+);
+
+-- 朴素贝叶斯分类器的词元统计
+CREATE TABLE IF NOT EXISTS spam_tokens
+(
+    token      TEXT PRIMARY KEY  NOT NULL,
+    spam_count INTEGER DEFAULT 0 NOT NULL,
+    ham_count  INTEGER DEFAULT 0 NOT NULL
+);
+
+-- 朴素贝叶斯分类器各类别的样本数量
+CREATE TABLE IF NOT EXISTS spam_documents
+(
+    label TEXT PRIMARY KEY  NOT NULL CHECK (label IN ('spam', 'ham')),
+    count INTEGER DEFAULT 0 NOT NULL
+);
+
+INSERT INTO spam_documents (label, count)
+VALUES ('spam', 0),
+       ('ham', 0);
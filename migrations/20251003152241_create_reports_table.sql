@@ -0,0 +1,43 @@
+-- 读者对文章和评论的举报，每个用户对同一内容只能举报一次
+CREATE TABLE IF NOT EXISTS reports
+(
+    id              INTEGER PRIMARY KEY                 NOT NULL,
+    target_type     TEXT                                NOT NULL CHECK (target_type IN ('post', 'comment')),
+    target_id       INTEGER                             NOT NULL,
+    reporter_id     INTEGER                             NOT NULL,
+    reason          TEXT                                NOT NULL,
+    details         TEXT,
+    status          TEXT      DEFAULT 'open'            NOT NULL CHECK (status IN ('open', 'resolved', 'dismissed')),
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    resolved_by     INTEGER,
+    resolved_at     TIMESTAMP,
+    resolution_note TEXT,
+    UNIQUE (target_type, target_id, reporter_id),
+    FOREIGN KEY (reporter_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (resolved_by) REFERENCES users (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_reports_status ON reports (status, target_type);
+
+-- 举报数量达到阈值或举报成立后隐藏的内容
+ALTER TABLE posts
+    ADD COLUMN hidden_at TIMESTAMP;
+
+ALTER TABLE comments
+    ADD COLUMN hidden_at TIMESTAMP;
+
+-- 举报通过 target_type / target_id 指向文章或评论，没有外键约束；内容被物理删除后同步删除举报，
+-- 否则评论 ID 被重新使用时，旧的举报会落到新评论上
+CREATE TRIGGER IF NOT EXISTS reports_after_post_delete
+    AFTER DELETE
+    ON posts
+BEGIN
+    DELETE FROM reports WHERE target_type = 'post' AND target_id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS reports_after_comment_delete
+    AFTER DELETE
+    ON comments
+BEGIN
+    DELETE FROM reports WHERE target_type = 'comment' AND target_id = old.comment_id;
+END;
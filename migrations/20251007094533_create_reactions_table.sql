@@ -0,0 +1,30 @@
+-- 读者对文章和评论的表情回应，每个用户对同一内容的每种表情只能回应一次
+CREATE TABLE IF NOT EXISTS reactions
+(
+    id          INTEGER PRIMARY KEY                 NOT NULL,
+    target_type TEXT                                NOT NULL CHECK (target_type IN ('post', 'comment')),
+    target_id   INTEGER                             NOT NULL,
+    user_id     INTEGER                             NOT NULL,
+    kind        TEXT                                NOT NULL CHECK (kind IN ('like', 'love', 'laugh', 'wow', 'sad', 'angry')),
+    created_at  TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (target_type, target_id, user_id, kind),
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_reactions_target ON reactions (target_type, target_id, kind);
+
+-- 表情回应通过 target_type / target_id 指向文章或评论，没有外键约束；内容被物理删除后同步删除回应，
+-- 否则评论 ID 被重新使用时，旧的回应会计入新评论
+CREATE TRIGGER IF NOT EXISTS reactions_after_post_delete
+    AFTER DELETE
+    ON posts
+BEGIN
+    DELETE FROM reactions WHERE target_type = 'post' AND target_id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS reactions_after_comment_delete
+    AFTER DELETE
+    ON comments
+BEGIN
+    DELETE FROM reactions WHERE target_type = 'comment' AND target_id = old.comment_id;
+END;
@@ -0,0 +1,12 @@
+-- 用户收藏的文章 (阅读列表)
+CREATE TABLE IF NOT EXISTS bookmarks
+(
+    user_id    INTEGER                             NOT NULL,
+    post_id    INTEGER                             NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, post_id),
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmarks_user_created_at ON bookmarks (user_id, created_at);
@@ -0,0 +1,16 @@
+-- 用户之间的关注关系
+CREATE TABLE IF NOT EXISTS follows
+(
+    follower_id INTEGER                             NOT NULL,
+    followee_id INTEGER                             NOT NULL,
+    created_at  TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (follower_id, followee_id),
+    CHECK (follower_id <> followee_id),
+    FOREIGN KEY (follower_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (followee_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_follows_followee_id ON follows (followee_id);
+
+-- 关注动态按发布时间倒序分页
+CREATE INDEX IF NOT EXISTS idx_posts_author_published ON posts (author_id, published_at, id);
@@ -0,0 +1,18 @@
+-- 用于找回密码的邮箱，保存为小写
+ALTER TABLE users ADD COLUMN email TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email ON users (email);
+
+-- 重置密码的一次性令牌，只保存摘要
+CREATE TABLE IF NOT EXISTS password_reset_tokens
+(
+    id         INTEGER PRIMARY KEY                 NOT NULL,
+    user_id    INTEGER                             NOT NULL,
+    token_hash TEXT                                NOT NULL UNIQUE,
+    expires_at TIMESTAMP                           NOT NULL,
+    used_at    TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user_id ON password_reset_tokens (user_id);
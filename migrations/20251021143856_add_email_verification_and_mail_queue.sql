@@ -0,0 +1,42 @@
+-- 邮箱验证时间，更换邮箱后重新置空
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP;
+
+-- 只有验证过的邮箱需要唯一：未验证的邮箱可能填错或被冒用，不能阻止真正的所有者使用
+DROP INDEX IF EXISTS idx_users_email;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_verified_email ON users (email) WHERE email_verified_at IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_users_email ON users (email);
+
+-- 验证邮箱的一次性令牌，只保存摘要，令牌对应签发时的邮箱
+CREATE TABLE IF NOT EXISTS email_verification_tokens
+(
+    id         INTEGER PRIMARY KEY                 NOT NULL,
+    user_id    INTEGER                             NOT NULL,
+    email      TEXT                                NOT NULL,
+    token_hash TEXT                                NOT NULL UNIQUE,
+    expires_at TIMESTAMP                           NOT NULL,
+    used_at    TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_verification_tokens_user_id ON email_verification_tokens (user_id);
+
+-- 待发送的邮件，发送失败后按退避时间重试
+CREATE TABLE IF NOT EXISTS mail_queue
+(
+    id              INTEGER PRIMARY KEY                 NOT NULL,
+    to_address      TEXT                                NOT NULL,
+    subject         TEXT                                NOT NULL,
+    text_body       TEXT                                NOT NULL,
+    html_body       TEXT,
+    status          TEXT      DEFAULT 'pending'         NOT NULL CHECK (status IN ('pending', 'sent', 'failed')),
+    attempts        INTEGER   DEFAULT 0                 NOT NULL,
+    last_error      TEXT,
+    next_attempt_at TIMESTAMP                           NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    sent_at         TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_mail_queue_pending ON mail_queue (status, next_attempt_at);
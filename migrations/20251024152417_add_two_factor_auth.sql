@@ -0,0 +1,32 @@
+-- TOTP 两步验证：确认前的密钥保存在 totp_pending_secret，确认后移入 totp_secret
+ALTER TABLE users ADD COLUMN totp_secret TEXT;
+ALTER TABLE users ADD COLUMN totp_pending_secret TEXT;
+ALTER TABLE users ADD COLUMN totp_enabled_at TIMESTAMP;
+-- 最近一次使用的验证码所在的时间步，同一个验证码不能重复使用
+ALTER TABLE users ADD COLUMN totp_last_step INTEGER;
+
+-- 恢复码只保存摘要，每个只能使用一次
+CREATE TABLE IF NOT EXISTS two_factor_recovery_codes
+(
+    id         INTEGER PRIMARY KEY                 NOT NULL,
+    user_id    INTEGER                             NOT NULL,
+    code_hash  TEXT                                NOT NULL,
+    used_at    TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_two_factor_recovery_codes_user_id ON two_factor_recovery_codes (user_id);
+
+-- 密码验证通过后签发的登录挑战，提交验证码后换取令牌
+CREATE TABLE IF NOT EXISTS two_factor_challenges
+(
+    id         INTEGER PRIMARY KEY                 NOT NULL,
+    user_id    INTEGER                             NOT NULL,
+    token_hash TEXT                                NOT NULL UNIQUE,
+    attempts   INTEGER   DEFAULT 0                 NOT NULL,
+    expires_at TIMESTAMP                           NOT NULL,
+    used_at    TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
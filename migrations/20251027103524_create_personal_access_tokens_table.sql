@@ -0,0 +1,18 @@
+-- 用于自动化脚本的个人访问令牌，只保存摘要，token_prefix 用于在列表中辨认令牌
+CREATE TABLE IF NOT EXISTS personal_access_tokens
+(
+    id           INTEGER PRIMARY KEY                 NOT NULL,
+    user_id      INTEGER                             NOT NULL,
+    name         TEXT                                NOT NULL,
+    token_prefix TEXT                                NOT NULL,
+    token_hash   TEXT                                NOT NULL UNIQUE,
+    -- 授权范围，JSON 数组
+    scopes       TEXT                                NOT NULL,
+    last_used_at TIMESTAMP,
+    expires_at   TIMESTAMP,
+    revoked_at   TIMESTAMP,
+    created_at   TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_personal_access_tokens_user_id ON personal_access_tokens (user_id);
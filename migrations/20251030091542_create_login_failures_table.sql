@@ -0,0 +1,10 @@
+-- 登录失败的统计，scope 为 account 时 key 是提交的用户名（包括不存在的用户名），为 ip 时 key 是客户端地址
+CREATE TABLE IF NOT EXISTS login_failures
+(
+    scope          TEXT      NOT NULL,
+    key            TEXT      NOT NULL,
+    failures       INTEGER   NOT NULL,
+    last_failed_at TIMESTAMP NOT NULL,
+    locked_until   TIMESTAMP,
+    PRIMARY KEY (scope, key)
+);
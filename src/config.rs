@@ -1,3 +1,7 @@
+use crate::mail::MailTransportKind;
+use crate::models::ModerationMode;
+use crate::permissions::Role;
+use crate::utils::PasswordAlgorithm;
 use std::env;
 
 #[derive(Debug, Clone)]
@@ -7,12 +11,47 @@ pub struct Config {
     pub server_host: String,
     pub server_port: u16,
     pub db_max_connections: u32,
-    pub jwt_expiration_days: i64,
+    pub access_token_expiration_minutes: i64,
+    pub refresh_token_expiration_days: i64,
+    pub default_user_role: Role,
+    pub scheduler_interval_seconds: u64,
+    pub comment_moderation: ModerationMode,
+    pub report_hide_threshold: i64,
+    pub mail_transport: MailTransportKind,
+    pub mail_outbox_dir: String,
+    pub mail_from: String,
+    /// `MAIL_TRANSPORT` 为 `smtp` 时使用的服务器地址
+    pub smtp_url: Option<String>,
+    pub mail_retry_interval_seconds: u64,
+    /// 站点对外的地址，用于生成邮件中的链接
+    pub public_url: String,
+    pub password_reset_expiration_minutes: i64,
+    pub email_verification_expiration_hours: i64,
+    /// 必须启用两步验证的角色，未启用时只保留作者的权限
+    pub two_factor_required_roles: Vec<Role>,
+    pub two_factor_challenge_expiration_minutes: i64,
+    /// 验证器应用中显示的站点名称
+    pub totp_issuer: String,
+    /// 同一用户名连续登录失败多少次后锁定，为 0 时不锁定
+    pub login_max_failures_per_account: i64,
+    /// 同一客户端地址连续登录失败多少次后锁定，为 0 时不锁定
+    pub login_max_failures_per_ip: i64,
+    /// 第一次锁定的时长，之后每多失败一次加倍
+    pub login_lockout_base_seconds: i64,
+    pub login_lockout_max_minutes: i64,
+    /// 部署在反向代理之后时，从 `X-Forwarded-For` 读取客户端地址
+    pub trust_proxy_headers: bool,
+    /// 新密码使用的哈希算法，已有的哈希在登录成功后按当前配置重新计算
+    pub password_hash_algorithm: PasswordAlgorithm,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub bcrypt_cost: u32,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self, String> {
-        Ok(Config {
+        let config = Config {
             database_url: env::var("DATABASE_URL")
                 .map_err(|_| "DATABASE_URL must be set".to_string())?,
             jwt_secret: env::var("JWT_SECRET").map_err(|_| "JWT_SECRET must be set".to_string())?,
@@ -25,11 +64,135 @@ impl Config {
                 .unwrap_or_else(|_| "10".to_string())
                 .parse()
                 .map_err(|_| "Invalid DB_MAX_CONNECTIONS format".to_string())?,
-            jwt_expiration_days: env::var("JWT_EXPIRATION_DAYS")
+            access_token_expiration_minutes: env::var("ACCESS_TOKEN_EXPIRATION_MINUTES")
+                .unwrap_or_else(|_| "15".to_string())
+                .parse()
+                .map_err(|_| "Invalid ACCESS_TOKEN_EXPIRATION_MINUTES format".to_string())?,
+            refresh_token_expiration_days: env::var("REFRESH_TOKEN_EXPIRATION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .map_err(|_| "Invalid REFRESH_TOKEN_EXPIRATION_DAYS format".to_string())?,
+            default_user_role: env::var("DEFAULT_USER_ROLE")
+                .unwrap_or_else(|_| "author".to_string())
+                .parse()
+                .map_err(|_| "Invalid DEFAULT_USER_ROLE format".to_string())?,
+            scheduler_interval_seconds: env::var("SCHEDULER_INTERVAL_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .map_err(|_| "Invalid SCHEDULER_INTERVAL_SECONDS format".to_string())?,
+            comment_moderation: env::var("COMMENT_MODERATION")
+                .unwrap_or_else(|_| "open".to_string())
+                .parse()
+                .map_err(|_| "Invalid COMMENT_MODERATION format".to_string())?,
+            report_hide_threshold: env::var("REPORT_HIDE_THRESHOLD")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .map_err(|_| "Invalid REPORT_HIDE_THRESHOLD format".to_string())?,
+            mail_transport: env::var("MAIL_TRANSPORT")
+                .unwrap_or_else(|_| "file".to_string())
+                .parse()
+                .map_err(|_| "Invalid MAIL_TRANSPORT format".to_string())?,
+            mail_outbox_dir: env::var("MAIL_OUTBOX_DIR").unwrap_or_else(|_| "outbox".to_string()),
+            mail_from: env::var("MAIL_FROM").unwrap_or_else(|_| "noreply@localhost".to_string()),
+            smtp_url: env::var("SMTP_URL").ok(),
+            mail_retry_interval_seconds: env::var("MAIL_RETRY_INTERVAL_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .map_err(|_| "Invalid MAIL_RETRY_INTERVAL_SECONDS format".to_string())?,
+            public_url: env::var("PUBLIC_URL")
+                .unwrap_or_else(|_| "http://localhost:3000".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            password_reset_expiration_minutes: env::var("PASSWORD_RESET_EXPIRATION_MINUTES")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .map_err(|_| "Invalid PASSWORD_RESET_EXPIRATION_MINUTES format".to_string())?,
+            email_verification_expiration_hours: env::var("EMAIL_VERIFICATION_EXPIRATION_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .map_err(|_| "Invalid EMAIL_VERIFICATION_EXPIRATION_HOURS format".to_string())?,
+            two_factor_required_roles: env::var("TWO_FACTOR_REQUIRED_ROLES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|role| !role.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| "Invalid TWO_FACTOR_REQUIRED_ROLES format".to_string())?,
+            two_factor_challenge_expiration_minutes: env::var(
+                "TWO_FACTOR_CHALLENGE_EXPIRATION_MINUTES",
+            )
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .map_err(|_| "Invalid TWO_FACTOR_CHALLENGE_EXPIRATION_MINUTES format".to_string())?,
+            totp_issuer: env::var("TOTP_ISSUER").unwrap_or_else(|_| "Inkwell".to_string()),
+            login_max_failures_per_account: env::var("LOGIN_MAX_FAILURES_PER_ACCOUNT")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .map_err(|_| "Invalid LOGIN_MAX_FAILURES_PER_ACCOUNT format".to_string())?,
+            login_max_failures_per_ip: env::var("LOGIN_MAX_FAILURES_PER_IP")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .map_err(|_| "Invalid LOGIN_MAX_FAILURES_PER_IP format".to_string())?,
+            login_lockout_base_seconds: env::var("LOGIN_LOCKOUT_BASE_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .map_err(|_| "Invalid LOGIN_LOCKOUT_BASE_SECONDS format".to_string())?,
+            login_lockout_max_minutes: env::var("LOGIN_LOCKOUT_MAX_MINUTES")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .map_err(|_| "Invalid LOGIN_LOCKOUT_MAX_MINUTES format".to_string())?,
+            trust_proxy_headers: env::var("TRUST_PROXY_HEADERS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| "Invalid TRUST_PROXY_HEADERS format".to_string())?,
+            password_hash_algorithm: env::var("PASSWORD_HASH_ALGORITHM")
+                .unwrap_or_else(|_| "argon2id".to_string())
+                .parse()
+                .map_err(|_| "Invalid PASSWORD_HASH_ALGORITHM format".to_string())?,
+            argon2_memory_kib: env::var("ARGON2_MEMORY_KIB")
+                .unwrap_or_else(|_| "19456".to_string())
+                .parse()
+                .map_err(|_| "Invalid ARGON2_MEMORY_KIB format".to_string())?,
+            argon2_iterations: env::var("ARGON2_ITERATIONS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .map_err(|_| "Invalid ARGON2_ITERATIONS format".to_string())?,
+            argon2_parallelism: env::var("ARGON2_PARALLELISM")
                 .unwrap_or_else(|_| "1".to_string())
                 .parse()
-                .map_err(|_| "Invalid JWT_EXPIRATION_DAYS format".to_string())?,
-        })
+                .map_err(|_| "Invalid ARGON2_PARALLELISM format".to_string())?,
+            bcrypt_cost: env::var("BCRYPT_COST")
+                .unwrap_or_else(|_| bcrypt::DEFAULT_COST.to_string())
+                .parse()
+                .map_err(|_| "Invalid BCRYPT_COST format".to_string())?,
+        };
+
+        // tokio 的 interval 在周期为 0 时会 panic，后台任务会直接退出
+        if config.scheduler_interval_seconds == 0 {
+            return Err("SCHEDULER_INTERVAL_SECONDS must be greater than 0".to_string());
+        }
+        if config.mail_retry_interval_seconds == 0 {
+            return Err("MAIL_RETRY_INTERVAL_SECONDS must be greater than 0".to_string());
+        }
+        // 阈值为 0 或负数时任何内容都会在第一次举报时被隐藏
+        if config.report_hide_threshold < 1 {
+            return Err("REPORT_HIDE_THRESHOLD must be greater than 0".to_string());
+        }
+
+        // 参数超出范围时在启动时报错，而不是等到第一次注册时才失败
+        argon2::Params::new(
+            config.argon2_memory_kib,
+            config.argon2_iterations,
+            config.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+        if !(4..=31).contains(&config.bcrypt_cost) {
+            return Err("BCRYPT_COST must be between 4 and 31".to_string());
+        }
+
+        Ok(config)
     }
 
     pub fn server_address(&self) -> String {
@@ -2,9 +2,20 @@ use crate::{
     errors::ErrorResponse,
     handlers::*,
     models::{
-        Comment, CreateComment, CreatePost, LoginUser, PaginatedResponse, Post, RegisterUser,
-        TokenResponse,
+        AccessTokenResponse, AdminUserResponse, BlocklistEntry, BlocklistKind, ChangePassword,
+        Comment, CommentResponse, CommentSort, CommentStatus, CreateAccessToken,
+        CreateBlocklistEntry, CreateComment, CreatePost, CreateReport, CreatedAccessToken,
+        DiffLine, DiffOp, DisableTwoFactor, FeedResponse, FieldDiff, ForgotPassword, LoginResponse,
+        LoginUser, MeResponse, ModerateComments, ModerationMode, ModerationResult,
+        PaginatedResponse, Post, PostResponse, PostRevisionResponse, PostRevisionSummary, PostSort,
+        ReactionKind, ReactionSummary, RecoveryCodesResponse, RefreshTokenRequest, RegisterUser,
+        RenameTag, ReportDecision, ReportReason, ReportResponse, ReportStatus, ReportTarget,
+        ResetPassword, RevisionDiffResponse, SearchResult, SuspendUser, TagPageResponse,
+        TagResponse, TokenResponse, TwoFactorChallenge, TwoFactorCode, TwoFactorLogin,
+        TwoFactorSetupResponse, UpdateCommentModeration, UpdateEmail, UpdateProfile,
+        UpdateUserRole, UserProfile, VerifyEmail,
     },
+    permissions::{Role, TokenScope},
     routes::*,
 };
 use utoipa::OpenApi;
@@ -15,15 +26,74 @@ use utoipa::OpenApi;
         root,
         register,
         login,
+        refresh_token,
+        logout,
+        logout_all,
+        change_password,
+        forgot_password,
+        reset_password,
+        update_email,
+        resend_verification_email,
+        verify_email,
+        login_two_factor,
+        setup_two_factor,
+        confirm_two_factor,
+        disable_two_factor,
+        regenerate_recovery_codes,
+        create_access_token,
+        get_access_tokens,
+        delete_access_token,
         get_posts,
         create_post,
         get_post_by_id,
+        get_post_by_slug,
         update_post,
         delete_post,
+        update_comment_moderation,
+        get_post_revisions,
+        get_post_revision,
+        diff_post_revisions,
+        restore_post_revision,
         get_comments_for_post,
         create_comment_for_post,
         update_comment,
         delete_comment,
+        get_moderation_queue,
+        moderate_comments,
+        get_blocklist,
+        create_blocklist_entry,
+        delete_blocklist_entry,
+        report_post,
+        report_comment,
+        list_reports,
+        resolve_report,
+        dismiss_report,
+        add_bookmark,
+        remove_bookmark,
+        get_bookmarks,
+        add_post_reaction,
+        remove_post_reaction,
+        add_comment_reaction,
+        remove_comment_reaction,
+        get_tags,
+        get_tag,
+        rename_tag,
+        search,
+        get_user_profile,
+        get_user_posts,
+        get_me,
+        update_me,
+        follow_user,
+        unfollow_user,
+        get_feed,
+        list_users,
+        get_user,
+        update_user_role,
+        suspend_user,
+        unsuspend_user,
+        unlock_user,
+        reset_user_two_factor,
+        delete_user,
     ),
     components(
         schemas(
@@ -31,17 +101,86 @@ use utoipa::OpenApi;
             CreatePost,
             Comment,
             CreateComment,
+            CommentSort,
+            CommentStatus,
+            PaginatedResponse<CommentResponse>,
+            ModerationMode,
+            UpdateCommentModeration,
+            ModerateComments,
+            ModerationResult,
+            BlocklistKind,
+            BlocklistEntry,
+            CreateBlocklistEntry,
+            ReportTarget,
+            ReportReason,
+            ReportStatus,
+            CreateReport,
+            ReportDecision,
+            ReportResponse,
+            PaginatedResponse<ReportResponse>,
+            ReactionKind,
+            ReactionSummary,
+            PostSort,
             PaginatedResponse<Post>,
             ErrorResponse,
             RegisterUser,
             LoginUser,
-            TokenResponse
+            TokenResponse,
+            RefreshTokenRequest,
+            ChangePassword,
+            ForgotPassword,
+            ResetPassword,
+            UpdateEmail,
+            VerifyEmail,
+            LoginResponse,
+            TwoFactorChallenge,
+            TwoFactorLogin,
+            TwoFactorSetupResponse,
+            TwoFactorCode,
+            DisableTwoFactor,
+            RecoveryCodesResponse,
+            TokenScope,
+            AccessTokenResponse,
+            CreatedAccessToken,
+            CreateAccessToken,
+            Role,
+            AdminUserResponse,
+            PaginatedResponse<AdminUserResponse>,
+            UpdateUserRole,
+            SuspendUser,
+            PostRevisionSummary,
+            PostRevisionResponse,
+            RevisionDiffResponse,
+            FieldDiff,
+            DiffLine,
+            DiffOp,
+            TagResponse,
+            TagPageResponse,
+            RenameTag,
+            PaginatedResponse<PostResponse>,
+            SearchResult,
+            PaginatedResponse<SearchResult>,
+            UserProfile,
+            MeResponse,
+            UpdateProfile,
+            FeedResponse
         )
     ),
     tags(
         (name = "Rust Blog API", description = "一个用 Rust 和 Axum 构建的简单博客 API"),
+        (name = "Auth", description = "关于注册、登录和令牌的操作"),
+        (name = "Two-Factor", description = "TOTP 两步验证和恢复码"),
+        (name = "Access Tokens", description = "用于自动化脚本的个人访问令牌"),
         (name = "Posts", description = "关于文章的操作"),
-        (name = "Comments", description = "关于评论的操作")
+        (name = "Revisions", description = "关于文章修订历史的操作"),
+        (name = "Tags", description = "关于标签的操作"),
+        (name = "Comments", description = "关于评论的操作"),
+        (name = "Reports", description = "举报文章和评论以及处理举报"),
+        (name = "Bookmarks", description = "收藏文章和查看阅读列表"),
+        (name = "Reactions", description = "对文章和评论的表情回应"),
+        (name = "Search", description = "全文搜索文章和评论"),
+        (name = "Users", description = "用户资料、关注和关注动态"),
+        (name = "Admin", description = "管理员的用户管理操作")
     )
 )]
 pub struct ApiDoc;
@@ -1,5 +1,8 @@
-use axum::{Json, http::StatusCode, response::IntoResponse};
-use log::error;
+use axum::{
+    Json,
+    http::{HeaderValue, StatusCode, header},
+    response::IntoResponse,
+};
 use serde::Serialize;
 use thiserror::Error;
 use utoipa::ToSchema;
@@ -24,6 +27,9 @@ pub enum AppError {
     #[error("冲突错误: {message}")]
     Conflict { message: String },
 
+    #[error("请求过于频繁: {message}")]
+    TooManyRequests { message: String, retry_after: u64 },
+
     #[error("内部服务器错误: {message}")]
     Internal { message: String },
 
@@ -55,6 +61,7 @@ impl AppError {
             AppError::Authorization { .. } => StatusCode::FORBIDDEN,
             AppError::NotFound { .. } => StatusCode::NOT_FOUND,
             AppError::Conflict { .. } => StatusCode::CONFLICT,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
             AppError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::PasswordHash => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::Jwt(_) => StatusCode::UNAUTHORIZED,
@@ -81,7 +88,13 @@ impl IntoResponse for AppError {
 
         tracing::error!(error = ?self, "请求处理失败");
 
-        (status, Json(error_response)).into_response()
+        let mut response = (status, Json(error_response)).into_response();
+        if let AppError::TooManyRequests { retry_after, .. } = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
 }
 
@@ -115,6 +128,14 @@ impl AppError {
         }
     }
 
+    /// `retry_after_seconds` 通过 `Retry-After` 响应头告诉客户端多久之后可以重试
+    pub fn too_many_requests(msg: impl Into<String>, retry_after_seconds: u64) -> Self {
+        Self::TooManyRequests {
+            message: msg.into(),
+            retry_after: retry_after_seconds,
+        }
+    }
+
     pub fn internal(msg: impl Into<String>) -> Self {
         Self::Internal {
             message: msg.into(),
@@ -0,0 +1,249 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    models::{
+        AccessTokenResponse, AppState, Claims, CreateAccessToken, CreatedAccessToken,
+        PersonalAccessToken, User,
+    },
+    permissions::TokenScope,
+    utils::{check_delete_result, created_response, generate_token, hash_token},
+    validation::ValidatedJson,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::{Method, StatusCode},
+    response::IntoResponse,
+};
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+/// 个人访问令牌的固定前缀，用于和 JWT 区分
+pub const ACCESS_TOKEN_PREFIX: &str = "inkpat_";
+
+/// 列表中展示的令牌开头的长度
+const DISPLAY_PREFIX_LEN: usize = ACCESS_TOKEN_PREFIX.len() + 5;
+
+#[utoipa::path(
+    post,
+    path = "/me/tokens",
+    request_body = CreateAccessToken,
+    responses(
+        (status = 201, description = "成功创建令牌，令牌原文只在本次响应中返回", body = CreatedAccessToken),
+        (status = 400, description = "令牌数据无效", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Access Tokens",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn create_access_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<CreateAccessToken>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = json_payload.validate_json()?;
+    let user = find_user(&state, &claims).await?;
+
+    let mut scopes = payload.scopes;
+    scopes.sort();
+    scopes.dedup();
+
+    let token = format!("{}{}", ACCESS_TOKEN_PREFIX, generate_token());
+    let stored: PersonalAccessToken = sqlx::query_as(
+        "INSERT INTO personal_access_tokens (user_id, name, token_prefix, token_hash, scopes, expires_at, created_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(user.id)
+    .bind(payload.name.trim())
+    .bind(&token[..DISPLAY_PREFIX_LEN])
+    .bind(hash_token(&token))
+    .bind(serde_json::to_string(&scopes).unwrap_or_default())
+    .bind(payload.expires_in_days.map(|days| Utc::now() + Duration::days(days)))
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(created_response(CreatedAccessToken {
+        info: stored.into(),
+        token,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/tokens",
+    responses(
+        (status = 200, description = "列出当前用户未吊销的令牌，最近创建的在前", body = Vec<AccessTokenResponse>),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Access Tokens",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_access_tokens(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<AccessTokenResponse>>, AppError> {
+    let user = find_user(&state, &claims).await?;
+
+    let tokens: Vec<PersonalAccessToken> = sqlx::query_as(
+        "SELECT * FROM personal_access_tokens WHERE user_id = ? AND revoked_at IS NULL \
+         ORDER BY created_at DESC, id DESC",
+    )
+    .bind(user.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(tokens.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/tokens/{id}",
+    params(("id" = u64, Path, description = "令牌 ID")),
+    responses(
+        (status = 204, description = "成功吊销令牌"),
+        (status = 401, description = "未认证", body = ErrorResponse),
+        (status = 404, description = "未找到令牌或已吊销", body = ErrorResponse)
+    ),
+    tag = "Access Tokens",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn delete_access_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, AppError> {
+    let user = find_user(&state, &claims).await?;
+
+    let result = sqlx::query(
+        "UPDATE personal_access_tokens SET revoked_at = ? \
+         WHERE id = ? AND user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(id as i64)
+    .bind(user.id)
+    .execute(&state.pool)
+    .await?;
+
+    check_delete_result(result, "Access token")
+}
+
+/// 吊销用户所有未吊销的个人访问令牌，密码修改或重置后调用
+pub(crate) async fn revoke_user_access_tokens(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE personal_access_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 根据个人访问令牌生成请求的身份信息，令牌无效、已吊销或已过期时返回 401
+pub(crate) async fn access_token_claims(state: &AppState, token: &str) -> Result<Claims, AppError> {
+    let now = Utc::now();
+    let stored: PersonalAccessToken = sqlx::query_as(
+        "SELECT * FROM personal_access_tokens WHERE token_hash = ? AND revoked_at IS NULL \
+         AND (expires_at IS NULL OR expires_at > ?)",
+    )
+    .bind(hash_token(token))
+    .bind(now)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::authentication("Invalid or expired token"))?;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = ?")
+        .bind(stored.user_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    // 最近使用时间精确到分钟即可，避免每个请求都写数据库
+    sqlx::query(
+        "UPDATE personal_access_tokens SET last_used_at = ?1 \
+         WHERE id = ?2 AND (last_used_at IS NULL OR last_used_at < ?3)",
+    )
+    .bind(now)
+    .bind(stored.id)
+    .bind(now - Duration::minutes(1))
+    .execute(&state.pool)
+    .await?;
+
+    Ok(Claims {
+        sub: user.username,
+        role: user.role,
+        exp: stored
+            .expires_at
+            .map_or(usize::MAX, |expires_at| expires_at.timestamp() as usize),
+        jti: format!("pat:{}", stored.id),
+        iat: Claims::timestamp(stored.created_at),
+        two_factor_pending: false,
+        token_scopes: Some(stored.scopes),
+    })
+}
+
+/// 检查个人访问令牌能否访问指定的路由
+///
+/// 令牌可以读取当前用户能看到的内容，但写操作只限于授权范围对应的路由，也不能管理令牌本身；
+/// 审核、屏蔽列表、举报和用户管理等管理端点即使只是读取也不能访问。
+pub(crate) fn check_token_scope(
+    scopes: &[TokenScope],
+    method: &Method,
+    route: &str,
+) -> Result<(), AppError> {
+    if route.starts_with("/me/tokens") {
+        return Err(AppError::authorization("个人访问令牌不能管理令牌"));
+    }
+    if is_privileged_route(route) {
+        return Err(AppError::authorization("个人访问令牌不能访问管理端点"));
+    }
+    if method == Method::GET || method == Method::HEAD {
+        return Ok(());
+    }
+    match required_scope(method, route) {
+        Some(scope) if scopes.contains(&scope) => Ok(()),
+        Some(scope) => Err(AppError::authorization(format!(
+            "访问令牌缺少 {} 授权范围",
+            scope.as_str()
+        ))),
+        None => Err(AppError::authorization("个人访问令牌不能执行此操作")),
+    }
+}
+
+/// 判断路由是否为只有编辑或管理员才能访问的管理端点，`route` 为路由模板
+fn is_privileged_route(route: &str) -> bool {
+    route.starts_with("/admin/")
+        || route.starts_with("/comments/moderation")
+        || route.starts_with("/comments/blocklist")
+}
+
+/// 写操作所需的授权范围，`route` 为路由模板
+fn required_scope(method: &Method, route: &str) -> Option<TokenScope> {
+    match (method.as_str(), route) {
+        ("POST", "/posts")
+        | ("PUT" | "DELETE", "/posts/{id}")
+        | ("PUT", "/posts/{id}/comment-moderation")
+        | ("POST", "/posts/{id}/revisions/{revision}/restore") => Some(TokenScope::PostsWrite),
+        ("POST", "/posts/{id}/comments")
+        | ("PUT" | "DELETE", "/posts/{post_id}/comments/{comment_id}") => {
+            Some(TokenScope::CommentsWrite)
+        }
+        _ => None,
+    }
+}
+
+async fn find_user(state: &AppState, claims: &Claims) -> Result<User, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+    Ok(user)
+}
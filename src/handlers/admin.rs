@@ -0,0 +1,387 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    handlers::two_factor::clear_two_factor,
+    login_throttle::clear_login_failures,
+    models::{
+        AdminUserResponse, AppState, Claims, PaginatedResponse, Pagination, SuspendUser,
+        UpdateUserRole, User, UserFilter, UserStatus,
+    },
+    utils::check_delete_result,
+    validation::{ValidatedJson, format_validation_errors},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use validator::Validate;
+
+/// 列出用户时共用的筛选条件，参数依次为 role、status、now、q
+const USER_FILTER_SQL: &str = "(?1 IS NULL OR role = ?1) \
+     AND (?2 IS NULL \
+          OR (?2 = 'suspended' AND suspended_at IS NOT NULL AND (suspended_until IS NULL OR suspended_until > ?3)) \
+          OR (?2 = 'active' AND (suspended_at IS NULL OR suspended_until <= ?3))) \
+     AND (?4 IS NULL OR username LIKE '%' || ?4 || '%')";
+
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    params(
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页数量"),
+        ("role" = Option<String>, Query, description = "按角色筛选"),
+        ("status" = Option<String>, Query, description = "按状态筛选: active 或 suspended"),
+        ("q" = Option<String>, Query, description = "用户名包含的关键字")
+    ),
+    responses(
+        (status = 200, description = "成功列出用户", body = PaginatedResponse<AdminUserResponse>),
+        (status = 403, description = "无权限操作", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(pagination): Query<Pagination>,
+    Query(filter): Query<UserFilter>,
+) -> Result<Json<PaginatedResponse<AdminUserResponse>>, AppError> {
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let role = filter.role.map(|role| role.as_str());
+    let status = filter.status.map(|status| match status {
+        UserStatus::Active => "active",
+        UserStatus::Suspended => "suspended",
+    });
+    let now = Utc::now();
+
+    let total: (i64,) = sqlx::query_as(&format!(
+        "SELECT COUNT(*) FROM users WHERE {USER_FILTER_SQL}"
+    ))
+    .bind(role)
+    .bind(status)
+    .bind(now)
+    .bind(&filter.q)
+    .fetch_one(&state.pool)
+    .await?;
+    let total = total.0 as u64;
+
+    let offset = (pagination.page - 1) * pagination.page_size;
+    let total_pages = total.div_ceil(pagination.page_size);
+
+    let users = sqlx::query_as::<_, User>(&format!(
+        "SELECT * FROM users WHERE {USER_FILTER_SQL} ORDER BY id LIMIT ?5 OFFSET ?6"
+    ))
+    .bind(role)
+    .bind(status)
+    .bind(now)
+    .bind(&filter.q)
+    .bind(pagination.page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let response = PaginatedResponse {
+        data: users.into_iter().map(Into::into).collect(),
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        total_pages,
+    };
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}",
+    params(("id" = u64, Path, description = "用户 ID")),
+    responses(
+        (status = 200, description = "根据 ID 获取用户", body = AdminUserResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = find_user(&state, id).await?;
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/role",
+    params(("id" = u64, Path, description = "用户 ID")),
+    request_body = UpdateUserRole,
+    responses(
+        (status = 200, description = "成功修改用户角色", body = AdminUserResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse),
+        (status = 409, description = "不能修改自己的角色", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn update_user_role(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UpdateUserRole>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = find_user(&state, id).await?;
+    ensure_not_self(&user, &claims, "不能修改自己的角色")?;
+
+    let user = sqlx::query_as::<_, User>("UPDATE users SET role = ? WHERE id = ? RETURNING *")
+        .bind(payload.role.as_str())
+        .bind(user.id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/suspend",
+    params(("id" = u64, Path, description = "用户 ID")),
+    request_body = SuspendUser,
+    responses(
+        (status = 200, description = "成功停用用户", body = AdminUserResponse),
+        (status = 400, description = "停用截止时间已过", body = ErrorResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse),
+        (status = 409, description = "不能停用自己", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn suspend_user(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<SuspendUser>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let now = Utc::now();
+
+    if payload.until.is_some_and(|until| until <= now) {
+        return Err(AppError::validation("停用截止时间必须晚于当前时间"));
+    }
+
+    let user = find_user(&state, id).await?;
+    ensure_not_self(&user, &claims, "不能停用自己")?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET suspended_at = ?, suspended_until = ?, suspension_reason = ? WHERE id = ? RETURNING *",
+    )
+    .bind(now)
+    .bind(payload.until)
+    .bind(&payload.reason)
+    .bind(user.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/unsuspend",
+    params(("id" = u64, Path, description = "用户 ID")),
+    responses(
+        (status = 200, description = "成功恢复用户", body = AdminUserResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn unsuspend_user(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET suspended_at = NULL, suspended_until = NULL, suspension_reason = NULL WHERE id = ? RETURNING *",
+    )
+    .bind(id as i64)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("用户未找到"))?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}/2fa",
+    params(("id" = u64, Path, description = "用户 ID")),
+    responses(
+        (status = 200, description = "成功关闭该用户的两步验证，用于用户丢失验证器和恢复码的情况", body = AdminUserResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn reset_user_two_factor(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(id as i64)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("用户未找到"))?;
+
+    clear_two_factor(&state, user.id).await?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user.id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/unlock",
+    params(("id" = u64, Path, description = "用户 ID")),
+    responses(
+        (status = 200, description = "成功解除因登录失败次数过多造成的锁定", body = AdminUserResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = find_user(&state, id).await?;
+    clear_login_failures(&state.pool, &user.username).await?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}",
+    params(("id" = u64, Path, description = "用户 ID")),
+    responses(
+        (status = 204, description = "成功删除用户及其文章和评论，其他用户对其评论的回复保留并上移一级"),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse),
+        (status = 409, description = "不能删除自己", body = ErrorResponse)
+    ),
+    tag = "Admin",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    let user = find_user(&state, id).await?;
+    ensure_not_self(&user, &claims, "不能删除自己")?;
+
+    // 文章和评论通过外键引用用户，需要先删除用户的内容
+    let mut tx = state.pool.begin().await?;
+
+    // 删除评论会级联删除它的回复，其他用户的回复改为挂到被删除评论的上一级
+    let threads: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT post_id FROM comments WHERE author_id = ?1 \
+         AND post_id NOT IN (SELECT id FROM posts WHERE author_id = ?1)",
+    )
+    .bind(user.id)
+    .fetch_all(&mut *tx)
+    .await?;
+    loop {
+        let result = sqlx::query(
+            "UPDATE comments \
+             SET parent_id = (SELECT p.parent_id FROM comments p WHERE p.comment_id = comments.parent_id) \
+             WHERE author_id != ?1 AND parent_id IN (SELECT comment_id FROM comments WHERE author_id = ?1)",
+        )
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            break;
+        }
+    }
+
+    // 举报和表情回应由触发器随内容一起删除
+    sqlx::query("DELETE FROM comments WHERE author_id = ?")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM posts WHERE author_id = ?")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM users WHERE id = ?")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    // 回复上移后重新计算嵌套层数
+    for (post_id,) in threads {
+        sqlx::query(
+            "WITH RECURSIVE thread(comment_id, depth) AS ( \
+                 SELECT comment_id, 0 FROM comments WHERE post_id = ?1 AND parent_id IS NULL \
+                 UNION ALL \
+                 SELECT c.comment_id, t.depth + 1 FROM comments c JOIN thread t ON c.parent_id = t.comment_id \
+             ) \
+             UPDATE comments SET depth = (SELECT depth FROM thread WHERE thread.comment_id = comments.comment_id) \
+             WHERE post_id = ?1",
+        )
+        .bind(post_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    check_delete_result(result, "User")
+}
+
+async fn find_user(state: &AppState, id: u64) -> Result<User, AppError> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(id as i64)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("用户未找到"))
+}
+
+/// 防止管理员对自己执行降级、停用或删除等操作
+fn ensure_not_self(user: &User, claims: &Claims, message: &str) -> Result<(), AppError> {
+    if user.username == claims.sub {
+        return Err(AppError::conflict(message));
+    }
+    Ok(())
+}
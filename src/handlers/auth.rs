@@ -1,49 +1,146 @@
-use crate::models::{Claims, LoginUser, RegisterUser, TokenResponse, User};
+use crate::handlers::{
+    access_tokens::{
+        ACCESS_TOKEN_PREFIX, access_token_claims, check_token_scope, revoke_user_access_tokens,
+    },
+    two_factor::create_login_challenge,
+};
+use crate::models::{
+    ChangePassword, Claims, ForgotPassword, LoginResponse, LoginUser, MeResponse, RefreshToken,
+    RefreshTokenRequest, RegisterUser, ResetPassword, TokenResponse, UpdateEmail, User,
+    VerifyEmail,
+};
 use crate::{
     errors::{AppError, ErrorResponse},
+    login_throttle::{begin_login_attempt, clear_login_failures, client_ip},
+    mail::Template,
     models::AppState,
-    utils::{hash_password, verify_password},
+    permissions::Permission,
+    utils::{
+        generate_token, hash_password, hash_token, password_needs_rehash, verify_login_password,
+        verify_password,
+    },
     validation::ValidatedJson,
 };
 use axum::{
-    Json,
-    extract::{Request, State},
-    http::{StatusCode, header},
+    Extension, Json,
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{HeaderMap, StatusCode, header},
     middleware::Next,
     response::{IntoResponse, Response},
 };
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use uuid::Uuid;
 
 pub async fn auth_middleware(
     State(state): State<AppState>,
     mut req: Request,
     next: Next,
 ) -> Response {
-    let token = req
-        .headers()
+    match authenticate(&state, req.headers()).await {
+        Ok(Some(claims)) => {
+            // 个人访问令牌只能访问授权范围内的路由
+            if let Some(scopes) = &claims.token_scopes {
+                let route = req
+                    .extensions()
+                    .get::<MatchedPath>()
+                    .map_or_else(|| req.uri().path(), MatchedPath::as_str);
+                if let Err(e) = check_token_scope(scopes, req.method(), route) {
+                    return e.into_response();
+                }
+            }
+            // 将解码的用户信息添加到请求扩展中，供后续处理器使用
+            req.extensions_mut().insert(claims);
+            next.run(req).await
+        }
+        Ok(None) => AppError::authentication("Missing Authorization header").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// 用于公开路由的认证中间件：携带有效令牌时注入用户信息，否则按匿名访问处理
+pub async fn optional_auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if let Ok(Some(claims)) = authenticate(&state, req.headers()).await {
+        req.extensions_mut().insert(claims);
+    }
+    next.run(req).await
+}
+
+/// 校验请求中的 JWT 或个人访问令牌，未携带令牌时返回 `Ok(None)`
+async fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<Option<Claims>, AppError> {
+    let token = headers
         .get(header::AUTHORIZATION)
         .and_then(|auth_header| auth_header.to_str().ok())
         .and_then(|auth_value| auth_value.strip_prefix("Bearer "));
 
-    let token = match token {
-        Some(token) => token,
-        None => {
-            return AppError::authentication("Missing Authorization header").into_response();
-        }
+    let Some(token) = token else {
+        return Ok(None);
     };
 
-    let decoding_key = DecodingKey::from_secret(state.config.jwt_secret.as_ref());
+    let mut claims = if token.starts_with(ACCESS_TOKEN_PREFIX) {
+        access_token_claims(state, token).await?
+    } else {
+        let decoding_key = DecodingKey::from_secret(state.config.jwt_secret.as_ref());
+
+        let token_data = decode::<Claims>(token, &decoding_key, &Validation::default())
+            .map_err(|_| AppError::authentication("Invalid or expired token"))?;
 
-    let token_data = match decode::<Claims>(token, &decoding_key, &Validation::default()) {
-        Ok(data) => data,
-        Err(_) => {
-            return AppError::authentication("Invalid or expired token").into_response();
+        // 已注销的访问令牌在过期前同样需要拒绝
+        let revoked = sqlx::query("SELECT 1 FROM revoked_tokens WHERE jti = ?")
+            .bind(&token_data.claims.jti)
+            .fetch_optional(&state.pool)
+            .await?;
+        if revoked.is_some() {
+            return Err(AppError::authentication("Token has been revoked"));
         }
+        token_data.claims
     };
 
-    // 将解码的用户信息添加到请求扩展中，供后续处理器使用
-    req.extensions_mut().insert(token_data.claims);
+    // 每次请求都重新读取用户，使停用和角色变更立即生效
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::authentication("User no longer exists"))?;
+
+    if user.is_suspended() {
+        return Err(AppError::authorization("账户已被停用"));
+    }
+    // 注销全部会话或修改密码之前签发的访问令牌，包括刷新令牌轮换前签发的，都已失效；
+    // 个人访问令牌不属于会话，修改或重置密码时单独吊销
+    let issued_before_revocation = user
+        .tokens_valid_after
+        .is_some_and(|valid_after| claims.iat < Claims::timestamp(valid_after));
+    if claims.token_scopes.is_none() && issued_before_revocation {
+        return Err(AppError::authentication("Token has been revoked"));
+    }
+    claims.two_factor_pending = user.totp_enabled_at.is_none()
+        && user
+            .role
+            .parse()
+            .is_ok_and(|role| state.config.two_factor_required_roles.contains(&role));
+    claims.role = user.role;
+
+    Ok(Some(claims))
+}
+
+/// 检查当前用户是否拥有指定权限，必须放在 `auth_middleware` 之后使用
+pub async fn require_permission(permission: Permission, req: Request, next: Next) -> Response {
+    let claims = req.extensions().get::<Claims>();
+
+    if !claims.is_some_and(|claims| claims.has_permission(permission)) {
+        // 角色本身拥有该权限，只是还没有按要求启用两步验证
+        if claims.is_some_and(|claims| claims.role().has_permission(permission)) {
+            return AppError::authorization("该角色需要先启用两步验证").into_response();
+        }
+        return AppError::authorization("权限不足").into_response();
+    }
 
     next.run(req).await
 }
@@ -54,7 +151,7 @@ pub async fn auth_middleware(
     request_body = RegisterUser,
     responses(
         (status = 201, description = "用户注册成功"),
-        (status = 409, description = "用户名已存在", body = ErrorResponse),
+        (status = 409, description = "用户名已存在或邮箱已被其他用户验证", body = ErrorResponse),
         (status = 500, description = "内部服务器错误", body = ErrorResponse)
     ),
     tag = "Auth"
@@ -64,17 +161,36 @@ pub async fn register(
     json_payload: Json<RegisterUser>,
 ) -> Result<StatusCode, AppError> {
     let payload = json_payload.validate_json()?;
-    // 使用 bcrypt 哈希密码，使用 spawn_blocking 在单独线程中执行以避免阻塞异步运行时
-    let password_hash = hash_password(&payload.password).await?;
+    if let Some(email) = payload.email.as_deref().map(normalize_email) {
+        ensure_email_available(&state, &email).await?;
+    }
+    // 按配置的算法哈希密码，使用 spawn_blocking 在单独线程中执行以避免阻塞异步运行时
+    let password_hash = hash_password(&state.config, &payload.password).await?;
 
-    let result = sqlx::query("INSERT INTO users (username, password_hash) VALUES (?, ?)")
-        .bind(&payload.username)
-        .bind(&password_hash)
-        .execute(&state.pool)
-        .await;
+    let result = sqlx::query(
+        "INSERT INTO users (username, password_hash, role, created_at, email) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&payload.username)
+    .bind(&password_hash)
+    .bind(state.config.default_user_role.as_str())
+    .bind(Utc::now())
+    .bind(payload.email.as_deref().map(normalize_email))
+    .execute(&state.pool)
+    .await;
 
     match result {
-        Ok(_) => Ok(StatusCode::CREATED),
+        Ok(result) => {
+            if let Some(email) = payload.email.as_deref().map(normalize_email) {
+                try_send_verification_email(
+                    &state,
+                    result.last_insert_rowid(),
+                    &payload.username,
+                    &email,
+                )
+                .await;
+            }
+            Ok(StatusCode::CREATED)
+        }
         Err(e) => {
             if let Some(db_err) = e.as_database_error()
                 && db_err.is_unique_violation()
@@ -91,36 +207,618 @@ pub async fn register(
     path = "/login",
     request_body = LoginUser,
     responses(
-        (status = 200, description = "用户登录成功", body = TokenResponse),
+        (status = 200, description = "用户登录成功；启用了两步验证的用户返回登录挑战，需要再调用 /login/2fa", body = LoginResponse),
         (status = 401, description = "用户名或密码错误", body = ErrorResponse),
+        (status = 403, description = "账户已被停用", body = ErrorResponse),
+        (status = 429, description = "登录失败次数过多，用户名或客户端地址被临时锁定", body = ErrorResponse),
         (status = 500, description = "内部服务器错误", body = ErrorResponse)
     ),
     tag = "Auth"
 )]
 pub async fn login(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     json_payload: Json<LoginUser>,
-) -> Result<Json<TokenResponse>, AppError> {
+) -> Result<Json<LoginResponse>, AppError> {
     let payload = json_payload.validate_json()?;
-    // 1. 根据用户名从数据库查找用户
+    let ip = client_ip(&state.config, peer, &headers);
+
+    // 1. 锁定期内不再验证密码，即使密码正确也拒绝；否则先把本次尝试计为失败
+    let attempt = begin_login_attempt(&state.pool, &state.config, &payload.username, ip).await?;
+
+    // 2. 根据用户名从数据库查找用户
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
         .bind(&payload.username)
         .fetch_optional(&state.pool) // fetch_optional 返回 Option<User>
+        .await?;
+
+    // 3. 验证密码 - 使用 spawn_blocking 避免阻塞异步运行时；用户不存在时也要花同样的时间
+    let password_valid = verify_login_password(
+        &state.config,
+        &payload.password,
+        user.as_ref().map(|user| user.password_hash.as_str()),
+    )
+    .await?;
+    let user = match user {
+        Some(user) if password_valid => user,
+        _ => return Err(AppError::authentication("用户名或密码错误")),
+    };
+
+    // 旧算法或旧参数的哈希在密码验证通过后按当前配置重新计算
+    if password_needs_rehash(&state.config, &user.password_hash) {
+        let password_hash = hash_password(&state.config, &payload.password).await?;
+        // 只在哈希没有被同时修改时更新，避免覆盖刚修改的密码
+        sqlx::query("UPDATE users SET password_hash = ? WHERE id = ? AND password_hash = ?")
+            .bind(&password_hash)
+            .bind(user.id)
+            .bind(&user.password_hash)
+            .execute(&state.pool)
+            .await?;
+        tracing::info!(user_id = user.id, "已按当前配置重新哈希密码");
+    }
+
+    if user.is_suspended() {
+        attempt.succeed(&state.pool).await?;
+        return Err(AppError::authorization(suspension_message(&user)));
+    }
+
+    // 4. 启用了两步验证时先签发登录挑战，提交验证码后才签发令牌
+    if user.totp_enabled_at.is_some() {
+        attempt.release(&state.pool).await?;
+        let challenge = create_login_challenge(&state, &user).await?;
+        return Ok(Json(LoginResponse::TwoFactor(challenge)));
+    }
+
+    // 5. 签发访问令牌和刷新令牌
+    attempt.succeed(&state.pool).await?;
+    let tokens = issue_tokens(&state, &user).await?;
+
+    Ok(Json(LoginResponse::Tokens(tokens)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/token/refresh",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "成功换取新的令牌对", body = TokenResponse),
+        (status = 401, description = "刷新令牌无效、已过期或已被吊销", body = ErrorResponse),
+        (status = 403, description = "账户已被停用", body = ErrorResponse)
+    ),
+    tag = "Auth"
+)]
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    json_payload: Json<RefreshTokenRequest>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+
+    let stored: RefreshToken = sqlx::query_as("SELECT * FROM refresh_tokens WHERE token_hash = ?")
+        .bind(hash_token(&payload.refresh_token))
+        .fetch_optional(&state.pool)
         .await?
-        .ok_or_else(|| AppError::authentication("用户名或密码错误"))?;
+        .ok_or_else(|| AppError::authentication("无效的刷新令牌"))?;
+
+    // 已轮换过的刷新令牌被再次使用，说明令牌可能已泄露，吊销该用户的全部会话
+    if stored.revoked_at.is_some() {
+        revoke_all_sessions(&state, stored.user_id).await?;
+        return Err(AppError::authentication("刷新令牌已被吊销"));
+    }
+
+    if stored.expires_at <= Utc::now() {
+        return Err(AppError::authentication("刷新令牌已过期"));
+    }
+
+    // 先作废旧令牌再签发新令牌，并发请求中只有一个能够成功
+    let result =
+        sqlx::query("UPDATE refresh_tokens SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(stored.id)
+            .execute(&state.pool)
+            .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::authentication("刷新令牌已被吊销"));
+    }
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = ?")
+        .bind(stored.user_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    if user.is_suspended() {
+        return Err(AppError::authorization(suspension_message(&user)));
+    }
+
+    let tokens = issue_tokens(&state, &user).await?;
+
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 204, description = "成功注销当前会话"),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<RefreshTokenRequest>,
+) -> Result<StatusCode, AppError> {
+    let payload = json_payload.validate_json()?;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = ? WHERE token_hash = ? AND user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(hash_token(&payload.refresh_token))
+    .bind(user.id)
+    .execute(&state.pool)
+    .await?;
+
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    revoke_access_token(&state.pool, &claims.jti, expires_at).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout/all",
+    responses(
+        (status = 204, description = "成功注销该用户的所有会话，个人访问令牌不受影响，需要单独吊销"),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+
+    revoke_all_sessions(&state, user.id).await?;
+
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    revoke_access_token(&state.pool, &claims.jti, expires_at).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/password",
+    request_body = ChangePassword,
+    responses(
+        (status = 200, description = "成功修改密码，其他会话和个人访问令牌全部失效，返回新的令牌对", body = TokenResponse),
+        (status = 400, description = "当前密码错误或新密码无效", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<ChangePassword>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+
+    if !verify_password(&payload.current_password, &user.password_hash).await? {
+        return Err(AppError::validation("当前密码错误"));
+    }
+
+    let password_hash = hash_password(&state.config, &payload.new_password).await?;
+    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+        .bind(&password_hash)
+        .bind(user.id)
+        .execute(&state.pool)
+        .await?;
+
+    // 密码可能已经泄露，吊销包括当前会话在内的所有会话和个人访问令牌，再为当前客户端签发新的令牌
+    revoke_all_sessions(&state, user.id).await?;
+    revoke_user_access_tokens(&state.pool, user.id).await?;
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    revoke_access_token(&state.pool, &claims.jti, expires_at).await?;
+
+    let tokens = issue_tokens(&state, &user).await?;
+
+    Ok(Json(tokens))
+}
 
-    // 2. 验证密码 - 使用 spawn_blocking 避免阻塞异步运行时
-    let password_valid = verify_password(&payload.password, &user.password_hash).await?;
+#[utoipa::path(
+    post,
+    path = "/password/forgot",
+    request_body = ForgotPassword,
+    responses(
+        (status = 202, description = "如果邮箱属于某个用户，重置链接将在后台发送到该邮箱")
+    ),
+    tag = "Auth"
+)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    json_payload: Json<ForgotPassword>,
+) -> Result<StatusCode, AppError> {
+    let payload = json_payload.validate_json()?;
+
+    // 无论邮箱是否存在都返回相同的结果，避免借此探测注册的邮箱；
+    // 未验证的邮箱可能属于其他人，不发送重置链接
+    let user: Option<User> =
+        sqlx::query_as("SELECT * FROM users WHERE email = ? AND email_verified_at IS NOT NULL")
+            .bind(normalize_email(&payload.email))
+            .fetch_optional(&state.pool)
+            .await?;
+    let Some(user) = user else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    let Some(email) = user.email.clone() else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+
+    // 生成令牌和投递邮件都在后台完成，邮箱是否存在时响应的耗时相同
+    tokio::spawn(async move {
+        if let Err(e) = send_password_reset_email(&state, &user, &email).await {
+            tracing::error!(error = ?e, user_id = user.id, "发送密码重置邮件失败");
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/password/reset",
+    request_body = ResetPassword,
+    responses(
+        (status = 204, description = "成功重置密码，该用户的所有会话和个人访问令牌失效"),
+        (status = 400, description = "重置令牌无效、已使用或已过期", body = ErrorResponse)
+    ),
+    tag = "Auth"
+)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    json_payload: Json<ResetPassword>,
+) -> Result<StatusCode, AppError> {
+    let payload = json_payload.validate_json()?;
+    let password_hash = hash_password(&state.config, &payload.new_password).await?;
+
+    // 在同一条语句中检查并作废令牌，并发请求中只有一个能够成功
+    let user_id: (i64,) = sqlx::query_as(
+        "UPDATE password_reset_tokens SET used_at = ?1 \
+         WHERE token_hash = ?2 AND used_at IS NULL AND expires_at > ?1 RETURNING user_id",
+    )
+    .bind(Utc::now())
+    .bind(hash_token(&payload.token))
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::validation("重置链接无效或已过期"))?;
+
+    let username: (String,) =
+        sqlx::query_as("UPDATE users SET password_hash = ? WHERE id = ? RETURNING username")
+            .bind(&password_hash)
+            .bind(user_id.0)
+            .fetch_one(&state.pool)
+            .await?;
+
+    revoke_all_sessions(&state, user_id.0).await?;
+    revoke_user_access_tokens(&state.pool, user_id.0).await?;
+    // 能够收到重置邮件说明是账户的主人，解除因他人猜测密码造成的锁定
+    clear_login_failures(&state.pool, &username.0).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/email",
+    request_body = UpdateEmail,
+    responses(
+        (status = 200, description = "成功修改邮箱，新邮箱需要重新验证", body = MeResponse),
+        (status = 400, description = "邮箱格式不正确", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse),
+        (status = 409, description = "邮箱已被其他用户验证", body = ErrorResponse)
+    ),
+    tag = "Auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn update_email(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<UpdateEmail>,
+) -> Result<Json<MeResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+    let email = payload.email.as_deref().map(normalize_email);
+
+    // 邮箱没有变化时保留验证状态，也不重复发送验证邮件
+    if email == user.email {
+        return Ok(Json(user.into()));
+    }
+    if let Some(email) = &email {
+        ensure_email_available(&state, email).await?;
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET email = ?, email_verified_at = NULL WHERE id = ? RETURNING *",
+    )
+    .bind(&email)
+    .bind(user.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    // 旧邮箱的验证链接和重置链接全部作废
+    sqlx::query("DELETE FROM email_verification_tokens WHERE user_id = ? AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&state.pool)
+        .await?;
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = ? AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&state.pool)
+        .await?;
+
+    if let Some(email) = &user.email {
+        try_send_verification_email(&state, user.id, &user.username, email).await;
+    }
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/email/verification",
+    responses(
+        (status = 202, description = "验证邮件已重新发送"),
+        (status = 400, description = "没有填写邮箱或邮箱已经验证", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+    let Some(email) = &user.email else {
+        return Err(AppError::validation("尚未填写邮箱"));
+    };
+    if user.email_verified_at.is_some() {
+        return Err(AppError::validation("邮箱已经验证"));
+    }
+
+    send_verification_email(&state, user.id, &user.username, email).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/email/verify",
+    request_body = VerifyEmail,
+    responses(
+        (status = 204, description = "邮箱验证成功"),
+        (status = 400, description = "验证令牌无效、已使用或已过期", body = ErrorResponse),
+        (status = 409, description = "邮箱已被其他用户验证", body = ErrorResponse)
+    ),
+    tag = "Auth"
+)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    json_payload: Json<VerifyEmail>,
+) -> Result<StatusCode, AppError> {
+    let payload = json_payload.validate_json()?;
+    let now = Utc::now();
 
-    if !password_valid {
-        return Err(AppError::authentication("用户名或密码错误"));
+    let mut tx = state.pool.begin().await?;
+    let token: Option<(i64, String)> = sqlx::query_as(
+        "UPDATE email_verification_tokens SET used_at = ?1 \
+         WHERE token_hash = ?2 AND used_at IS NULL AND expires_at > ?1 RETURNING user_id, email",
+    )
+    .bind(now)
+    .bind(hash_token(&payload.token))
+    .fetch_optional(&mut *tx)
+    .await?;
+    let (user_id, email) = token.ok_or_else(|| AppError::validation("验证链接无效或已过期"))?;
+
+    // 签发后用户可能已经更换了邮箱，此时旧链接不能验证新邮箱
+    let result = sqlx::query(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, ?) \
+         WHERE id = ? AND email = ?",
+    )
+    .bind(now)
+    .bind(user_id)
+    .bind(&email)
+    .execute(&mut *tx)
+    .await;
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            // 其他用户已经先验证了这个邮箱
+            if let Some(db_err) = e.as_database_error()
+                && db_err.is_unique_violation()
+            {
+                return Err(AppError::conflict("邮箱已被使用"));
+            }
+            return Err(e.into());
+        }
+    };
+    if result.rows_affected() == 0 {
+        return Err(AppError::validation("验证链接无效或已过期"));
     }
 
-    // 3. 生成 JWT
+    // 其他用户未验证的同一个邮箱作废，之前发给他们的验证链接也随之失效
+    sqlx::query(
+        "UPDATE users SET email = NULL WHERE email = ? AND id <> ? AND email_verified_at IS NULL",
+    )
+    .bind(&email)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM email_verification_tokens WHERE email = ? AND user_id <> ? AND used_at IS NULL")
+        .bind(&email)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 注册或修改邮箱后发送验证邮件
+///
+/// 此时邮箱已经保存，请求本身已经成功；发送失败只记录日志，用户可以通过
+/// `POST /me/email/verification` 重新发送。
+async fn try_send_verification_email(state: &AppState, user_id: i64, username: &str, email: &str) {
+    if let Err(e) = send_verification_email(state, user_id, username, email).await {
+        tracing::error!(error = ?e, user_id, "发送验证邮件失败，用户可以重新发送");
+    }
+}
+
+/// 为用户当前的邮箱签发验证令牌并发送验证邮件，之前发出的验证链接失效
+async fn send_verification_email(
+    state: &AppState,
+    user_id: i64,
+    username: &str,
+    email: &str,
+) -> Result<(), AppError> {
+    let token = generate_token();
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("DELETE FROM email_verification_tokens WHERE user_id = ? AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO email_verification_tokens (user_id, email, token_hash, expires_at) \
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(email)
+    .bind(hash_token(&token))
+    .bind(Utc::now() + Duration::hours(state.config.email_verification_expiration_hours))
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let link = format!("{}/verify-email?token={}", state.config.public_url, token);
+    let message = Template::VerifyEmail {
+        username,
+        link: &link,
+        expires_in_hours: state.config.email_verification_expiration_hours,
+    }
+    .render(email);
+    state.mailer.send(&state.pool, message).await?;
+    Ok(())
+}
+
+/// 为用户签发密码重置令牌并发送重置邮件，之前发出的重置链接失效
+async fn send_password_reset_email(
+    state: &AppState,
+    user: &User,
+    email: &str,
+) -> Result<(), AppError> {
+    let token = generate_token();
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = ? AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES (?, ?, ?)",
+    )
+    .bind(user.id)
+    .bind(hash_token(&token))
+    .bind(Utc::now() + Duration::minutes(state.config.password_reset_expiration_minutes))
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let link = format!("{}/reset-password?token={}", state.config.public_url, token);
+    let message = Template::PasswordReset {
+        username: &user.username,
+        link: &link,
+        expires_in_minutes: state.config.password_reset_expiration_minutes,
+    }
+    .render(email);
+    state.mailer.send(&state.pool, message).await?;
+    Ok(())
+}
+
+/// 邮箱已被其他用户验证时返回 409；未验证的邮箱不影响其他用户使用
+async fn ensure_email_available(state: &AppState, email: &str) -> Result<(), AppError> {
+    let taken: Option<(i64,)> =
+        sqlx::query_as("SELECT id FROM users WHERE email = ? AND email_verified_at IS NOT NULL")
+            .bind(email)
+            .fetch_optional(&state.pool)
+            .await?;
+    if taken.is_some() {
+        return Err(AppError::conflict("邮箱已被使用"));
+    }
+    Ok(())
+}
+
+/// 邮箱不区分大小写，统一保存为小写
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// 生成包含停用原因和截止时间的提示信息
+pub(crate) fn suspension_message(user: &User) -> String {
+    let reason = user.suspension_reason.as_deref().unwrap_or("未说明原因");
+    match user.suspended_until {
+        Some(until) => format!("账户已被停用至 {}: {}", until.to_rfc3339(), reason),
+        None => format!("账户已被永久封禁: {}", reason),
+    }
+}
+
+/// 为用户签发新的访问令牌，并持久化与之配对的刷新令牌
+pub(crate) async fn issue_tokens(state: &AppState, user: &User) -> Result<TokenResponse, AppError> {
+    let now = Utc::now();
+    let access_ttl = Duration::minutes(state.config.access_token_expiration_minutes);
+    let jti = Uuid::new_v4().to_string();
+
     let claims = Claims {
-        sub: user.username,
-        role: user.role,
-        exp: (Utc::now() + Duration::days(state.config.jwt_expiration_days)).timestamp() as usize,
+        sub: user.username.clone(),
+        role: user.role.clone(),
+        exp: (now + access_ttl).timestamp() as usize,
+        jti: jti.clone(),
+        iat: Claims::timestamp(now),
+        two_factor_pending: false,
+        token_scopes: None,
     };
 
     let token = encode(
@@ -129,5 +827,61 @@ pub async fn login(
         &EncodingKey::from_secret(state.config.jwt_secret.as_ref()),
     )?;
 
-    Ok(Json(TokenResponse { token }))
+    let refresh_token = generate_token();
+    sqlx::query(
+        "INSERT INTO refresh_tokens (user_id, token_hash, access_jti, expires_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(user.id)
+    .bind(hash_token(&refresh_token))
+    .bind(&jti)
+    .bind(now + Duration::days(state.config.refresh_token_expiration_days))
+    .execute(&state.pool)
+    .await?;
+
+    Ok(TokenResponse {
+        token,
+        refresh_token,
+        expires_in: access_ttl.num_seconds(),
+    })
+}
+
+/// 吊销用户所有未失效的刷新令牌，并使此前签发的访问令牌全部失效
+async fn revoke_all_sessions(state: &AppState, user_id: i64) -> Result<(), AppError> {
+    let now = Utc::now();
+
+    sqlx::query("UPDATE users SET tokens_valid_after = ? WHERE id = ?")
+        .bind(now)
+        .bind(user_id)
+        .execute(&state.pool)
+        .await?;
+
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(now)
+    .bind(user_id)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 将访问令牌加入黑名单，并顺便清理已经过期的条目
+async fn revoke_access_token(
+    pool: &SqlitePool,
+    jti: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < ?")
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+    sqlx::query("INSERT OR IGNORE INTO revoked_tokens (jti, expires_at) VALUES (?, ?)")
+        .bind(jti)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+
+    Ok(())
 }
@@ -0,0 +1,152 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    handlers::posts::{POST_RESPONSE_SELECT, can_view, find_post_response},
+    models::{AppState, Claims, PaginatedResponse, Pagination, PostResponse, User},
+    permissions::Permission,
+    utils::check_delete_result,
+    validation::format_validation_errors,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use validator::Validate;
+
+/// 收藏列表中仍需显示的文章，参数 ?1 为用户 ID，?2 表示用户能否查看所有人的文章
+///
+/// 已删除的文章不再显示；未发布或被隐藏的文章只有作者本人和编辑可见。
+const BOOKMARK_FILTER_SQL: &str = "b.user_id = ?1 AND p.deleted_at IS NULL \
+    AND ((p.status = 'published' AND p.hidden_at IS NULL) OR p.author_id = ?1 OR ?2)";
+
+#[utoipa::path(
+    put,
+    path = "/posts/{id}/bookmark",
+    params(("id" = u64, Path, description = "文章 ID")),
+    responses(
+        (status = 204, description = "成功收藏文章，重复收藏不做改动"),
+        (status = 404, description = "未找到文章", body = ErrorResponse)
+    ),
+    tag = "Bookmarks",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn add_bookmark(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    let post = find_post_response(&state, id as i64).await?;
+    if !can_view(&post, Some(&claims)) {
+        return Err(AppError::not_found("文章未找到"));
+    }
+
+    let user = find_user(&state, &claims).await?;
+    sqlx::query("INSERT INTO bookmarks (user_id, post_id) VALUES (?, ?) ON CONFLICT DO NOTHING")
+        .bind(user.id)
+        .bind(post.id)
+        .execute(&state.pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/posts/{id}/bookmark",
+    params(("id" = u64, Path, description = "文章 ID")),
+    responses(
+        (status = 204, description = "成功取消收藏"),
+        (status = 404, description = "没有收藏该文章", body = ErrorResponse)
+    ),
+    tag = "Bookmarks",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn remove_bookmark(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    let user = find_user(&state, &claims).await?;
+    let result = sqlx::query("DELETE FROM bookmarks WHERE user_id = ? AND post_id = ?")
+        .bind(user.id)
+        .bind(id as i64)
+        .execute(&state.pool)
+        .await?;
+
+    check_delete_result(result, "Bookmark")
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/bookmarks",
+    params(
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页数量")
+    ),
+    responses(
+        (status = 200, description = "分页列出当前用户收藏的文章，最近收藏的在前；已删除的文章不再列出", body = PaginatedResponse<PostResponse>),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Bookmarks",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_bookmarks(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<PaginatedResponse<PostResponse>>, AppError> {
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let user = find_user(&state, &claims).await?;
+    let view_all = claims.has_permission(Permission::ManageAnyPost);
+
+    let total: (i64,) = sqlx::query_as(&format!(
+        "SELECT COUNT(*) FROM bookmarks b JOIN posts p ON p.id = b.post_id WHERE {BOOKMARK_FILTER_SQL}"
+    ))
+    .bind(user.id)
+    .bind(view_all)
+    .fetch_one(&state.pool)
+    .await?;
+    let total = total.0 as u64;
+
+    let offset = (pagination.page - 1) * pagination.page_size;
+    let total_pages = total.div_ceil(pagination.page_size);
+
+    let posts = sqlx::query_as::<_, PostResponse>(&format!(
+        "{POST_RESPONSE_SELECT} JOIN bookmarks b ON b.post_id = p.id \
+         WHERE {BOOKMARK_FILTER_SQL} ORDER BY b.created_at DESC, b.rowid DESC LIMIT ?3 OFFSET ?4"
+    ))
+    .bind(user.id)
+    .bind(view_all)
+    .bind(pagination.page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(PaginatedResponse {
+        data: posts,
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        total_pages,
+    }))
+}
+
+async fn find_user(state: &AppState, claims: &Claims) -> Result<User, AppError> {
+    let user = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+    Ok(user)
+}
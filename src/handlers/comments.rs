@@ -1,37 +1,156 @@
 use crate::{
     errors::{AppError, ErrorResponse},
-    models::{AppState, Claims, Comment, CommentResponse, CreateComment, User},
+    handlers::posts::{can_view, find_post_response},
+    models::{
+        AppState, Claims, Comment, CommentQuery, CommentResponse, CommentSort, CommentStatus,
+        CreateComment, ModerateComments, ModerationFilter, ModerationMode, ModerationResult,
+        PaginatedResponse, Pagination, Post, User,
+    },
+    permissions::Permission,
+    search::index_comment,
+    spam::{SpamInput, SpamLabel, content_hash, train_comment, untrain_comment},
     utils::{check_delete_result, created_response},
-    validation::ValidatedJson,
+    validation::{ValidatedJson, format_validation_errors},
 };
 use axum::{
     Extension, Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
 use chrono::Utc;
+use std::collections::HashMap;
+use validator::Validate;
+
+/// 回复的最大嵌套层数，顶层评论为第 0 层
+const MAX_REPLY_DEPTH: i64 = 5;
+
+/// 已删除评论在回复树中的占位文本
+const DELETED_PLACEHOLDER: &str = "[deleted]";
+
+/// 评论响应的字段，需要连接 `comments c` 和 `users u`，表情回应数量以 JSON 对象的形式取出
+const COMMENT_RESPONSE_COLUMNS: &str = "c.comment_id as id, c.post_id, c.parent_id, c.depth, \
+    c.deleted_at IS NOT NULL as deleted, c.status, u.username as author, c.content, c.created_at, \
+    (SELECT json_group_object(kind, n) FROM (SELECT kind, COUNT(*) AS n FROM reactions WHERE target_type = 'comment' AND target_id = c.comment_id GROUP BY kind)) AS reactions";
+
+/// 审核队列的筛选条件，参数依次为审核状态、文章 ID
+const MODERATION_FILTER_SQL: &str =
+    "c.deleted_at IS NULL AND c.status = ?1 AND (?2 IS NULL OR c.post_id = ?2)";
+
+/// 文章下仍需显示的顶层评论 (讨论串)，参数 ?1 为文章 ID，?2 为当前用户名
+///
+/// 已通过审核的评论所有人可见，等待审核的评论只有作者本人可见，因举报被隐藏的评论所有人都不可见，
+/// 不可见评论的回复也一并隐藏。
+/// 已删除且所有回复也已删除的讨论串不显示。`replies` 为未删除的回复数量，`reactions` 为顶层评论的表情回应数量。
+const THREAD_ROOTS_SQL: &str = "\
+    WITH RECURSIVE thread(root_id, comment_id, deleted) AS ( \
+        SELECT comment_id, comment_id, deleted_at IS NOT NULL FROM comments c \
+        WHERE post_id = ?1 AND parent_id IS NULL AND c.hidden_at IS NULL \
+          AND (c.status = 'approved' OR (c.status = 'pending' AND c.author_id = (SELECT id FROM users WHERE username = ?2))) \
+        UNION ALL \
+        SELECT t.root_id, c.comment_id, c.deleted_at IS NOT NULL FROM comments c JOIN thread t ON c.parent_id = t.comment_id \
+        WHERE c.hidden_at IS NULL \
+          AND (c.status = 'approved' OR (c.status = 'pending' AND c.author_id = (SELECT id FROM users WHERE username = ?2))) \
+    ) \
+    SELECT root_id, SUM(NOT deleted AND comment_id <> root_id) AS replies, \
+        (SELECT COUNT(*) FROM reactions WHERE target_type = 'comment' AND target_id = root_id) AS reactions FROM thread \
+    GROUP BY root_id HAVING SUM(NOT deleted) > 0";
 
 #[utoipa::path(
     get,
     path = "/posts/{id}/comments",
-    params(("id" = u64, Path, description = "文章 ID")),
+    params(
+        ("id" = u64, Path, description = "文章 ID"),
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页的顶层评论数量，每条顶层评论的回复随其一起返回"),
+        ("sort" = Option<String>, Query, description = "顶层评论的排序方式: oldest (默认)、newest 或 top")
+    ),
     responses(
-        (status = 200, description = "列出文章的所有评论", body = [CommentResponse])
+        (status = 200, description = "分页列出顶层评论，每条评论后按深度优先紧跟其回复；当前用户等待审核的评论也包含在内", body = PaginatedResponse<CommentResponse>),
+        (status = 404, description = "未找到文章，或文章未发布且当前用户无权查看", body = ErrorResponse)
     ),
     tag = "Comments"
 )]
 pub async fn get_comments_for_post(
     State(state): State<AppState>,
     Path(id): Path<u64>,
-) -> Result<Json<Vec<CommentResponse>>, AppError> {
-    let comments = sqlx::query_as::<_, CommentResponse>(
-        "SELECT c.comment_id as id, c.post_id, u.username as author, c.content, c.created_at FROM comments c JOIN users u ON c.author_id = u.id WHERE c.post_id = ? AND c.deleted_at IS NULL",
-    )
+    claims: Option<Extension<Claims>>,
+    Query(pagination): Query<Pagination>,
+    Query(query): Query<CommentQuery>,
+) -> Result<Json<PaginatedResponse<CommentResponse>>, AppError> {
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    // 评论跟随文章的可见性，看不到文章的用户也看不到它的评论
+    let post = find_post_response(&state, id as i64).await?;
+    if !can_view(&post, claims.as_ref().map(|Extension(claims)| claims)) {
+        return Err(AppError::not_found("文章未找到"));
+    }
+
+    let viewer = claims.as_ref().map(|Extension(claims)| claims.sub.as_str());
+
+    let total: (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM ({THREAD_ROOTS_SQL})"))
         .bind(id as i64)
-        .fetch_all(&state.pool)
+        .bind(viewer)
+        .fetch_one(&state.pool)
         .await?;
-    Ok(Json(comments))
+    let total = total.0 as u64;
+
+    let offset = (pagination.page - 1) * pagination.page_size;
+    let total_pages = total.div_ceil(pagination.page_size);
+
+    let order = match query.sort {
+        CommentSort::Oldest => "root_id",
+        CommentSort::Newest => "root_id DESC",
+        CommentSort::Top => "reactions DESC, replies DESC, root_id",
+    };
+    let root_ids: Vec<i64> = sqlx::query_scalar(&format!(
+        "SELECT root_id FROM ({THREAD_ROOTS_SQL}) ORDER BY {order} LIMIT ?3 OFFSET ?4"
+    ))
+    .bind(id as i64)
+    .bind(viewer)
+    .bind(pagination.page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(&state.pool)
+    .await?;
+
+    // 取出这一页讨论串中的全部评论，已删除的也要取出，以便保留其下仍可见的回复
+    let mut comments = sqlx::query_as::<_, CommentResponse>(&format!(
+        "WITH RECURSIVE thread(comment_id) AS ( \
+             SELECT value FROM json_each(?1) \
+             UNION ALL \
+             SELECT c.comment_id FROM comments c JOIN thread t ON c.parent_id = t.comment_id \
+             WHERE c.hidden_at IS NULL \
+               AND (c.status = 'approved' OR (c.status = 'pending' AND c.author_id = (SELECT id FROM users WHERE username = ?2))) \
+         ) \
+         SELECT {COMMENT_RESPONSE_COLUMNS} \
+         FROM thread t JOIN comments c ON c.comment_id = t.comment_id JOIN users u ON c.author_id = u.id \
+         ORDER BY c.comment_id"
+    ))
+    .bind(serde_json::to_string(&root_ids).unwrap_or_default())
+    .bind(viewer)
+    .fetch_all(&state.pool)
+    .await?;
+
+    // 顶层评论按本页的排序排列，回复仍按发表先后排列
+    comments.sort_by_key(|comment| match comment.parent_id {
+        None => root_ids.iter().position(|root_id| *root_id == comment.id),
+        Some(_) => None,
+    });
+
+    let response = PaginatedResponse {
+        data: flatten_thread(comments),
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        total_pages,
+    };
+
+    Ok(Json(response))
 }
 
 #[utoipa::path(
@@ -40,7 +159,9 @@ pub async fn get_comments_for_post(
     params(("id" = u64, Path, description = "文章 ID")),
     request_body = CreateComment,
     responses(
-        (status = 201, description = "成功创建评论", body = CommentResponse)
+        (status = 201, description = "成功创建评论，需要审核时状态为 pending", body = CommentResponse),
+        (status = 400, description = "回复的嵌套层数超过限制", body = ErrorResponse),
+        (status = 404, description = "未找到文章或要回复的评论，或文章未发布", body = ErrorResponse)
     ),
     tag = "Comments",
     security(
@@ -58,14 +179,57 @@ pub async fn create_comment_for_post(
         .bind(&claims.sub)
         .fetch_one(&state.pool)
         .await?;
+
+    // 只能评论已发布的文章
+    let post: Post = sqlx::query_as(
+        "SELECT * FROM posts WHERE id = ? AND deleted_at IS NULL AND hidden_at IS NULL AND status = 'published'",
+    )
+    .bind(post_id as i64)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("文章未找到"))?;
+
+    // 只能回复同一篇文章下未删除、并且自己可见的评论
+    let depth = match payload.parent_id {
+        None => 0,
+        Some(parent_id) => {
+            let parent: Comment = sqlx::query_as(
+                "SELECT * FROM comments WHERE comment_id = ? AND post_id = ? AND deleted_at IS NULL AND hidden_at IS NULL \
+                 AND (status = 'approved' OR (status = 'pending' AND author_id = ?))",
+            )
+            .bind(parent_id)
+            .bind(post_id as i64)
+            .bind(user.id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("要回复的评论未找到"))?;
+            if parent.depth >= MAX_REPLY_DEPTH {
+                return Err(AppError::validation(format!(
+                    "回复最多嵌套 {} 层",
+                    MAX_REPLY_DEPTH
+                )));
+            }
+            parent.depth + 1
+        }
+    };
+
+    let status = decide_status(&state, &claims, &user, &post, &payload.content, None).await?;
+
+    let mut tx = state.pool.begin().await?;
     let comment = sqlx::query_as::<_, Comment>(
-        "INSERT INTO comments (post_id, author_id, content) VALUES (?, ?, ?) RETURNING *",
+        "INSERT INTO comments (post_id, author_id, content, content_hash, parent_id, depth, status) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
     )
     .bind(post_id as i64)
     .bind(user.id)
     .bind(&payload.content)
-    .fetch_one(&state.pool)
+    .bind(content_hash(&payload.content))
+    .bind(payload.parent_id)
+    .bind(depth)
+    .bind(status)
+    .fetch_one(&mut *tx)
     .await?;
+    index_comment(&mut tx, comment.id, &comment.content).await?;
+    tx.commit().await?;
     let comment_response: CommentResponse = (comment, user).into();
     Ok(created_response(comment_response))
 }
@@ -79,7 +243,7 @@ pub async fn create_comment_for_post(
     ),
     request_body = CreateComment,
     responses(
-        (status = 200, description = "成功更新评论", body = CommentResponse),
+        (status = 200, description = "成功更新评论，修改后的内容需要审核时状态为 pending", body = CommentResponse),
         (status = 403, description = "无权限操作", body = ErrorResponse),
         (status = 404, description = "未找到评论", body = ErrorResponse)
     ),
@@ -109,19 +273,55 @@ pub async fn update_comment(
     if comment.deleted_at.is_some() {
         return Err(AppError::not_found("评论未找到"));
     }
-    if comment.author_id != user.id {
+    if comment.author_id != user.id && !claims.has_permission(Permission::ManageAnyComment) {
         return Err(AppError::authorization("无权限修改此评论"));
     }
 
+    // 作者修改后的内容和新评论一样经过垃圾评论检测和审核，审核员修改时保持原状态；
+    // 已被拒绝或标记为垃圾的评论不能通过修改恢复
+    let status = match comment.status {
+        CommentStatus::Pending | CommentStatus::Approved
+            if !claims.has_permission(Permission::ManageAnyComment) =>
+        {
+            let post: Post = sqlx::query_as("SELECT * FROM posts WHERE id = ?")
+                .bind(post_id as i64)
+                .fetch_one(&state.pool)
+                .await?;
+            decide_status(
+                &state,
+                &claims,
+                &user,
+                &post,
+                &payload.content,
+                Some(comment.id),
+            )
+            .await?
+        }
+        status => status,
+    };
+
+    let mut tx = state.pool.begin().await?;
+    // 分类器按修改前的内容训练过，内容变化后撤销那次训练
+    untrain_comment(&mut tx, comment.id).await?;
     let updated_comment = sqlx::query_as::<_, Comment>(
-        "UPDATE comments SET content = ? WHERE comment_id = ? AND post_id = ? RETURNING *",
+        "UPDATE comments SET content = ?, content_hash = ?, status = ? WHERE comment_id = ? AND post_id = ? RETURNING *",
     )
     .bind(&payload.content)
+    .bind(content_hash(&payload.content))
+    .bind(status)
     .bind(comment_id as i64)
     .bind(post_id as i64)
+    .fetch_one(&mut *tx)
+    .await?;
+    index_comment(&mut tx, updated_comment.id, &updated_comment.content).await?;
+    tx.commit().await?;
+    let comment_response = sqlx::query_as::<_, CommentResponse>(&format!(
+        "SELECT {COMMENT_RESPONSE_COLUMNS} FROM comments c JOIN users u ON c.author_id = u.id \
+         WHERE c.comment_id = ?"
+    ))
+    .bind(updated_comment.id)
     .fetch_one(&state.pool)
     .await?;
-    let comment_response: CommentResponse = (comment, user).into();
     Ok(Json(comment_response))
 }
 
@@ -158,7 +358,7 @@ pub async fn delete_comment(
             .fetch_one(&state.pool)
             .await?;
 
-    if comment.author_id != user.id {
+    if comment.author_id != user.id && !claims.has_permission(Permission::ManageAnyComment) {
         return Err(AppError::authorization("无权限删除此评论"));
     }
     if comment.deleted_at.is_some() {
@@ -172,3 +372,219 @@ pub async fn delete_comment(
         .await?;
     check_delete_result(result, "Comment")
 }
+
+#[utoipa::path(
+    get,
+    path = "/comments/moderation",
+    params(
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页数量"),
+        ("status" = Option<CommentStatus>, Query, description = "按审核状态筛选，默认 pending"),
+        ("post_id" = Option<i64>, Query, description = "只列出该文章下的评论")
+    ),
+    responses(
+        (status = 200, description = "按发表先后列出审核队列中的评论", body = PaginatedResponse<CommentResponse>),
+        (status = 403, description = "无权限操作", body = ErrorResponse)
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_moderation_queue(
+    State(state): State<AppState>,
+    Query(pagination): Query<Pagination>,
+    Query(filter): Query<ModerationFilter>,
+) -> Result<Json<PaginatedResponse<CommentResponse>>, AppError> {
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let status = filter.status.unwrap_or(CommentStatus::Pending);
+
+    let total: (i64,) = sqlx::query_as(&format!(
+        "SELECT COUNT(*) FROM comments c WHERE {MODERATION_FILTER_SQL}"
+    ))
+    .bind(status)
+    .bind(filter.post_id)
+    .fetch_one(&state.pool)
+    .await?;
+    let total = total.0 as u64;
+
+    let offset = (pagination.page - 1) * pagination.page_size;
+    let total_pages = total.div_ceil(pagination.page_size);
+
+    let comments = sqlx::query_as::<_, CommentResponse>(&format!(
+        "SELECT {COMMENT_RESPONSE_COLUMNS} \
+         FROM comments c JOIN users u ON c.author_id = u.id \
+         WHERE {MODERATION_FILTER_SQL} ORDER BY c.comment_id LIMIT ?3 OFFSET ?4"
+    ))
+    .bind(status)
+    .bind(filter.post_id)
+    .bind(pagination.page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let response = PaginatedResponse {
+        data: comments,
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        total_pages,
+    };
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/comments/moderation",
+    request_body = ModerateComments,
+    responses(
+        (status = 200, description = "成功审核评论", body = ModerationResult),
+        (status = 400, description = "请求数据无效", body = ErrorResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse)
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn moderate_comments(
+    State(state): State<AppState>,
+    json_payload: Json<ModerateComments>,
+) -> Result<Json<ModerationResult>, AppError> {
+    let payload = json_payload.validate_json()?;
+    if payload.status == CommentStatus::Pending {
+        return Err(AppError::validation("审核结果不能为 pending"));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let updated: Vec<i64> = sqlx::query_scalar(
+        "UPDATE comments SET status = ? \
+         WHERE comment_id IN (SELECT value FROM json_each(?)) AND deleted_at IS NULL \
+         RETURNING comment_id",
+    )
+    .bind(payload.status)
+    .bind(serde_json::to_string(&payload.comment_ids).unwrap_or_default())
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // 审核员的决定用于训练垃圾评论分类器，被拒绝的评论不一定是垃圾评论，因此不参与训练
+    let label = match payload.status {
+        CommentStatus::Spam => Some(SpamLabel::Spam),
+        CommentStatus::Approved => Some(SpamLabel::Ham),
+        CommentStatus::Pending | CommentStatus::Rejected => None,
+    };
+    if let Some(label) = label {
+        for comment_id in &updated {
+            train_comment(&mut tx, *comment_id, label).await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(Json(ModerationResult {
+        updated: updated.len() as u64,
+    }))
+}
+
+/// 决定新发表或修改后的评论的状态，拥有评论管理权限的用户不需要审核
+///
+/// 垃圾评论检测认为可疑的评论进入审核队列或直接标记为垃圾评论，其余的按文章和站点的审核方式处理。
+/// 修改已有评论时 `editing` 为该评论的 ID，它不计入重复内容和已通过的评论。
+async fn decide_status(
+    state: &AppState,
+    claims: &Claims,
+    user: &User,
+    post: &Post,
+    content: &str,
+    editing: Option<i64>,
+) -> Result<CommentStatus, AppError> {
+    if claims.has_permission(Permission::ManageAnyComment) {
+        return Ok(CommentStatus::Approved);
+    }
+
+    let input = SpamInput {
+        comment_id: editing,
+        author_id: user.id,
+        content: content.to_string(),
+        author_created_at: user.created_at,
+    };
+    let verdict = state.spam_filter.evaluate(&state.pool, &input).await?;
+    if let Some(status) = verdict.status() {
+        tracing::info!(
+            author = %user.username,
+            post_id = post.id,
+            score = verdict.score,
+            signals = ?verdict.signals,
+            ?status,
+            edited = editing.is_some(),
+            "评论被垃圾评论检测拦截"
+        );
+        return Ok(status);
+    }
+
+    let mode = post
+        .comment_moderation
+        .unwrap_or(state.config.comment_moderation);
+    let status = match mode {
+        ModerationMode::Open => CommentStatus::Approved,
+        ModerationMode::All => CommentStatus::Pending,
+        ModerationMode::FirstTime => {
+            let approved = sqlx::query(
+                "SELECT 1 FROM comments WHERE author_id = ? AND status = 'approved' AND comment_id IS NOT ?",
+            )
+            .bind(user.id)
+            .bind(editing)
+            .fetch_optional(&state.pool)
+            .await?;
+            if approved.is_some() {
+                CommentStatus::Approved
+            } else {
+                CommentStatus::Pending
+            }
+        }
+    };
+    Ok(status)
+}
+
+/// 将评论排列为深度优先的回复树，每条评论后紧跟它的回复
+///
+/// 已删除的评论如果仍有可见的回复，则保留为 `[deleted]` 占位，否则不显示。
+fn flatten_thread(comments: Vec<CommentResponse>) -> Vec<CommentResponse> {
+    let mut replies: HashMap<Option<i64>, Vec<CommentResponse>> = HashMap::new();
+    for comment in comments {
+        replies.entry(comment.parent_id).or_default().push(comment);
+    }
+
+    let mut thread = Vec::new();
+    append_replies(None, &mut replies, &mut thread);
+    thread
+}
+
+fn append_replies(
+    parent_id: Option<i64>,
+    replies: &mut HashMap<Option<i64>, Vec<CommentResponse>>,
+    thread: &mut Vec<CommentResponse>,
+) {
+    for mut comment in replies.remove(&parent_id).unwrap_or_default() {
+        let id = comment.id;
+        let deleted = comment.deleted;
+        if deleted {
+            comment.author = DELETED_PLACEHOLDER.to_string();
+            comment.content = DELETED_PLACEHOLDER.to_string();
+        }
+
+        let position = thread.len();
+        thread.push(comment);
+        append_replies(Some(id), replies, thread);
+
+        if deleted && thread.len() == position + 1 {
+            thread.pop();
+        }
+    }
+}
@@ -1,7 +1,29 @@
+pub mod access_tokens;
+pub mod admin;
 pub mod auth;
+pub mod bookmarks;
 pub mod comments;
 pub mod posts;
+pub mod reactions;
+pub mod reports;
+pub mod revisions;
+pub mod search;
+pub mod spam;
+pub mod tags;
+pub mod two_factor;
+pub mod users;
 
+pub use access_tokens::*;
+pub use admin::*;
 pub use auth::*;
+pub use bookmarks::*;
 pub use comments::*;
 pub use posts::*;
+pub use reactions::*;
+pub use reports::*;
+pub use revisions::*;
+pub use search::*;
+pub use spam::*;
+pub use tags::*;
+pub use two_factor::*;
+pub use users::*;
@@ -1,8 +1,16 @@
 use crate::{
     errors::{AppError, ErrorResponse},
+    handlers::{
+        revisions::record_revision,
+        tags::{load_post_tags, set_post_tags},
+    },
     models::{
-        AppState, Claims, CreatePost, PaginatedResponse, Pagination, Post, PostResponse, User,
+        AppState, Claims, CreatePost, PaginatedResponse, Pagination, Post, PostFilter,
+        PostResponse, PostSort, PostStatus, UpdateCommentModeration, User,
     },
+    permissions::Permission,
+    search::index_post,
+    slugs::{unique_post_slug, update_post_slug},
     utils::{check_delete_result, created_response},
     validation::{ValidatedJson, format_validation_errors},
 };
@@ -10,27 +18,44 @@ use axum::{
     Extension, Json,
     extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Redirect, Response},
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use validator::Validate;
 
+/// 文章响应的查询，标签和表情回应数量以 JSON 的形式一并取出，渲染后的正文取自最新的修订
+pub(crate) const POST_RESPONSE_SELECT: &str = "SELECT p.*, u.username as author, p.hidden_at IS NOT NULL AS hidden, \
+    (SELECT json_group_array(t.name ORDER BY t.slug) FROM post_tags pt JOIN tags t ON t.id = pt.tag_id WHERE pt.post_id = p.id) AS tags, \
+    (SELECT json_group_object(kind, n) FROM (SELECT kind, COUNT(*) AS n FROM reactions WHERE target_type = 'post' AND target_id = p.id GROUP BY kind)) AS reactions, \
+    COALESCE((SELECT r.content_html FROM post_revisions r WHERE r.post_id = p.id ORDER BY r.revision_number DESC LIMIT 1), '') AS content_html \
+    FROM posts p JOIN users u ON p.author_id = u.id";
+
+/// 列出文章时共用的筛选条件，参数依次为 status、作者用户名、标签 slug
+const POST_FILTER_SQL: &str = "p.deleted_at IS NULL AND p.hidden_at IS NULL AND p.status = ?1 AND (?2 IS NULL OR u.username = ?2) \
+    AND (?3 IS NULL OR EXISTS (SELECT 1 FROM post_tags pt JOIN tags t ON t.id = pt.tag_id WHERE pt.post_id = p.id AND t.slug = ?3))";
+
 #[utoipa::path(
     get,
     path = "/posts",
     params(
         ("page" = Option<u64>, Query, description = "页码"),
-        ("page_size" = Option<u64>, Query, description = "每页数量")
+        ("page_size" = Option<u64>, Query, description = "每页数量"),
+        ("status" = Option<PostStatus>, Query, description = "按发布状态筛选，非 published 状态仅返回当前用户可见的文章"),
+        ("tag" = Option<String>, Query, description = "按标签的 slug 筛选"),
+        ("sort" = Option<PostSort>, Query, description = "排序方式，默认 oldest")
     ),
     responses(
         (status = 200, description = "成功列出所有文章", body = PaginatedResponse<PostResponse>),
+        (status = 401, description = "查看未发布的文章需要认证", body = ErrorResponse),
         (status = 500, description = "内部服务器错误", body = ErrorResponse)
     ),
     tag = "Posts"
 )]
 pub async fn get_posts(
     State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
     Query(pagination): Query<Pagination>,
+    Query(filter): Query<PostFilter>,
 ) -> Result<Json<PaginatedResponse<PostResponse>>, AppError> {
     pagination.validate().map_err(|validation_errors| {
         AppError::validation(format!(
@@ -39,29 +64,25 @@ pub async fn get_posts(
         ))
     })?;
 
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts WHERE deleted_at IS NULL")
-        .fetch_one(&state.pool)
-        .await?;
-    let total = total.0 as u64;
+    let status = filter.status.unwrap_or(PostStatus::Published);
 
-    let offset = (pagination.page - 1) * pagination.page_size;
-    let total_pages = total.div_ceil(pagination.page_size);
+    // 未发布的文章只对作者本人可见，编辑可以看到所有人的
+    let author_filter = match (status, claims.as_ref()) {
+        (PostStatus::Published, _) => None,
+        (_, None) => return Err(AppError::authentication("查看未发布的文章需要登录")),
+        (_, Some(Extension(claims))) if claims.has_permission(Permission::ManageAnyPost) => None,
+        (_, Some(Extension(claims))) => Some(claims.sub.clone()),
+    };
 
-    let posts = sqlx::query_as::<_, PostResponse>(
-        "SELECT p.*, u.username as author FROM posts p JOIN users u ON p.author_id = u.id WHERE p.deleted_at IS NULL ORDER BY p.id LIMIT ? OFFSET ?",
+    let response = list_posts(
+        &state,
+        status,
+        author_filter.as_deref(),
+        filter.tag.as_deref(),
+        filter.sort,
+        &pagination,
     )
-        .bind(pagination.page_size as i64)
-        .bind(offset as i64)
-        .fetch_all(&state.pool)
-        .await?;
-
-    let response = PaginatedResponse {
-        data: posts,
-        page: pagination.page,
-        page_size: pagination.page_size,
-        total,
-        total_pages,
-    };
+    .await?;
 
     Ok(Json(response))
 }
@@ -91,18 +112,28 @@ pub async fn create_post(
         .fetch_one(&state.pool)
         .await?;
 
+    let (status, published_at) = resolve_publication(&payload, None)?;
+
+    let mut tx = state.pool.begin().await?;
+    let slug = unique_post_slug(&mut tx, &payload.title, None).await?;
     let post = sqlx::query_as::<_, Post>(
-        "INSERT INTO posts (title, author_id, content, tags, copyright) VALUES (?, ?, ?, ?, ?) RETURNING *",
+        "INSERT INTO posts (title, slug, author_id, content, copyright, status, published_at) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
     )
         .bind(&payload.title)
+        .bind(&slug)
         .bind(user.id)
         .bind(&payload.content)
-        .bind(&payload.tags)
         .bind(&payload.copyright)
-        .fetch_one(&state.pool)
+        .bind(status)
+        .bind(published_at)
+        .fetch_one(&mut *tx)
         .await?;
+    set_post_tags(&mut tx, post.id, &payload.tags).await?;
+    record_revision(&mut tx, &post, user.id, None).await?;
+    index_post(&mut tx, post.id, &post.title, &post.content).await?;
+    tx.commit().await?;
 
-    let post_response: PostResponse = (post, user).into();
+    let post_response = find_post_response(&state, post.id).await?;
 
     Ok(created_response(post_response))
 }
@@ -113,23 +144,66 @@ pub async fn create_post(
     params(("id" = u64, Path, description = "文章 ID")),
     responses(
         (status = 200, description = "根据 ID 获取文章", body = PostResponse),
-        (status = 404, description = "未找到文章", body = ErrorResponse)
+        (status = 404, description = "未找到文章，或文章未发布且当前用户无权查看", body = ErrorResponse)
     ),
     tag = "Posts"
 )]
 pub async fn get_post_by_id(
     State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
     Path(id): Path<u64>,
 ) -> Result<Json<PostResponse>, AppError> {
-    let post = sqlx::query_as::<_, PostResponse>(
-        "SELECT p.*, u.username as author FROM posts p JOIN users u ON p.author_id = u.id WHERE p.id = ? AND p.deleted_at IS NULL",
-    )
-        .bind(id as i64)
-        .fetch_one(&state.pool)
-        .await?;
+    let post = find_post_response(&state, id as i64).await?;
+
+    if !can_view(&post, claims.as_ref().map(|Extension(claims)| claims)) {
+        return Err(AppError::not_found("文章未找到"));
+    }
+
     Ok(Json(post))
 }
 
+#[utoipa::path(
+    get,
+    path = "/posts/by-slug/{slug}",
+    params(("slug" = String, Path, description = "文章的 slug")),
+    responses(
+        (status = 200, description = "根据 slug 获取文章", body = PostResponse),
+        (status = 308, description = "文章标题已修改，重定向到当前的 slug"),
+        (status = 404, description = "未找到文章，或文章未发布且当前用户无权查看", body = ErrorResponse)
+    ),
+    tag = "Posts"
+)]
+pub async fn get_post_by_slug(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(slug): Path<String>,
+) -> Result<Response, AppError> {
+    let post = sqlx::query_as::<_, PostResponse>(&format!(
+        "{POST_RESPONSE_SELECT} WHERE p.slug = ? AND p.deleted_at IS NULL"
+    ))
+    .bind(&slug)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    if let Some(post) = post {
+        if !can_view(&post, claims.as_ref().map(|Extension(claims)| claims)) {
+            return Err(AppError::not_found("文章未找到"));
+        }
+        return Ok(Json(post).into_response());
+    }
+
+    // 旧的 slug 重定向到文章当前的 slug
+    let current: (String,) = sqlx::query_as(
+        "SELECT p.slug FROM post_slug_redirects r JOIN posts p ON p.id = r.post_id WHERE r.slug = ? AND p.deleted_at IS NULL",
+    )
+    .bind(&slug)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("文章未找到"))?;
+
+    Ok(Redirect::permanent(&format!("/posts/by-slug/{}", current.0)).into_response())
+}
+
 #[utoipa::path(
     put,
     path = "/posts/{id}",
@@ -167,22 +241,48 @@ pub async fn update_post(
         return Err(AppError::not_found("文章未找到"));
     }
 
-    if post.author_id != user.id {
+    if post.author_id != user.id && !claims.has_permission(Permission::ManageAnyPost) {
         return Err(AppError::authorization("无权限修改此文章"));
     }
 
+    let (status, published_at) = resolve_publication(&payload, Some(&post))?;
+
+    let mut tx = state.pool.begin().await?;
+    let old_tags = load_post_tags(&mut tx, post.id).await?;
     let updated_post = sqlx::query_as::<_, Post>(
-        "UPDATE posts SET title = ?, content = ?, tags = ?, copyright = ? WHERE id = ? RETURNING *",
+        "UPDATE posts SET title = ?, content = ?, copyright = ?, status = ?, published_at = ? WHERE id = ? RETURNING *",
     )
     .bind(&payload.title)
     .bind(&payload.content)
-    .bind(&payload.tags)
     .bind(&payload.copyright)
+    .bind(status)
+    .bind(published_at)
     .bind(id as i64)
-    .fetch_one(&state.pool)
+    .fetch_one(&mut *tx)
     .await?;
+    let tags = set_post_tags(&mut tx, post.id, &payload.tags).await?;
+    if updated_post.title != post.title {
+        update_post_slug(&mut tx, post.id, &post.slug, &updated_post.title).await?;
+    }
+    // 只有内容发生变化时才产生新的修订，单纯修改发布状态不计入历史
+    let content_changed = updated_post.title != post.title
+        || updated_post.content != post.content
+        || tags != old_tags
+        || updated_post.copyright != post.copyright;
+    if content_changed {
+        record_revision(&mut tx, &updated_post, user.id, None).await?;
+        index_post(
+            &mut tx,
+            updated_post.id,
+            &updated_post.title,
+            &updated_post.content,
+        )
+        .await?;
+    }
+    tx.commit().await?;
 
-    let post_response: PostResponse = (post, user).into();
+    // 编辑或管理员可能在修改他人的文章，响应中的作者始终是文章原作者
+    let post_response = find_post_response(&state, post.id).await?;
 
     Ok(Json(post_response))
 }
@@ -216,7 +316,7 @@ pub async fn delete_post(
         .fetch_one(&state.pool)
         .await?;
 
-    if post.author_id != user.id {
+    if post.author_id != user.id && !claims.has_permission(Permission::ManageAnyPost) {
         return Err(AppError::authorization("无权限删除此文章"));
     }
 
@@ -228,3 +328,156 @@ pub async fn delete_post(
 
     check_delete_result(result, "Post")
 }
+
+#[utoipa::path(
+    put,
+    path = "/posts/{id}/comment-moderation",
+    params(("id" = u64, Path, description = "文章 ID")),
+    request_body = UpdateCommentModeration,
+    responses(
+        (status = 200, description = "成功修改评论审核方式", body = PostResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到文章", body = ErrorResponse)
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn update_comment_moderation(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UpdateCommentModeration>,
+) -> Result<Json<PostResponse>, AppError> {
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let post: Post = sqlx::query_as("SELECT * FROM posts WHERE id = ? AND deleted_at IS NULL")
+        .bind(id as i64)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("文章未找到"))?;
+
+    if post.author_id != user.id && !claims.has_permission(Permission::ManageAnyPost) {
+        return Err(AppError::authorization("无权限修改此文章"));
+    }
+
+    sqlx::query("UPDATE posts SET comment_moderation = ? WHERE id = ?")
+        .bind(payload.mode)
+        .bind(post.id)
+        .execute(&state.pool)
+        .await?;
+
+    let post_response = find_post_response(&state, post.id).await?;
+
+    Ok(Json(post_response))
+}
+
+/// 根据 ID 读取未删除的文章
+pub(crate) async fn find_post_response(
+    state: &AppState,
+    id: i64,
+) -> Result<PostResponse, AppError> {
+    sqlx::query_as::<_, PostResponse>(&format!(
+        "{POST_RESPONSE_SELECT} WHERE p.id = ? AND p.deleted_at IS NULL"
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("文章未找到"))
+}
+
+/// 按状态、作者和标签分页列出文章，供文章列表和标签页面共用
+pub(crate) async fn list_posts(
+    state: &AppState,
+    status: PostStatus,
+    author: Option<&str>,
+    tag: Option<&str>,
+    sort: PostSort,
+    pagination: &Pagination,
+) -> Result<PaginatedResponse<PostResponse>, AppError> {
+    let total: (i64,) = sqlx::query_as(&format!(
+        "SELECT COUNT(*) FROM posts p JOIN users u ON p.author_id = u.id WHERE {POST_FILTER_SQL}"
+    ))
+    .bind(status)
+    .bind(author)
+    .bind(tag)
+    .fetch_one(&state.pool)
+    .await?;
+    let total = total.0 as u64;
+
+    let offset = (pagination.page - 1) * pagination.page_size;
+    let total_pages = total.div_ceil(pagination.page_size);
+
+    let order = match sort {
+        PostSort::Oldest => "p.id",
+        PostSort::Top => {
+            "(SELECT COUNT(*) FROM reactions WHERE target_type = 'post' AND target_id = p.id) DESC, p.id"
+        }
+    };
+    let posts = sqlx::query_as::<_, PostResponse>(&format!(
+        "{POST_RESPONSE_SELECT} WHERE {POST_FILTER_SQL} ORDER BY {order} LIMIT ?4 OFFSET ?5"
+    ))
+    .bind(status)
+    .bind(author)
+    .bind(tag)
+    .bind(pagination.page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(PaginatedResponse {
+        data: posts,
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        total_pages,
+    })
+}
+
+/// 判断当前用户能否查看文章：已发布且未被隐藏的文章所有人可见，其他情况仅作者本人和编辑可见
+pub(crate) fn can_view(post: &PostResponse, claims: Option<&Claims>) -> bool {
+    (post.status == PostStatus::Published && !post.hidden)
+        || claims.is_some_and(|claims| {
+            claims.sub == post.author || claims.has_permission(Permission::ManageAnyPost)
+        })
+}
+
+/// 根据请求的状态计算文章最终的状态和发布时间
+///
+/// 首次发布时记录当前时间，重新发布已归档的文章时保留原发布时间；
+/// 定时发布必须指定一个未来的时间。
+fn resolve_publication(
+    payload: &CreatePost,
+    current: Option<&Post>,
+) -> Result<(PostStatus, Option<DateTime<Utc>>), AppError> {
+    let now = Utc::now();
+    let status = payload
+        .status
+        .or(current.map(|post| post.status))
+        .unwrap_or(PostStatus::Published);
+    let current_published_at = current.and_then(|post| post.published_at);
+
+    let published_at = match status {
+        PostStatus::Draft => None,
+        PostStatus::Scheduled => {
+            let scheduled_at = payload.published_at.or(current_published_at);
+            match scheduled_at {
+                Some(at) if at > now => Some(at),
+                _ => {
+                    return Err(AppError::validation(
+                        "定时发布的文章必须指定一个未来的 published_at",
+                    ));
+                }
+            }
+        }
+        PostStatus::Published | PostStatus::Archived => {
+            Some(current_published_at.filter(|at| *at <= now).unwrap_or(now))
+        }
+    };
+
+    Ok((status, published_at))
+}
@@ -0,0 +1,201 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    models::{AppState, Claims, ReactionKind, ReactionSummary, User},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use std::collections::BTreeMap;
+
+#[utoipa::path(
+    put,
+    path = "/posts/{id}/reactions/{kind}",
+    params(
+        ("id" = u64, Path, description = "文章 ID"),
+        ("kind" = ReactionKind, Path, description = "表情回应的种类")
+    ),
+    responses(
+        (status = 200, description = "成功添加表情回应，重复添加不会重复计数", body = ReactionSummary),
+        (status = 404, description = "未找到文章", body = ErrorResponse)
+    ),
+    tag = "Reactions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn add_post_reaction(
+    State(state): State<AppState>,
+    Path((id, kind)): Path<(u64, ReactionKind)>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ReactionSummary>, AppError> {
+    find_visible_post(&state, id).await?;
+    let summary = set_reaction(&state, &claims, "post", id as i64, kind, true).await?;
+    Ok(Json(summary))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/posts/{id}/reactions/{kind}",
+    params(
+        ("id" = u64, Path, description = "文章 ID"),
+        ("kind" = ReactionKind, Path, description = "表情回应的种类")
+    ),
+    responses(
+        (status = 200, description = "成功撤销表情回应，未回应过时不做改动", body = ReactionSummary),
+        (status = 404, description = "未找到文章", body = ErrorResponse)
+    ),
+    tag = "Reactions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn remove_post_reaction(
+    State(state): State<AppState>,
+    Path((id, kind)): Path<(u64, ReactionKind)>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ReactionSummary>, AppError> {
+    find_visible_post(&state, id).await?;
+    let summary = set_reaction(&state, &claims, "post", id as i64, kind, false).await?;
+    Ok(Json(summary))
+}
+
+#[utoipa::path(
+    put,
+    path = "/posts/{post_id}/comments/{comment_id}/reactions/{kind}",
+    params(
+        ("post_id" = u64, Path, description = "文章 ID"),
+        ("comment_id" = u64, Path, description = "评论 ID"),
+        ("kind" = ReactionKind, Path, description = "表情回应的种类")
+    ),
+    responses(
+        (status = 200, description = "成功添加表情回应，重复添加不会重复计数", body = ReactionSummary),
+        (status = 404, description = "未找到评论", body = ErrorResponse)
+    ),
+    tag = "Reactions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn add_comment_reaction(
+    State(state): State<AppState>,
+    Path((post_id, comment_id, kind)): Path<(u64, u64, ReactionKind)>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ReactionSummary>, AppError> {
+    find_visible_comment(&state, post_id, comment_id).await?;
+    let summary = set_reaction(&state, &claims, "comment", comment_id as i64, kind, true).await?;
+    Ok(Json(summary))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/posts/{post_id}/comments/{comment_id}/reactions/{kind}",
+    params(
+        ("post_id" = u64, Path, description = "文章 ID"),
+        ("comment_id" = u64, Path, description = "评论 ID"),
+        ("kind" = ReactionKind, Path, description = "表情回应的种类")
+    ),
+    responses(
+        (status = 200, description = "成功撤销表情回应，未回应过时不做改动", body = ReactionSummary),
+        (status = 404, description = "未找到评论", body = ErrorResponse)
+    ),
+    tag = "Reactions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn remove_comment_reaction(
+    State(state): State<AppState>,
+    Path((post_id, comment_id, kind)): Path<(u64, u64, ReactionKind)>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ReactionSummary>, AppError> {
+    find_visible_comment(&state, post_id, comment_id).await?;
+    let summary = set_reaction(&state, &claims, "comment", comment_id as i64, kind, false).await?;
+    Ok(Json(summary))
+}
+
+/// 只能回应公开可见的文章
+async fn find_visible_post(state: &AppState, id: u64) -> Result<(), AppError> {
+    sqlx::query(
+        "SELECT 1 FROM posts \
+         WHERE id = ? AND deleted_at IS NULL AND hidden_at IS NULL AND status = 'published'",
+    )
+    .bind(id as i64)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("文章未找到"))?;
+    Ok(())
+}
+
+/// 只能回应公开可见的文章下已通过审核的评论
+async fn find_visible_comment(
+    state: &AppState,
+    post_id: u64,
+    comment_id: u64,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "SELECT 1 FROM comments c JOIN posts p ON p.id = c.post_id \
+         WHERE c.comment_id = ? AND c.post_id = ? AND c.deleted_at IS NULL AND c.hidden_at IS NULL \
+           AND c.status = 'approved' \
+           AND p.deleted_at IS NULL AND p.hidden_at IS NULL AND p.status = 'published'",
+    )
+    .bind(comment_id as i64)
+    .bind(post_id as i64)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("评论未找到"))?;
+    Ok(())
+}
+
+/// 添加或撤销当前用户的表情回应，返回回应后的汇总
+///
+/// 每个用户对同一内容的每种表情只记一次，由唯一约束保证，重复的请求不会改变计数。
+async fn set_reaction(
+    state: &AppState,
+    claims: &Claims,
+    target_type: &str,
+    target_id: i64,
+    kind: ReactionKind,
+    add: bool,
+) -> Result<ReactionSummary, AppError> {
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let query = if add {
+        "INSERT INTO reactions (target_type, target_id, user_id, kind) VALUES (?, ?, ?, ?) \
+         ON CONFLICT (target_type, target_id, user_id, kind) DO NOTHING"
+    } else {
+        "DELETE FROM reactions WHERE target_type = ? AND target_id = ? AND user_id = ? AND kind = ?"
+    };
+    sqlx::query(query)
+        .bind(target_type)
+        .bind(target_id)
+        .bind(user.id)
+        .bind(kind)
+        .execute(&state.pool)
+        .await?;
+
+    let counts: Vec<(ReactionKind, i64)> = sqlx::query_as(
+        "SELECT kind, COUNT(*) FROM reactions WHERE target_type = ? AND target_id = ? GROUP BY kind",
+    )
+    .bind(target_type)
+    .bind(target_id)
+    .fetch_all(&state.pool)
+    .await?;
+    let mut mine: Vec<ReactionKind> = sqlx::query_scalar(
+        "SELECT kind FROM reactions WHERE target_type = ? AND target_id = ? AND user_id = ?",
+    )
+    .bind(target_type)
+    .bind(target_id)
+    .bind(user.id)
+    .fetch_all(&state.pool)
+    .await?;
+    mine.sort();
+
+    Ok(ReactionSummary {
+        reactions: counts.into_iter().collect::<BTreeMap<_, _>>(),
+        mine,
+    })
+}
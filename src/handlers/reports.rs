@@ -0,0 +1,391 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    models::{
+        AppState, Claims, CreateReport, PaginatedResponse, Pagination, ReportDecision,
+        ReportFilter, ReportReason, ReportResponse, ReportStatus, ReportTarget, User,
+    },
+    utils::created_response,
+    validation::{ValidatedJson, format_validation_errors},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use chrono::Utc;
+use sqlx::SqliteConnection;
+use validator::Validate;
+
+/// 举报信息的查询，被举报的内容可能已随作者一起被彻底删除
+const REPORT_SELECT: &str = "SELECT r.id, r.target_type, r.target_id, \
+    CASE r.target_type WHEN 'post' THEN (SELECT p.id FROM posts p WHERE p.id = r.target_id) \
+         ELSE (SELECT c.post_id FROM comments c WHERE c.comment_id = r.target_id) END AS post_id, \
+    reporter.username AS reporter, r.reason, r.details, r.status, \
+    (SELECT COUNT(*) FROM reports o WHERE o.target_type = r.target_type AND o.target_id = r.target_id AND o.status = 'open') AS open_reports, \
+    COALESCE(CASE r.target_type WHEN 'post' THEN (SELECT p.hidden_at IS NOT NULL FROM posts p WHERE p.id = r.target_id) \
+         ELSE (SELECT c.hidden_at IS NOT NULL FROM comments c WHERE c.comment_id = r.target_id) END, 0) AS target_hidden, \
+    r.created_at, moderator.username AS resolved_by, r.resolved_at, r.resolution_note \
+    FROM reports r JOIN users reporter ON reporter.id = r.reporter_id \
+    LEFT JOIN users moderator ON moderator.id = r.resolved_by";
+
+/// 举报列表的筛选条件，参数依次为处理状态、内容类型
+const REPORT_FILTER_SQL: &str = "r.status = ?1 AND (?2 IS NULL OR r.target_type = ?2)";
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/report",
+    params(("id" = u64, Path, description = "文章 ID")),
+    request_body = CreateReport,
+    responses(
+        (status = 201, description = "成功举报文章", body = ReportResponse),
+        (status = 400, description = "举报数据无效，或举报自己的文章", body = ErrorResponse),
+        (status = 404, description = "未找到文章", body = ErrorResponse),
+        (status = 409, description = "已经举报过该文章", body = ErrorResponse)
+    ),
+    tag = "Reports",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn report_post(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<CreateReport>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = json_payload.validate_json()?;
+
+    let author_id: (i64,) = sqlx::query_as(
+        "SELECT author_id FROM posts \
+         WHERE id = ? AND deleted_at IS NULL AND hidden_at IS NULL AND status = 'published'",
+    )
+    .bind(id as i64)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("文章未找到"))?;
+
+    let report = create_report(
+        &state,
+        &claims,
+        ReportTarget::Post,
+        id as i64,
+        author_id.0,
+        payload,
+    )
+    .await?;
+    Ok(created_response(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{post_id}/comments/{comment_id}/report",
+    params(
+        ("post_id" = u64, Path, description = "文章 ID"),
+        ("comment_id" = u64, Path, description = "评论 ID")
+    ),
+    request_body = CreateReport,
+    responses(
+        (status = 201, description = "成功举报评论", body = ReportResponse),
+        (status = 400, description = "举报数据无效，或举报自己的评论", body = ErrorResponse),
+        (status = 404, description = "未找到评论", body = ErrorResponse),
+        (status = 409, description = "已经举报过该评论", body = ErrorResponse)
+    ),
+    tag = "Reports",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn report_comment(
+    State(state): State<AppState>,
+    Path((post_id, comment_id)): Path<(u64, u64)>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<CreateReport>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = json_payload.validate_json()?;
+
+    // 只能举报公开可见的评论
+    let author_id: (i64,) = sqlx::query_as(
+        "SELECT c.author_id FROM comments c JOIN posts p ON p.id = c.post_id \
+         WHERE c.comment_id = ? AND c.post_id = ? AND c.deleted_at IS NULL AND c.hidden_at IS NULL \
+           AND c.status = 'approved' \
+           AND p.deleted_at IS NULL AND p.hidden_at IS NULL AND p.status = 'published'",
+    )
+    .bind(comment_id as i64)
+    .bind(post_id as i64)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("评论未找到"))?;
+
+    let report = create_report(
+        &state,
+        &claims,
+        ReportTarget::Comment,
+        comment_id as i64,
+        author_id.0,
+        payload,
+    )
+    .await?;
+    Ok(created_response(report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/reports",
+    params(
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页数量"),
+        ("status" = Option<ReportStatus>, Query, description = "按处理状态筛选，默认 open"),
+        ("target_type" = Option<ReportTarget>, Query, description = "按被举报内容的类型筛选")
+    ),
+    responses(
+        (status = 200, description = "按提交先后列出举报", body = PaginatedResponse<ReportResponse>),
+        (status = 403, description = "无权限操作", body = ErrorResponse)
+    ),
+    tag = "Reports",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn list_reports(
+    State(state): State<AppState>,
+    Query(pagination): Query<Pagination>,
+    Query(filter): Query<ReportFilter>,
+) -> Result<Json<PaginatedResponse<ReportResponse>>, AppError> {
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let status = filter.status.unwrap_or(ReportStatus::Open);
+
+    let total: (i64,) = sqlx::query_as(&format!(
+        "SELECT COUNT(*) FROM reports r WHERE {REPORT_FILTER_SQL}"
+    ))
+    .bind(status)
+    .bind(filter.target_type)
+    .fetch_one(&state.pool)
+    .await?;
+    let total = total.0 as u64;
+
+    let offset = (pagination.page - 1) * pagination.page_size;
+    let total_pages = total.div_ceil(pagination.page_size);
+
+    let reports = sqlx::query_as::<_, ReportResponse>(&format!(
+        "{REPORT_SELECT} WHERE {REPORT_FILTER_SQL} ORDER BY r.id LIMIT ?3 OFFSET ?4"
+    ))
+    .bind(status)
+    .bind(filter.target_type)
+    .bind(pagination.page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let response = PaginatedResponse {
+        data: reports,
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        total_pages,
+    };
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/reports/{id}/resolve",
+    params(("id" = u64, Path, description = "举报 ID")),
+    request_body = ReportDecision,
+    responses(
+        (status = 200, description = "举报成立，同一内容的其他待处理举报一并处理，内容保持隐藏", body = ReportResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到举报", body = ErrorResponse),
+        (status = 409, description = "举报已经处理过", body = ErrorResponse)
+    ),
+    tag = "Reports",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn resolve_report(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<ReportDecision>,
+) -> Result<Json<ReportResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let report = decide_report(&state, &claims, id, ReportStatus::Resolved, payload).await?;
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/reports/{id}/dismiss",
+    params(("id" = u64, Path, description = "举报 ID")),
+    request_body = ReportDecision,
+    responses(
+        (status = 200, description = "举报不成立，同一内容的其他待处理举报一并驳回，内容恢复显示", body = ReportResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到举报", body = ErrorResponse),
+        (status = 409, description = "举报已经处理过", body = ErrorResponse)
+    ),
+    tag = "Reports",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn dismiss_report(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<ReportDecision>,
+) -> Result<Json<ReportResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let report = decide_report(&state, &claims, id, ReportStatus::Dismissed, payload).await?;
+    Ok(Json(report))
+}
+
+/// 保存举报，同一内容待处理的举报数量达到阈值时隐藏该内容
+async fn create_report(
+    state: &AppState,
+    claims: &Claims,
+    target_type: ReportTarget,
+    target_id: i64,
+    author_id: i64,
+    payload: CreateReport,
+) -> Result<ReportResponse, AppError> {
+    let details = payload
+        .details
+        .map(|details| details.trim().to_string())
+        .filter(|details| !details.is_empty());
+    if payload.reason == ReportReason::Other && details.is_none() {
+        return Err(AppError::validation("举报原因为 other 时必须填写说明"));
+    }
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+    if user.id == author_id {
+        return Err(AppError::validation("不能举报自己的内容"));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let report_id: i64 = sqlx::query_scalar(
+        "INSERT INTO reports (target_type, target_id, reporter_id, reason, details) VALUES (?, ?, ?, ?, ?) \
+         ON CONFLICT (target_type, target_id, reporter_id) DO NOTHING RETURNING id",
+    )
+    .bind(target_type)
+    .bind(target_id)
+    .bind(user.id)
+    .bind(payload.reason)
+    .bind(&details)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::conflict("已经举报过该内容"))?;
+
+    let open_reports: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM reports WHERE target_type = ? AND target_id = ? AND status = 'open'",
+    )
+    .bind(target_type)
+    .bind(target_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if open_reports >= state.config.report_hide_threshold {
+        set_hidden(&mut tx, target_type, target_id, true).await?;
+        tracing::info!(
+            ?target_type,
+            target_id,
+            open_reports,
+            "被举报的内容已自动隐藏"
+        );
+    }
+    tx.commit().await?;
+
+    find_report(state, report_id).await
+}
+
+/// 处理举报，决定作用于被举报的内容，因此同一内容其他待处理的举报也一并处理
+async fn decide_report(
+    state: &AppState,
+    claims: &Claims,
+    id: u64,
+    status: ReportStatus,
+    payload: ReportDecision,
+) -> Result<ReportResponse, AppError> {
+    let (target_type, target_id, current): (ReportTarget, i64, ReportStatus) =
+        sqlx::query_as("SELECT target_type, target_id, status FROM reports WHERE id = ?")
+            .bind(id as i64)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("举报未找到"))?;
+    if current != ReportStatus::Open {
+        return Err(AppError::conflict("举报已经处理过"));
+    }
+
+    let moderator: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+    let note = payload
+        .note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query(
+        "UPDATE reports SET status = ?, resolved_by = ?, resolved_at = ?, resolution_note = ? \
+         WHERE target_type = ? AND target_id = ? AND status = 'open'",
+    )
+    .bind(status)
+    .bind(moderator.id)
+    .bind(Utc::now())
+    .bind(&note)
+    .bind(target_type)
+    .bind(target_id)
+    .execute(&mut *tx)
+    .await?;
+    set_hidden(
+        &mut tx,
+        target_type,
+        target_id,
+        status == ReportStatus::Resolved,
+    )
+    .await?;
+    tx.commit().await?;
+
+    find_report(state, id as i64).await
+}
+
+/// 隐藏或恢复被举报的内容，已经隐藏的内容保留最初的隐藏时间
+async fn set_hidden(
+    conn: &mut SqliteConnection,
+    target_type: ReportTarget,
+    target_id: i64,
+    hidden: bool,
+) -> Result<(), sqlx::Error> {
+    let (table, id_column) = match target_type {
+        ReportTarget::Post => ("posts", "id"),
+        ReportTarget::Comment => ("comments", "comment_id"),
+    };
+    let hidden_at = hidden.then(Utc::now);
+    sqlx::query(&format!(
+        "UPDATE {table} SET hidden_at = CASE WHEN ?1 IS NULL THEN NULL ELSE COALESCE(hidden_at, ?1) END \
+         WHERE {id_column} = ?2"
+    ))
+    .bind(hidden_at)
+    .bind(target_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn find_report(state: &AppState, id: i64) -> Result<ReportResponse, AppError> {
+    sqlx::query_as::<_, ReportResponse>(&format!("{REPORT_SELECT} WHERE r.id = ?"))
+        .bind(id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("举报未找到"))
+}
@@ -0,0 +1,274 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    handlers::tags::set_post_tags,
+    markdown::render_markdown,
+    models::{
+        AppState, Claims, DiffLine, DiffOp, FieldDiff, Post, PostRevision, PostRevisionResponse,
+        PostRevisionSummary, RevisionDiffQuery, RevisionDiffResponse, User,
+    },
+    permissions::Permission,
+    search::index_post,
+    slugs::update_post_slug,
+    utils::created_response,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use similar::{ChangeTag, TextDiff};
+use sqlx::SqliteConnection;
+
+const REVISION_SELECT: &str = "SELECT r.post_id, r.revision_number, u.username as editor, r.title, r.content, r.tags, r.copyright, r.restored_from, r.created_at FROM post_revisions r LEFT JOIN users u ON r.editor_id = u.id";
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions",
+    params(("id" = u64, Path, description = "文章 ID")),
+    responses(
+        (status = 200, description = "列出文章的所有修订", body = [PostRevisionSummary]),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到文章", body = ErrorResponse)
+    ),
+    tag = "Revisions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_post_revisions(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<PostRevisionSummary>>, AppError> {
+    let post = find_editable_post(&state, id, &claims).await?;
+
+    let revisions = sqlx::query_as::<_, PostRevisionSummary>(
+        "SELECT r.revision_number, u.username as editor, r.title, r.restored_from, r.created_at FROM post_revisions r LEFT JOIN users u ON r.editor_id = u.id WHERE r.post_id = ? ORDER BY r.revision_number DESC",
+    )
+    .bind(post.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(revisions))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions/{revision}",
+    params(
+        ("id" = u64, Path, description = "文章 ID"),
+        ("revision" = i64, Path, description = "修订号")
+    ),
+    responses(
+        (status = 200, description = "获取修订的完整内容", body = PostRevisionResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到文章或修订", body = ErrorResponse)
+    ),
+    tag = "Revisions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_post_revision(
+    State(state): State<AppState>,
+    Path((id, revision)): Path<(u64, i64)>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<PostRevisionResponse>, AppError> {
+    let post = find_editable_post(&state, id, &claims).await?;
+    let revision = find_revision(&state, post.id, revision).await?;
+    Ok(Json(revision))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions/diff",
+    params(
+        ("id" = u64, Path, description = "文章 ID"),
+        ("from" = i64, Query, description = "旧修订号"),
+        ("to" = i64, Query, description = "新修订号")
+    ),
+    responses(
+        (status = 200, description = "两个修订之间的逐行差异", body = RevisionDiffResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到文章或修订", body = ErrorResponse)
+    ),
+    tag = "Revisions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn diff_post_revisions(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<Json<RevisionDiffResponse>, AppError> {
+    let post = find_editable_post(&state, id, &claims).await?;
+    let old = find_revision(&state, post.id, query.from).await?;
+    let new = find_revision(&state, post.id, query.to).await?;
+
+    // 标签每行一个，逐个比较
+    let (old_tags, new_tags) = (old.tags.join("\n"), new.tags.join("\n"));
+    let changes = [
+        ("title", &old.title, &new.title),
+        ("content", &old.content, &new.content),
+        ("tags", &old_tags, &new_tags),
+        ("copyright", &old.copyright, &new.copyright),
+    ]
+    .into_iter()
+    .filter(|(_, old, new)| old != new)
+    .map(|(field, old, new)| FieldDiff {
+        field: field.to_string(),
+        lines: diff_lines(old, new),
+    })
+    .collect();
+
+    Ok(Json(RevisionDiffResponse {
+        from: query.from,
+        to: query.to,
+        changes,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/revisions/{revision}/restore",
+    params(
+        ("id" = u64, Path, description = "文章 ID"),
+        ("revision" = i64, Path, description = "要恢复的修订号")
+    ),
+    responses(
+        (status = 201, description = "成功恢复，返回新创建的修订", body = PostRevisionResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到文章或修订", body = ErrorResponse)
+    ),
+    tag = "Revisions",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn restore_post_revision(
+    State(state): State<AppState>,
+    Path((id, revision)): Path<(u64, i64)>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    let post = find_editable_post(&state, id, &claims).await?;
+    let source = find_revision(&state, post.id, revision).await?;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+
+    // 恢复旧内容并记录为一个新的修订，历史本身保持不变
+    let mut tx = state.pool.begin().await?;
+    let restored_post = sqlx::query_as::<_, Post>(
+        "UPDATE posts SET title = ?, content = ?, copyright = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&source.title)
+    .bind(&source.content)
+    .bind(&source.copyright)
+    .bind(post.id)
+    .fetch_one(&mut *tx)
+    .await?;
+    set_post_tags(&mut tx, post.id, &source.tags).await?;
+    if restored_post.title != post.title {
+        update_post_slug(&mut tx, post.id, &post.slug, &restored_post.title).await?;
+    }
+    let new_revision = record_revision(
+        &mut tx,
+        &restored_post,
+        user.id,
+        Some(source.revision_number),
+    )
+    .await?;
+    index_post(
+        &mut tx,
+        restored_post.id,
+        &restored_post.title,
+        &restored_post.content,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let revision = find_revision(&state, post.id, new_revision.revision_number).await?;
+
+    Ok(created_response(revision))
+}
+
+/// 为文章的当前内容保存一个新的修订快照
+///
+/// 标签在快照中保存为名称的 JSON 数组，因此需要在更新文章标签之后调用。
+/// 正文在这里渲染为 HTML 并随修订一起保存。
+pub(crate) async fn record_revision(
+    conn: &mut SqliteConnection,
+    post: &Post,
+    editor_id: i64,
+    restored_from: Option<i64>,
+) -> Result<PostRevision, sqlx::Error> {
+    sqlx::query_as::<_, PostRevision>(
+        "INSERT INTO post_revisions (post_id, revision_number, editor_id, title, content, content_html, tags, copyright, restored_from) \
+         VALUES (?1, (SELECT COALESCE(MAX(revision_number), 0) + 1 FROM post_revisions WHERE post_id = ?1), ?2, ?3, ?4, ?5, \
+                 (SELECT json_group_array(t.name ORDER BY t.slug) FROM post_tags pt JOIN tags t ON t.id = pt.tag_id WHERE pt.post_id = ?1), \
+                 ?6, ?7) RETURNING *",
+    )
+    .bind(post.id)
+    .bind(editor_id)
+    .bind(&post.title)
+    .bind(&post.content)
+    .bind(render_markdown(&post.content))
+    .bind(&post.copyright)
+    .bind(restored_from)
+    .fetch_one(conn)
+    .await
+}
+
+/// 查找当前用户有权编辑的文章，修订历史可能包含未公开的内容，因此与修改文章的权限一致
+async fn find_editable_post(state: &AppState, id: u64, claims: &Claims) -> Result<Post, AppError> {
+    let post: Post = sqlx::query_as("SELECT * FROM posts WHERE id = ? AND deleted_at IS NULL")
+        .bind(id as i64)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("文章未找到"))?;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+
+    if post.author_id != user.id && !claims.has_permission(Permission::ManageAnyPost) {
+        return Err(AppError::authorization("无权限查看此文章的修订"));
+    }
+
+    Ok(post)
+}
+
+async fn find_revision(
+    state: &AppState,
+    post_id: i64,
+    revision: i64,
+) -> Result<PostRevisionResponse, AppError> {
+    sqlx::query_as::<_, PostRevisionResponse>(&format!(
+        "{REVISION_SELECT} WHERE r.post_id = ? AND r.revision_number = ?"
+    ))
+    .bind(post_id)
+    .bind(revision)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found(format!("修订 {} 未找到", revision)))
+}
+
+/// 计算两段文本的逐行差异
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            op: match change.tag() {
+                ChangeTag::Equal => DiffOp::Equal,
+                ChangeTag::Insert => DiffOp::Insert,
+                ChangeTag::Delete => DiffOp::Delete,
+            },
+            text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+        })
+        .collect()
+}
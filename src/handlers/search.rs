@@ -0,0 +1,140 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    models::{AppState, PaginatedResponse, Pagination, SearchQuery, SearchResult, SearchScope},
+    search::{build_match_query, contains_any_term, make_snippet},
+    validation::format_validation_errors,
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+/// 摘要最多包含的字符数
+const SNIPPET_CHARS: usize = 64;
+
+/// 索引中保存的是分词后的文本，摘要需要从原文生成，因此查询返回原文
+#[derive(sqlx::FromRow)]
+struct SearchRow {
+    kind: String,
+    post_id: i64,
+    comment_id: Option<i64>,
+    title: String,
+    author: String,
+    content: String,
+    score: f64,
+    created_at: DateTime<Utc>,
+}
+
+/// 文章和评论两部分搜索结果，参数依次为 FTS 查询、搜索范围
+const SEARCH_SQL: &str = "\
+    SELECT 'post' AS kind, p.id AS post_id, NULL AS comment_id, p.title AS title, u.username AS author, \
+           p.content AS content, -bm25(posts_fts, 10.0, 1.0) AS score, p.created_at AS created_at \
+    FROM posts_fts \
+        JOIN posts p ON p.id = posts_fts.rowid \
+        JOIN users u ON u.id = p.author_id \
+    WHERE ?2 IN ('all', 'posts') AND posts_fts MATCH ?1 \
+      AND p.deleted_at IS NULL AND p.hidden_at IS NULL AND p.status = 'published' \
+    UNION ALL \
+    SELECT 'comment', c.post_id, c.comment_id, p.title, u.username, \
+           c.content, -bm25(comments_fts), c.created_at \
+    FROM comments_fts \
+        JOIN comments c ON c.comment_id = comments_fts.rowid \
+        JOIN posts p ON p.id = c.post_id \
+        JOIN users u ON u.id = c.author_id \
+    WHERE ?2 IN ('all', 'comments') AND comments_fts MATCH ?1 \
+      AND c.deleted_at IS NULL AND c.hidden_at IS NULL AND c.status = 'approved' \
+      AND p.deleted_at IS NULL AND p.hidden_at IS NULL AND p.status = 'published'";
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    params(
+        ("q" = String, Query, description = "搜索关键字，多个关键字之间为“与”的关系"),
+        ("scope" = Option<String>, Query, description = "搜索范围: all、posts 或 comments，默认 all"),
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页数量")
+    ),
+    responses(
+        (status = 200, description = "按相关度排序的搜索结果", body = PaginatedResponse<SearchResult>),
+        (status = 400, description = "搜索参数无效", body = ErrorResponse)
+    ),
+    tag = "Search"
+)]
+pub async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<PaginatedResponse<SearchResult>>, AppError> {
+    query.validate().map_err(|validation_errors| {
+        AppError::validation(format_validation_errors(&validation_errors))
+    })?;
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let Some(fts_query) = build_match_query(&query.q) else {
+        return Err(AppError::validation("搜索关键字不能为空"));
+    };
+    let scope = match query.scope {
+        SearchScope::All => "all",
+        SearchScope::Posts => "posts",
+        SearchScope::Comments => "comments",
+    };
+
+    let total: (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM ({SEARCH_SQL})"))
+        .bind(&fts_query)
+        .bind(scope)
+        .fetch_one(&state.pool)
+        .await?;
+    let total = total.0 as u64;
+
+    let offset = (pagination.page - 1) * pagination.page_size;
+    let total_pages = total.div_ceil(pagination.page_size);
+
+    let rows = sqlx::query_as::<_, SearchRow>(&format!(
+        "{SEARCH_SQL} ORDER BY score DESC LIMIT ?3 OFFSET ?4"
+    ))
+    .bind(&fts_query)
+    .bind(scope)
+    .bind(pagination.page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let results = rows
+        .into_iter()
+        .map(|row| {
+            // 文章正文中没有关键字时说明匹配的是标题，摘要改用标题
+            let source = if row.kind == "post" && !contains_any_term(&row.content, &query.q) {
+                &row.title
+            } else {
+                &row.content
+            };
+            SearchResult {
+                snippet: make_snippet(source, &query.q, SNIPPET_CHARS),
+                kind: row.kind,
+                post_id: row.post_id,
+                comment_id: row.comment_id,
+                title: row.title,
+                author: row.author,
+                score: row.score,
+                created_at: row.created_at,
+            }
+        })
+        .collect();
+
+    let response = PaginatedResponse {
+        data: results,
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        total_pages,
+    };
+
+    Ok(Json(response))
+}
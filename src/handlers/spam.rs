@@ -0,0 +1,98 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    models::{AppState, BlocklistEntry, CreateBlocklistEntry},
+    spam::normalize_blocklist_value,
+    utils::{check_delete_result, created_response},
+    validation::ValidatedJson,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+#[utoipa::path(
+    get,
+    path = "/comments/blocklist",
+    responses(
+        (status = 200, description = "列出所有屏蔽词和屏蔽域名", body = [BlocklistEntry]),
+        (status = 403, description = "无权限操作", body = ErrorResponse)
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_blocklist(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<BlocklistEntry>>, AppError> {
+    let entries =
+        sqlx::query_as::<_, BlocklistEntry>("SELECT * FROM spam_blocklist ORDER BY kind, value")
+            .fetch_all(&state.pool)
+            .await?;
+
+    Ok(Json(entries))
+}
+
+#[utoipa::path(
+    post,
+    path = "/comments/blocklist",
+    request_body = CreateBlocklistEntry,
+    responses(
+        (status = 201, description = "成功添加屏蔽条目", body = BlocklistEntry),
+        (status = 400, description = "屏蔽内容无效", body = ErrorResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 409, description = "屏蔽条目已存在", body = ErrorResponse)
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn create_blocklist_entry(
+    State(state): State<AppState>,
+    json_payload: Json<CreateBlocklistEntry>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = json_payload.validate_json()?;
+    let value = normalize_blocklist_value(payload.kind, &payload.value)
+        .ok_or_else(|| AppError::validation("屏蔽内容无效"))?;
+
+    let entry = sqlx::query_as::<_, BlocklistEntry>(
+        "INSERT INTO spam_blocklist (kind, value) VALUES (?, ?) \
+         ON CONFLICT (kind, value) DO NOTHING RETURNING *",
+    )
+    .bind(payload.kind)
+    .bind(&value)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::conflict(format!("屏蔽条目 {} 已存在", value)))?;
+
+    Ok(created_response(entry))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/comments/blocklist/{id}",
+    params(("id" = u64, Path, description = "屏蔽条目 ID")),
+    responses(
+        (status = 204, description = "成功删除屏蔽条目"),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到屏蔽条目", body = ErrorResponse)
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn delete_blocklist_entry(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM spam_blocklist WHERE id = ?")
+        .bind(id as i64)
+        .execute(&state.pool)
+        .await?;
+
+    check_delete_result(result, "Blocklist entry")
+}
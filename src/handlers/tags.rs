@@ -0,0 +1,188 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    handlers::posts::list_posts,
+    models::{AppState, Pagination, PostSort, PostStatus, RenameTag, TagPageResponse, TagResponse},
+    utils::slugify,
+    validation::{ValidatedJson, format_validation_errors},
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use sqlx::SqliteConnection;
+use validator::Validate;
+
+/// 标签信息，`post_count` 只统计已发布且未删除的文章
+const TAG_SELECT: &str = "SELECT t.name, t.slug, \
+    (SELECT COUNT(*) FROM post_tags pt JOIN posts p ON p.id = pt.post_id \
+     WHERE pt.tag_id = t.id AND p.deleted_at IS NULL AND p.hidden_at IS NULL AND p.status = 'published') AS post_count \
+    FROM tags t";
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    responses(
+        (status = 200, description = "列出所有带有已发布文章的标签，按文章数量排序", body = [TagResponse])
+    ),
+    tag = "Tags"
+)]
+pub async fn get_tags(State(state): State<AppState>) -> Result<Json<Vec<TagResponse>>, AppError> {
+    let tags = sqlx::query_as::<_, TagResponse>(&format!(
+        "SELECT * FROM ({TAG_SELECT}) WHERE post_count > 0 ORDER BY post_count DESC, slug"
+    ))
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(tags))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tags/{slug}",
+    params(
+        ("slug" = String, Path, description = "标签的 slug"),
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页数量")
+    ),
+    responses(
+        (status = 200, description = "标签信息及其下已发布的文章", body = TagPageResponse),
+        (status = 404, description = "未找到标签", body = ErrorResponse)
+    ),
+    tag = "Tags"
+)]
+pub async fn get_tag(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<TagPageResponse>, AppError> {
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let tag = find_tag(&state, &slug).await?;
+    let posts = list_posts(
+        &state,
+        PostStatus::Published,
+        None,
+        Some(&tag.slug),
+        PostSort::Oldest,
+        &pagination,
+    )
+    .await?;
+
+    Ok(Json(TagPageResponse { tag, posts }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/tags/{slug}",
+    params(("slug" = String, Path, description = "标签的 slug")),
+    request_body = RenameTag,
+    responses(
+        (status = 200, description = "成功重命名标签，slug 随名称更新", body = TagResponse),
+        (status = 400, description = "标签名称无效", body = ErrorResponse),
+        (status = 403, description = "无权限操作", body = ErrorResponse),
+        (status = 404, description = "未找到标签", body = ErrorResponse),
+        (status = 409, description = "已存在同名标签", body = ErrorResponse)
+    ),
+    tag = "Tags",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn rename_tag(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    json_payload: Json<RenameTag>,
+) -> Result<Json<TagResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let name = normalize_tag_name(&payload.name);
+    let new_slug = slugify(&name);
+    if new_slug.is_empty() {
+        return Err(AppError::validation("标签必须包含字母、数字或文字"));
+    }
+
+    let tag = find_tag(&state, &slug).await?;
+
+    let taken = sqlx::query("SELECT 1 FROM tags WHERE slug = ? AND slug <> ?")
+        .bind(&new_slug)
+        .bind(&tag.slug)
+        .fetch_optional(&state.pool)
+        .await?;
+    if taken.is_some() {
+        return Err(AppError::conflict(format!("标签 {} 已存在", new_slug)));
+    }
+
+    sqlx::query("UPDATE tags SET name = ?, slug = ? WHERE slug = ?")
+        .bind(&name)
+        .bind(&new_slug)
+        .bind(&tag.slug)
+        .execute(&state.pool)
+        .await?;
+
+    let tag = find_tag(&state, &new_slug).await?;
+    Ok(Json(tag))
+}
+
+/// 替换文章的全部标签，不存在的标签会自动创建，返回整理后的标签名称
+pub(crate) async fn set_post_tags(
+    conn: &mut SqliteConnection,
+    post_id: i64,
+    tags: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query("DELETE FROM post_tags WHERE post_id = ?")
+        .bind(post_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for name in tags.iter().map(|tag| normalize_tag_name(tag)) {
+        let slug = slugify(&name);
+        if slug.is_empty() {
+            continue;
+        }
+        // slug 相同的标签视为同一个，沿用最先创建时的名称
+        sqlx::query("INSERT INTO tags (name, slug) VALUES (?, ?) ON CONFLICT (slug) DO NOTHING")
+            .bind(&name)
+            .bind(&slug)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO post_tags (post_id, tag_id) SELECT ?, id FROM tags WHERE slug = ?",
+        )
+        .bind(post_id)
+        .bind(&slug)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    load_post_tags(conn, post_id).await
+}
+
+/// 读取文章的标签名称，按 slug 排序
+pub(crate) async fn load_post_tags(
+    conn: &mut SqliteConnection,
+    post_id: i64,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT t.name FROM post_tags pt JOIN tags t ON t.id = pt.tag_id WHERE pt.post_id = ? ORDER BY t.slug",
+    )
+    .bind(post_id)
+    .fetch_all(conn)
+    .await
+}
+
+/// 去掉标签首尾的空白，并把中间连续的空白合并为一个空格
+fn normalize_tag_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+async fn find_tag(state: &AppState, slug: &str) -> Result<TagResponse, AppError> {
+    sqlx::query_as::<_, TagResponse>(&format!("{TAG_SELECT} WHERE t.slug = ?"))
+        .bind(slug)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("标签未找到"))
+}
@@ -0,0 +1,358 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    handlers::auth::{issue_tokens, suspension_message},
+    login_throttle::{begin_login_attempt, client_ip},
+    models::{
+        AppState, Claims, DisableTwoFactor, RecoveryCodesResponse, TokenResponse,
+        TwoFactorChallenge, TwoFactorCode, TwoFactorLogin, TwoFactorSetupResponse, User,
+    },
+    two_factor,
+    utils::{generate_token, hash_token, verify_password},
+    validation::ValidatedJson,
+};
+use axum::{
+    Extension, Json,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{Duration, Utc};
+use std::net::SocketAddr;
+
+/// 一个登录挑战最多可以提交的验证码次数，超过后需要重新输入密码
+const MAX_CHALLENGE_ATTEMPTS: i64 = 5;
+
+#[utoipa::path(
+    post,
+    path = "/login/2fa",
+    request_body = TwoFactorLogin,
+    responses(
+        (status = 200, description = "验证通过，返回令牌", body = TokenResponse),
+        (status = 401, description = "登录挑战无效、已过期或验证码错误", body = ErrorResponse),
+        (status = 403, description = "账户已被停用", body = ErrorResponse),
+        (status = 429, description = "登录失败次数过多，用户名或客户端地址被临时锁定", body = ErrorResponse)
+    ),
+    tag = "Two-Factor"
+)]
+pub async fn login_two_factor(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    json_payload: Json<TwoFactorLogin>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let ip = client_ip(&state.config, peer, &headers);
+    let now = Utc::now();
+
+    let challenge: (i64, i64) = sqlx::query_as(
+        "SELECT id, user_id FROM two_factor_challenges \
+         WHERE token_hash = ? AND used_at IS NULL AND expires_at > ? AND attempts < ?",
+    )
+    .bind(hash_token(&payload.challenge_token))
+    .bind(now)
+    .bind(MAX_CHALLENGE_ATTEMPTS)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::authentication("登录挑战无效或已过期，请重新登录"))?;
+    let (challenge_id, user_id) = challenge;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if user.is_suspended() {
+        return Err(AppError::authorization(suspension_message(&user)));
+    }
+
+    // 错误的验证码与错误的密码一样计入登录失败，重新输入密码获取新的挑战也不能继续猜测
+    let attempt = begin_login_attempt(&state.pool, &state.config, &user.username, ip).await?;
+    if !verify_second_factor(&state, &user, &payload.code).await? {
+        sqlx::query("UPDATE two_factor_challenges SET attempts = attempts + 1 WHERE id = ?")
+            .bind(challenge_id)
+            .execute(&state.pool)
+            .await?;
+        return Err(AppError::authentication("验证码错误"));
+    }
+
+    // 并发提交同一个挑战时只有一个请求能拿到令牌
+    let result = sqlx::query(
+        "UPDATE two_factor_challenges SET used_at = ? WHERE id = ? AND used_at IS NULL",
+    )
+    .bind(now)
+    .bind(challenge_id)
+    .execute(&state.pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        attempt.release(&state.pool).await?;
+        return Err(AppError::authentication("登录挑战无效或已过期，请重新登录"));
+    }
+
+    attempt.succeed(&state.pool).await?;
+    let tokens = issue_tokens(&state, &user).await?;
+
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/setup",
+    responses(
+        (status = 200, description = "生成新的密钥，提交验证码确认后才会启用", body = TwoFactorSetupResponse),
+        (status = 401, description = "未认证", body = ErrorResponse),
+        (status = 409, description = "已经启用了两步验证", body = ErrorResponse)
+    ),
+    tag = "Two-Factor",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn setup_two_factor(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<TwoFactorSetupResponse>, AppError> {
+    let user = find_user(&state, &claims).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::conflict("已经启用了两步验证"));
+    }
+
+    // 重复调用会替换尚未确认的密钥
+    let secret = two_factor::generate_secret();
+    sqlx::query("UPDATE users SET totp_pending_secret = ? WHERE id = ?")
+        .bind(&secret)
+        .bind(user.id)
+        .execute(&state.pool)
+        .await?;
+
+    let otpauth_uri =
+        two_factor::provisioning_uri(&secret, &state.config.totp_issuer, &user.username)
+            .ok_or_else(|| AppError::validation("TOTP_ISSUER 不能包含冒号"))?;
+
+    Ok(Json(TwoFactorSetupResponse {
+        secret,
+        otpauth_uri,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/confirm",
+    request_body = TwoFactorCode,
+    responses(
+        (status = 200, description = "成功启用两步验证，返回只显示一次的恢复码", body = RecoveryCodesResponse),
+        (status = 400, description = "尚未生成密钥或验证码错误", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse),
+        (status = 409, description = "已经启用了两步验证", body = ErrorResponse)
+    ),
+    tag = "Two-Factor",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn confirm_two_factor(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<TwoFactorCode>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let user = find_user(&state, &claims).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::conflict("已经启用了两步验证"));
+    }
+    let Some(secret) = &user.totp_pending_secret else {
+        return Err(AppError::validation("请先生成两步验证密钥"));
+    };
+
+    let now = Utc::now();
+    let step = two_factor::verify_code(secret, &payload.code, None, now)
+        .ok_or_else(|| AppError::validation("验证码错误"))?;
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query(
+        "UPDATE users SET totp_secret = totp_pending_secret, totp_pending_secret = NULL, \
+         totp_enabled_at = ?, totp_last_step = ? WHERE id = ?",
+    )
+    .bind(now)
+    .bind(step)
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await?;
+    let recovery_codes = replace_recovery_codes(&mut tx, user.id).await?;
+    tx.commit().await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/2fa",
+    request_body = DisableTwoFactor,
+    responses(
+        (status = 204, description = "成功关闭两步验证"),
+        (status = 400, description = "未启用两步验证，或者密码、验证码错误", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Two-Factor",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn disable_two_factor(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<DisableTwoFactor>,
+) -> Result<StatusCode, AppError> {
+    let payload = json_payload.validate_json()?;
+    let user = find_user(&state, &claims).await?;
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::validation("尚未启用两步验证"));
+    }
+    if !verify_password(&payload.password, &user.password_hash).await? {
+        return Err(AppError::validation("密码错误"));
+    }
+    if !verify_second_factor(&state, &user, &payload.code).await? {
+        return Err(AppError::validation("验证码错误"));
+    }
+
+    clear_two_factor(&state, user.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/recovery-codes",
+    request_body = TwoFactorCode,
+    responses(
+        (status = 200, description = "生成新的恢复码，之前的恢复码全部失效", body = RecoveryCodesResponse),
+        (status = 400, description = "未启用两步验证或验证码错误", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Two-Factor",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<TwoFactorCode>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let user = find_user(&state, &claims).await?;
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::validation("尚未启用两步验证"));
+    }
+    if !verify_second_factor(&state, &user, &payload.code).await? {
+        return Err(AppError::validation("验证码错误"));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let recovery_codes = replace_recovery_codes(&mut tx, user.id).await?;
+    tx.commit().await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// 为通过密码验证的用户签发登录挑战
+pub(crate) async fn create_login_challenge(
+    state: &AppState,
+    user: &User,
+) -> Result<TwoFactorChallenge, AppError> {
+    let ttl = Duration::minutes(state.config.two_factor_challenge_expiration_minutes);
+    let token = generate_token();
+    sqlx::query(
+        "INSERT INTO two_factor_challenges (user_id, token_hash, expires_at) VALUES (?, ?, ?)",
+    )
+    .bind(user.id)
+    .bind(hash_token(&token))
+    .bind(Utc::now() + ttl)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(TwoFactorChallenge {
+        two_factor_required: true,
+        challenge_token: token,
+        expires_in: ttl.num_seconds(),
+    })
+}
+
+/// 关闭用户的两步验证，删除恢复码和尚未完成的登录挑战
+pub(crate) async fn clear_two_factor(state: &AppState, user_id: i64) -> Result<(), AppError> {
+    let mut tx = state.pool.begin().await?;
+    sqlx::query(
+        "UPDATE users SET totp_secret = NULL, totp_pending_secret = NULL, totp_enabled_at = NULL, \
+         totp_last_step = NULL WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM two_factor_challenges WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 检查验证码或恢复码，通过后将其标记为已使用
+async fn verify_second_factor(state: &AppState, user: &User, code: &str) -> Result<bool, AppError> {
+    let Some(secret) = &user.totp_secret else {
+        return Ok(false);
+    };
+
+    if let Some(step) = two_factor::verify_code(secret, code, user.totp_last_step, Utc::now()) {
+        // 条件更新保证同一个验证码在并发请求中也只能使用一次
+        let result = sqlx::query(
+            "UPDATE users SET totp_last_step = ?1 \
+             WHERE id = ?2 AND (totp_last_step IS NULL OR totp_last_step < ?1)",
+        )
+        .bind(step)
+        .bind(user.id)
+        .execute(&state.pool)
+        .await?;
+        return Ok(result.rows_affected() > 0);
+    }
+
+    let result = sqlx::query(
+        "UPDATE two_factor_recovery_codes SET used_at = ? \
+         WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(user.id)
+    .bind(hash_token(&two_factor::normalize_recovery_code(code)))
+    .execute(&state.pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 删除用户现有的恢复码并生成一组新的，返回恢复码原文
+async fn replace_recovery_codes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+) -> Result<Vec<String>, AppError> {
+    sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    let codes = two_factor::generate_recovery_codes();
+    for code in &codes {
+        sqlx::query("INSERT INTO two_factor_recovery_codes (user_id, code_hash) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(hash_token(&two_factor::normalize_recovery_code(code)))
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(codes)
+}
+
+async fn find_user(state: &AppState, claims: &Claims) -> Result<User, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+        .bind(&claims.sub)
+        .fetch_one(&state.pool)
+        .await?;
+    Ok(user)
+}
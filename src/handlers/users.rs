@@ -0,0 +1,313 @@
+use crate::{
+    errors::{AppError, ErrorResponse},
+    handlers::posts::{POST_RESPONSE_SELECT, list_posts},
+    models::{
+        AppState, Claims, FeedQuery, FeedResponse, MeResponse, PaginatedResponse, Pagination,
+        PostFilter, PostResponse, PostSort, PostStatus, UpdateProfile, User, UserProfile,
+    },
+    permissions::Permission,
+    utils::check_delete_result,
+    validation::{ValidatedJson, format_validation_errors},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use validator::Validate;
+
+/// 关注动态中的文章：关注的作者已发布、未删除且未被隐藏的文章，参数 ?1 为当前用户 ID
+const FEED_FILTER_SQL: &str = "p.author_id IN (SELECT followee_id FROM follows WHERE follower_id = ?1) \
+    AND p.status = 'published' AND p.deleted_at IS NULL AND p.hidden_at IS NULL";
+
+#[utoipa::path(
+    get,
+    path = "/users/{username}",
+    params(("username" = String, Path, description = "用户名")),
+    responses(
+        (status = 200, description = "用户的公开资料", body = UserProfile),
+        (status = 404, description = "未找到用户", body = ErrorResponse)
+    ),
+    tag = "Users"
+)]
+pub async fn get_user_profile(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+) -> Result<Json<UserProfile>, AppError> {
+    let profile = sqlx::query_as::<_, UserProfile>(
+        "SELECT u.username, u.display_name, u.bio, u.avatar_url, u.website, u.created_at, \
+         (SELECT COUNT(*) FROM posts p WHERE p.author_id = u.id AND p.status = 'published' \
+            AND p.deleted_at IS NULL AND p.hidden_at IS NULL) AS post_count, \
+         (SELECT COUNT(*) FROM follows f WHERE f.followee_id = u.id) AS follower_count, \
+         (SELECT COUNT(*) FROM follows f WHERE f.follower_id = u.id) AS following_count \
+         FROM users u WHERE u.username = ?",
+    )
+    .bind(&username)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("用户未找到"))?;
+
+    Ok(Json(profile))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{username}/posts",
+    params(
+        ("username" = String, Path, description = "用户名"),
+        ("page" = Option<u64>, Query, description = "页码"),
+        ("page_size" = Option<u64>, Query, description = "每页数量"),
+        ("status" = Option<PostStatus>, Query, description = "按发布状态筛选，非 published 状态仅本人和编辑可以查看"),
+        ("tag" = Option<String>, Query, description = "按标签的 slug 筛选"),
+        ("sort" = Option<PostSort>, Query, description = "排序方式，默认 oldest")
+    ),
+    responses(
+        (status = 200, description = "分页列出该用户的文章", body = PaginatedResponse<PostResponse>),
+        (status = 401, description = "查看未发布的文章需要认证", body = ErrorResponse),
+        (status = 403, description = "无权查看该用户未发布的文章", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse)
+    ),
+    tag = "Users"
+)]
+pub async fn get_user_posts(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(username): Path<String>,
+    Query(pagination): Query<Pagination>,
+    Query(filter): Query<PostFilter>,
+) -> Result<Json<PaginatedResponse<PostResponse>>, AppError> {
+    pagination.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let user = find_user(&state, &username).await?;
+    let status = filter.status.unwrap_or(PostStatus::Published);
+
+    // 未发布的文章只对作者本人和编辑可见
+    match (status, claims.as_ref()) {
+        (PostStatus::Published, _) => {}
+        (_, None) => return Err(AppError::authentication("查看未发布的文章需要登录")),
+        (_, Some(Extension(claims)))
+            if claims.sub == user.username || claims.has_permission(Permission::ManageAnyPost) => {}
+        (_, Some(_)) => return Err(AppError::authorization("无权查看该用户未发布的文章")),
+    }
+
+    let response = list_posts(
+        &state,
+        status,
+        Some(&user.username),
+        filter.tag.as_deref(),
+        filter.sort,
+        &pagination,
+    )
+    .await?;
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    responses(
+        (status = 200, description = "当前登录用户的资料", body = MeResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Users",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_me(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<MeResponse>, AppError> {
+    let user = find_user(&state, &claims.sub).await?;
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/me",
+    request_body = UpdateProfile,
+    responses(
+        (status = 200, description = "成功修改个人资料", body = MeResponse),
+        (status = 400, description = "资料数据无效", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Users",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn update_me(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    json_payload: Json<UpdateProfile>,
+) -> Result<Json<MeResponse>, AppError> {
+    let payload = json_payload.validate_json()?;
+    let user = find_user(&state, &claims.sub).await?;
+
+    let user: User = sqlx::query_as(
+        "UPDATE users SET display_name = ?, bio = ?, avatar_url = ?, website = ? \
+         WHERE id = ? RETURNING *",
+    )
+    .bind(payload.display_name.unwrap_or(user.display_name))
+    .bind(payload.bio.unwrap_or(user.bio))
+    .bind(payload.avatar_url.unwrap_or(user.avatar_url))
+    .bind(payload.website.unwrap_or(user.website))
+    .bind(user.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/{username}/follow",
+    params(("username" = String, Path, description = "要关注的用户名")),
+    responses(
+        (status = 204, description = "成功关注，重复关注不做改动"),
+        (status = 400, description = "不能关注自己", body = ErrorResponse),
+        (status = 404, description = "未找到用户", body = ErrorResponse)
+    ),
+    tag = "Users",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn follow_user(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    if username == claims.sub {
+        return Err(AppError::validation("不能关注自己"));
+    }
+    let follower = find_user(&state, &claims.sub).await?;
+    let followee = find_user(&state, &username).await?;
+
+    sqlx::query(
+        "INSERT INTO follows (follower_id, followee_id) VALUES (?, ?) ON CONFLICT DO NOTHING",
+    )
+    .bind(follower.id)
+    .bind(followee.id)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{username}/follow",
+    params(("username" = String, Path, description = "要取消关注的用户名")),
+    responses(
+        (status = 204, description = "成功取消关注"),
+        (status = 404, description = "未关注该用户", body = ErrorResponse)
+    ),
+    tag = "Users",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn unfollow_user(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query(
+        "DELETE FROM follows \
+         WHERE follower_id = (SELECT id FROM users WHERE username = ?) \
+           AND followee_id = (SELECT id FROM users WHERE username = ?)",
+    )
+    .bind(&claims.sub)
+    .bind(&username)
+    .execute(&state.pool)
+    .await?;
+
+    check_delete_result(result, "Follow")
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/feed",
+    params(
+        ("before" = Option<i64>, Query, description = "上一页返回的 next_cursor"),
+        ("limit" = Option<u64>, Query, description = "每页数量")
+    ),
+    responses(
+        (status = 200, description = "关注的作者发布的文章，最新发布的在前", body = FeedResponse),
+        (status = 400, description = "分页参数或游标无效", body = ErrorResponse),
+        (status = 401, description = "未认证", body = ErrorResponse)
+    ),
+    tag = "Users",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_feed(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Json<FeedResponse>, AppError> {
+    query.validate().map_err(|validation_errors| {
+        AppError::validation(format!(
+            "分页参数{}",
+            format_validation_errors(&validation_errors)
+        ))
+    })?;
+
+    let user = find_user(&state, &claims.sub).await?;
+
+    // 按 (published_at, id) 做键集分页，游标是上一页最后一篇文章的 ID
+    let cursor = match query.before {
+        Some(before) => Some(
+            sqlx::query_as::<_, (String, i64)>(
+                "SELECT published_at, id FROM posts WHERE id = ? AND published_at IS NOT NULL",
+            )
+            .bind(before)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::validation("分页游标无效"))?,
+        ),
+        None => None,
+    };
+    let (cursor_published_at, cursor_id) = cursor.unzip();
+
+    let mut posts = sqlx::query_as::<_, PostResponse>(&format!(
+        "{POST_RESPONSE_SELECT} WHERE {FEED_FILTER_SQL} \
+           AND (?2 IS NULL OR (p.published_at, p.id) < (?2, ?3)) \
+         ORDER BY p.published_at DESC, p.id DESC LIMIT ?4"
+    ))
+    .bind(user.id)
+    .bind(cursor_published_at)
+    .bind(cursor_id)
+    .bind(query.limit as i64 + 1)
+    .fetch_all(&state.pool)
+    .await?;
+
+    // 多取一篇用来判断是否还有下一页
+    let next_cursor = if posts.len() as u64 > query.limit {
+        posts.truncate(query.limit as usize);
+        posts.last().map(|post| post.id)
+    } else {
+        None
+    };
+
+    Ok(Json(FeedResponse {
+        data: posts,
+        next_cursor,
+    }))
+}
+
+async fn find_user(state: &AppState, username: &str) -> Result<User, AppError> {
+    sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("用户未找到"))
+}
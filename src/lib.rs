@@ -2,8 +2,17 @@ pub mod config;
 pub mod docs;
 pub mod errors;
 pub mod handlers;
+pub mod login_throttle;
+pub mod mail;
+pub mod markdown;
 pub mod models;
+pub mod permissions;
 pub mod routes;
+pub mod scheduler;
+pub mod search;
+pub mod slugs;
+pub mod spam;
+pub mod two_factor;
 pub mod utils;
 pub mod validation;
 
@@ -0,0 +1,228 @@
+//! 登录失败限制
+//!
+//! 按提交的用户名和客户端地址分别统计登录失败的次数。失败次数达到阈值后临时锁定，
+//! 之后每多失败一次锁定时间加倍，直到配置的上限。用户名不存在时同样统计，
+//! 避免通过是否被锁定判断用户是否存在。
+//!
+//! 每次尝试在验证密码之前就计为失败，验证通过后再撤销，并发的猜测也不能超过阈值。
+//! 登录成功后清零该用户名的计数；客户端地址的计数只会过期，以免攻击者用自己的账户登录来重置。
+
+use crate::{config::Config, errors::AppError};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use std::net::{IpAddr, SocketAddr};
+
+const ACCOUNT_SCOPE: &str = "account";
+const IP_SCOPE: &str = "ip";
+
+/// 超过该时间没有再失败时重新开始计数
+const FAILURE_WINDOW_HOURS: i64 = 24;
+
+/// 请求的客户端地址，开启 `trust_proxy_headers` 时使用 `X-Forwarded-For` 中最后一个地址
+pub fn client_ip(config: &Config, peer: SocketAddr, headers: &HeaderMap) -> IpAddr {
+    if config.trust_proxy_headers {
+        // 最后一个地址由最近的代理添加，之前的地址都可能是客户端伪造的
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .last()
+            .and_then(|addr| addr.trim().parse().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+/// 一次已经预先计为失败的登录尝试
+///
+/// 验证密码较慢，先检查后记录会让并发的猜测在锁定生效前全部通过，
+/// 因此在验证之前就在同一个事务中检查锁定并增加计数，验证通过后再撤销。
+pub struct LoginAttempt {
+    username: String,
+    ip_key: String,
+    /// 每个范围在本次尝试之前和之后的锁定时间，撤销时只恢复仍由本次尝试设置的锁定
+    account_locked_until: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+    ip_locked_until: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+}
+
+/// 开始一次登录尝试：用户名或客户端地址处于锁定期时返回 429，否则先按失败计数
+pub async fn begin_login_attempt(
+    pool: &SqlitePool,
+    config: &Config,
+    username: &str,
+    ip: IpAddr,
+) -> Result<LoginAttempt, AppError> {
+    let now = Utc::now();
+    let window_start = now - Duration::hours(FAILURE_WINDOW_HOURS);
+    let ip_key = ip_key(ip);
+    let mut tx = pool.begin().await?;
+
+    // 第一条语句就是写操作，事务立即取得写锁，并发的尝试依次执行；顺便清理已经过期的记录
+    sqlx::query(
+        "DELETE FROM login_failures WHERE last_failed_at < ?1 \
+         AND (locked_until IS NULL OR locked_until < ?2)",
+    )
+    .bind(window_start)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    let locked_until: Option<(DateTime<Utc>,)> = sqlx::query_as(
+        "SELECT locked_until FROM login_failures \
+         WHERE ((scope = ?1 AND key = ?2) OR (scope = ?3 AND key = ?4)) AND locked_until > ?5 \
+         ORDER BY locked_until DESC LIMIT 1",
+    )
+    .bind(ACCOUNT_SCOPE)
+    .bind(username)
+    .bind(IP_SCOPE)
+    .bind(&ip_key)
+    .bind(now)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some((locked_until,)) = locked_until {
+        let retry_after = (locked_until - now).num_seconds().max(0) as u64 + 1;
+        return Err(AppError::too_many_requests(
+            format!("登录失败次数过多，请在 {} 秒后重试", retry_after),
+            retry_after,
+        ));
+    }
+
+    let mut reserved = Vec::with_capacity(2);
+    let targets = [
+        (
+            ACCOUNT_SCOPE,
+            username,
+            config.login_max_failures_per_account,
+        ),
+        (IP_SCOPE, ip_key.as_str(), config.login_max_failures_per_ip),
+    ];
+    for (scope, key, threshold) in targets {
+        let (failures, previous): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+            "INSERT INTO login_failures (scope, key, failures, last_failed_at) VALUES (?1, ?2, 1, ?3) \
+             ON CONFLICT (scope, key) DO UPDATE SET \
+             failures = CASE WHEN last_failed_at < ?4 THEN 1 ELSE failures + 1 END, \
+             last_failed_at = ?3 \
+             RETURNING failures, locked_until",
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(now)
+        .bind(window_start)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut locked_until = previous;
+        if let Some(duration) = lockout_duration(config, failures, threshold) {
+            locked_until = Some(now + duration);
+            sqlx::query("UPDATE login_failures SET locked_until = ? WHERE scope = ? AND key = ?")
+                .bind(locked_until)
+                .bind(scope)
+                .bind(key)
+                .execute(&mut *tx)
+                .await?;
+            tracing::warn!(
+                scope,
+                key,
+                failures,
+                seconds = duration.num_seconds(),
+                "登录失败次数过多，已临时锁定"
+            );
+        }
+        reserved.push((previous, locked_until));
+    }
+    tx.commit().await?;
+
+    Ok(LoginAttempt {
+        username: username.to_string(),
+        ip_key,
+        account_locked_until: reserved[0],
+        ip_locked_until: reserved[1],
+    })
+}
+
+impl LoginAttempt {
+    /// 登录成功：清零用户名的失败计数，并撤销本次尝试对客户端地址的计数
+    pub async fn succeed(self, pool: &SqlitePool) -> Result<(), AppError> {
+        clear_login_failures(pool, &self.username).await?;
+        undo_failure(pool, IP_SCOPE, &self.ip_key, self.ip_locked_until).await
+    }
+
+    /// 密码正确但还需要第二步验证：撤销本次尝试的计数，之前的失败仍然保留，
+    /// 直到第二步验证也通过
+    pub async fn release(self, pool: &SqlitePool) -> Result<(), AppError> {
+        undo_failure(
+            pool,
+            ACCOUNT_SCOPE,
+            &self.username,
+            self.account_locked_until,
+        )
+        .await?;
+        undo_failure(pool, IP_SCOPE, &self.ip_key, self.ip_locked_until).await
+    }
+}
+
+/// 撤销一次预先记录的失败，本次尝试设置的锁定恢复为之前的值
+async fn undo_failure(
+    pool: &SqlitePool,
+    scope: &str,
+    key: &str,
+    (previous, reserved): (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE login_failures SET failures = max(failures - 1, 0), \
+         locked_until = CASE WHEN locked_until IS ?1 THEN ?2 ELSE locked_until END \
+         WHERE scope = ?3 AND key = ?4",
+    )
+    .bind(reserved)
+    .bind(previous)
+    .bind(scope)
+    .bind(key)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 清零用户名的失败计数并解除锁定
+pub async fn clear_login_failures(pool: &SqlitePool, username: &str) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM login_failures WHERE scope = ? AND key = ?")
+        .bind(ACCOUNT_SCOPE)
+        .bind(username)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 第 `threshold` 次失败锁定 `login_lockout_base_seconds` 秒，之后每次加倍，不超过上限；
+/// `threshold` 为 0 时不锁定
+fn lockout_duration(config: &Config, failures: i64, threshold: i64) -> Option<Duration> {
+    if threshold <= 0 || failures < threshold {
+        return None;
+    }
+    let doublings = (failures - threshold).min(30) as u32;
+    let seconds = config
+        .login_lockout_base_seconds
+        .saturating_mul(1 << doublings)
+        .min(config.login_lockout_max_minutes * 60);
+    Some(Duration::seconds(seconds))
+}
+
+/// IPv6 按 /64 网段统计，同一网段内的地址通常属于同一个客户端
+fn ip_key(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => ip.to_string(),
+            None => {
+                let segments = ip.segments();
+                format!(
+                    "{:x}:{:x}:{:x}:{:x}::/64",
+                    segments[0], segments[1], segments[2], segments[3]
+                )
+            }
+        },
+    }
+}
@@ -0,0 +1,167 @@
+//! 邮件发送
+//!
+//! 业务代码通过 [`Mailer`] 发送由 [`templates`] 渲染的邮件，邮件先写入持久化的发送队列，
+//! 再交给 [`MailTransport`] 投递，投递失败的邮件由后台任务按退避时间重试。
+//! 具体的投递方式由配置决定：生产环境使用 [`SmtpTransport`]，开发和测试环境使用本地发件箱，
+//! [`FileOutbox`] 把邮件写成 `.eml` 文件，[`MemoryOutbox`] 把邮件保存在内存中供测试读取。
+
+pub mod queue;
+pub mod smtp;
+pub mod templates;
+
+pub use queue::{Mailer, spawn_mail_worker};
+pub use smtp::SmtpTransport;
+pub use templates::Template;
+
+use crate::config::Config;
+use lettre::message::{Mailbox, MultiPart, SinglePart};
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+/// 发送失败的原因，具体类型取决于发送方式
+pub type MailError = Box<dyn std::error::Error + Send + Sync>;
+
+/// 发送邮件返回的 Future
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<(), MailError>> + Send + 'a>>;
+
+/// 一封邮件，包含纯文本正文和可选的 HTML 正文
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+/// 邮件的投递方式
+pub trait MailTransport: Send + Sync {
+    fn send<'a>(&'a self, message: &'a MailMessage) -> SendFuture<'a>;
+}
+
+/// 可以通过 `MAIL_TRANSPORT` 选择的投递方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailTransportKind {
+    /// 写入 `MAIL_OUTBOX_DIR` 目录
+    File,
+    /// 保存在内存中，重启后丢失
+    Memory,
+    /// 通过 `SMTP_URL` 指定的服务器发送
+    Smtp,
+}
+
+impl FromStr for MailTransportKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Self::File),
+            "memory" => Ok(Self::Memory),
+            "smtp" => Ok(Self::Smtp),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 根据配置创建投递方式
+pub fn transport_from_config(config: &Config) -> Result<Arc<dyn MailTransport>, String> {
+    Ok(match config.mail_transport {
+        MailTransportKind::File => {
+            Arc::new(FileOutbox::new(&config.mail_outbox_dir, &config.mail_from))
+        }
+        MailTransportKind::Memory => Arc::new(MemoryOutbox::default()),
+        MailTransportKind::Smtp => {
+            let url = config
+                .smtp_url
+                .as_deref()
+                .ok_or_else(|| "SMTP_URL must be set when MAIL_TRANSPORT is smtp".to_string())?;
+            Arc::new(
+                SmtpTransport::from_url(url, &config.mail_from)
+                    .map_err(|e| format!("Invalid SMTP_URL: {}", e))?,
+            )
+        }
+    })
+}
+
+/// 生成符合 MIME 格式的邮件，有 HTML 正文时同时附带纯文本正文
+pub(crate) fn build_message(
+    from: &str,
+    message: &MailMessage,
+) -> Result<lettre::Message, MailError> {
+    let builder = lettre::Message::builder()
+        .from(from.parse::<Mailbox>()?)
+        .to(message.to.parse::<Mailbox>()?)
+        .subject(&message.subject);
+
+    let email = match &message.html_body {
+        Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+            message.text_body.clone(),
+            html.clone(),
+        ))?,
+        None => builder.singlepart(SinglePart::plain(message.text_body.clone()))?,
+    };
+    Ok(email)
+}
+
+/// 把每封邮件写成发件箱目录下的一个 `.eml` 文件
+pub struct FileOutbox {
+    dir: PathBuf,
+    from: String,
+}
+
+impl FileOutbox {
+    pub fn new(dir: impl Into<PathBuf>, from: &str) -> Self {
+        Self {
+            dir: dir.into(),
+            from: from.to_string(),
+        }
+    }
+}
+
+impl MailTransport for FileOutbox {
+    fn send<'a>(&'a self, message: &'a MailMessage) -> SendFuture<'a> {
+        Box::pin(async move {
+            let contents = build_message(&self.from, message)?.formatted();
+            tokio::fs::create_dir_all(&self.dir).await?;
+            // 文件名以时间开头，按名称排序即为发送顺序
+            let path = self.dir.join(format!(
+                "{}-{}.eml",
+                chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+                Uuid::new_v4()
+            ));
+            tokio::fs::write(path, contents).await?;
+            Ok(())
+        })
+    }
+}
+
+/// 把邮件保存在内存中，测试通过 [`MemoryOutbox::messages`] 读取
+#[derive(Default)]
+pub struct MemoryOutbox {
+    messages: Mutex<Vec<MailMessage>>,
+}
+
+impl MemoryOutbox {
+    /// 已发送的全部邮件，按发送顺序排列
+    pub fn messages(&self) -> Vec<MailMessage> {
+        self.messages
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl MailTransport for MemoryOutbox {
+    fn send<'a>(&'a self, message: &'a MailMessage) -> SendFuture<'a> {
+        self.messages
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(message.clone());
+        Box::pin(async { Ok(()) })
+    }
+}
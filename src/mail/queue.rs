@@ -0,0 +1,150 @@
+use super::{MailMessage, MailTransport};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+/// 最多尝试投递的次数，之后邮件标记为 `failed` 不再重试
+pub const MAX_ATTEMPTS: i64 = 6;
+
+/// 发送队列中的一封邮件
+#[derive(sqlx::FromRow)]
+struct QueuedMail {
+    id: i64,
+    to_address: String,
+    subject: String,
+    text_body: String,
+    html_body: Option<String>,
+    attempts: i64,
+}
+
+/// 第 `attempts` 次投递失败后到下一次重试的间隔：1、2、4、8…… 分钟
+fn retry_delay(attempts: i64) -> Duration {
+    Duration::minutes(1 << (attempts - 1).clamp(0, 10))
+}
+
+/// 把邮件写入发送队列并立即尝试投递
+pub struct Mailer {
+    transport: Arc<dyn MailTransport>,
+}
+
+impl Mailer {
+    pub fn new(transport: Arc<dyn MailTransport>) -> Self {
+        Self { transport }
+    }
+
+    /// 发送一封邮件
+    ///
+    /// 邮件先持久化再投递，投递失败不会返回错误，而是留在队列中等待 [`spawn_mail_worker`] 重试。
+    /// 正文只在等待重试期间保存，投递成功或放弃重试后清除，队列中只留下收件人和主题。
+    pub async fn send(&self, pool: &SqlitePool, message: MailMessage) -> Result<(), sqlx::Error> {
+        // 第一次投递由当前请求完成，重试时间推迟一个间隔，避免后台任务同时投递
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO mail_queue (to_address, subject, text_body, html_body, next_attempt_at) \
+             VALUES (?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(&message.to)
+        .bind(&message.subject)
+        .bind(&message.text_body)
+        .bind(&message.html_body)
+        .bind(Utc::now() + retry_delay(1))
+        .fetch_one(pool)
+        .await?;
+
+        self.deliver(pool, id, 0, &message).await.map(|_| ())
+    }
+
+    /// 重试所有到期的邮件，返回本次投递成功的数量
+    pub async fn retry_due(&self, pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+        let due: Vec<QueuedMail> = sqlx::query_as(
+            "SELECT id, to_address, subject, text_body, html_body, attempts FROM mail_queue \
+             WHERE status = 'pending' AND next_attempt_at <= ? ORDER BY id",
+        )
+        .bind(Utc::now())
+        .fetch_all(pool)
+        .await?;
+
+        let mut sent = 0;
+        for mail in due {
+            let message = MailMessage {
+                to: mail.to_address,
+                subject: mail.subject,
+                text_body: mail.text_body,
+                html_body: mail.html_body,
+            };
+            if self.deliver(pool, mail.id, mail.attempts, &message).await? {
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// 投递一封邮件并记录结果，`attempts` 为之前已经尝试的次数，返回是否投递成功
+    async fn deliver(
+        &self,
+        pool: &SqlitePool,
+        id: i64,
+        attempts: i64,
+        message: &MailMessage,
+    ) -> Result<bool, sqlx::Error> {
+        let attempts = attempts + 1;
+        let now = Utc::now();
+        match self.transport.send(message).await {
+            Ok(()) => {
+                // 正文中有重置密码、验证邮箱的链接，不再需要重试后立即清除
+                sqlx::query(
+                    "UPDATE mail_queue SET status = 'sent', attempts = ?, sent_at = ?, last_error = NULL, \
+                     text_body = '', html_body = NULL WHERE id = ?",
+                )
+                .bind(attempts)
+                .bind(now)
+                .bind(id)
+                .execute(pool)
+                .await?;
+                Ok(true)
+            }
+            Err(e) => {
+                let status = if attempts >= MAX_ATTEMPTS {
+                    "failed"
+                } else {
+                    "pending"
+                };
+                let next_attempt_at: DateTime<Utc> = now + retry_delay(attempts);
+                tracing::warn!(id, attempts, error = %e, "邮件投递失败");
+                // 放弃重试时同样清除正文
+                sqlx::query(
+                    "UPDATE mail_queue SET status = ?1, attempts = ?2, last_error = ?3, next_attempt_at = ?4, \
+                     text_body = CASE WHEN ?1 = 'failed' THEN '' ELSE text_body END, \
+                     html_body = CASE WHEN ?1 = 'failed' THEN NULL ELSE html_body END \
+                     WHERE id = ?5",
+                )
+                .bind(status)
+                .bind(attempts)
+                .bind(e.to_string())
+                .bind(next_attempt_at)
+                .bind(id)
+                .execute(pool)
+                .await?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// 启动后台任务，按固定间隔重试投递失败的邮件
+pub fn spawn_mail_worker(
+    pool: SqlitePool,
+    mailer: Arc<Mailer>,
+    interval: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match mailer.retry_due(&pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "已重新投递邮件"),
+                Err(e) => tracing::error!(error = ?e, "重试投递邮件失败"),
+            }
+        }
+    })
+}
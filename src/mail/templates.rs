@@ -0,0 +1,121 @@
+use super::MailMessage;
+
+/// 系统发送的各类邮件
+///
+/// 每个模板同时生成纯文本和 HTML 正文，新的通知类邮件在这里增加变体即可。
+pub enum Template<'a> {
+    /// 找回密码，`link` 为重置密码页面的完整地址
+    PasswordReset {
+        username: &'a str,
+        link: &'a str,
+        expires_in_minutes: i64,
+    },
+    /// 验证邮箱，`link` 为验证页面的完整地址
+    VerifyEmail {
+        username: &'a str,
+        link: &'a str,
+        expires_in_hours: i64,
+    },
+}
+
+/// 模板正文中的一段内容
+enum Block<'a> {
+    Paragraph(String),
+    Link(&'a str),
+}
+
+impl Template<'_> {
+    /// 生成发送给 `to` 的邮件
+    pub fn render(&self, to: &str) -> MailMessage {
+        let (subject, username, blocks) = match self {
+            Template::PasswordReset {
+                username,
+                link,
+                expires_in_minutes,
+            } => (
+                "重置你的密码",
+                *username,
+                vec![
+                    Block::Paragraph(format!(
+                        "我们收到了重置你的账户密码的请求。请打开下面的链接设置新密码，链接在 {} 分钟内有效，且只能使用一次：",
+                        expires_in_minutes
+                    )),
+                    Block::Link(link),
+                    Block::Paragraph("如果这不是你本人的操作，请忽略这封邮件。".to_string()),
+                ],
+            ),
+            Template::VerifyEmail {
+                username,
+                link,
+                expires_in_hours,
+            } => (
+                "验证你的邮箱",
+                *username,
+                vec![
+                    Block::Paragraph(format!(
+                        "请打开下面的链接确认这是你的邮箱地址，链接在 {} 小时内有效：",
+                        expires_in_hours
+                    )),
+                    Block::Link(link),
+                    Block::Paragraph(
+                        "如果你没有在本站填写过这个邮箱，请忽略这封邮件。".to_string(),
+                    ),
+                ],
+            ),
+        };
+
+        MailMessage {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            text_body: render_text(username, &blocks),
+            html_body: Some(render_html(subject, username, &blocks)),
+        }
+    }
+}
+
+fn render_text(username: &str, blocks: &[Block]) -> String {
+    let mut body = format!("{}，你好：\n", username);
+    for block in blocks {
+        body.push('\n');
+        match block {
+            Block::Paragraph(text) => body.push_str(text),
+            Block::Link(link) => body.push_str(link),
+        }
+        body.push('\n');
+    }
+    body
+}
+
+fn render_html(subject: &str, username: &str, blocks: &[Block]) -> String {
+    let mut body = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>\n<p>{}，你好：</p>\n",
+        escape_html(subject),
+        escape_html(username)
+    );
+    for block in blocks {
+        match block {
+            Block::Paragraph(text) => body.push_str(&format!("<p>{}</p>\n", escape_html(text))),
+            Block::Link(link) => {
+                let link = escape_html(link);
+                body.push_str(&format!("<p><a href=\"{}\">{}</a></p>\n", link, link));
+            }
+        }
+    }
+    body.push_str("</body>\n</html>\n");
+    body
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
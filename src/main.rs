@@ -1,7 +1,17 @@
 use axum::Router;
 use dotenvy::dotenv;
-use inkwell::{AppState, Config, create_router, docs::ApiDoc};
+use inkwell::{
+    AppState, Config, create_router,
+    docs::ApiDoc,
+    mail::{Mailer, spawn_mail_worker, transport_from_config},
+    markdown::ensure_rendered_revisions,
+    scheduler::spawn_post_scheduler,
+    search::ensure_search_index,
+    slugs::ensure_post_slugs,
+    spam::SpamFilter,
+};
 use sqlx::sqlite::SqlitePoolOptions;
+use std::{net::SocketAddr, sync::Arc};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use utoipa::OpenApi;
@@ -31,9 +41,42 @@ async fn main() {
         .await
         .expect("Can't connect to database");
 
+    // 为升级前创建的文章生成 slug
+    ensure_post_slugs(&pool)
+        .await
+        .expect("Failed to generate post slugs");
+
+    // 渲染升级前保存的修订
+    ensure_rendered_revisions(&pool)
+        .await
+        .expect("Failed to render post revisions");
+
+    // 分词方式变化或数据被直接修改后，重建全文索引
+    ensure_search_index(&pool)
+        .await
+        .expect("Failed to build search index");
+
+    // 启动定时发布任务
+    spawn_post_scheduler(
+        pool.clone(),
+        std::time::Duration::from_secs(config.scheduler_interval_seconds),
+    );
+
+    // 启动邮件重试任务
+    let mailer = Arc::new(Mailer::new(
+        transport_from_config(&config).expect("Failed to configure mail transport"),
+    ));
+    spawn_mail_worker(
+        pool.clone(),
+        mailer.clone(),
+        std::time::Duration::from_secs(config.mail_retry_interval_seconds),
+    );
+
     let app_state = AppState {
         pool,
         config: config.clone(),
+        spam_filter: Arc::new(SpamFilter::default()),
+        mailer,
     };
 
     let app = Router::new()
@@ -49,5 +92,11 @@ async fn main() {
         "API 文档地址: http://{}/swagger-ui",
         config.server_address()
     );
-    axum::serve(listener, app).await.unwrap();
+    // 登录限制需要知道客户端地址
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
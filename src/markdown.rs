@@ -0,0 +1,92 @@
+//! 文章正文的 Markdown 渲染
+//!
+//! 支持 CommonMark 以及 GFM 的表格、脚注、删除线和任务列表，代码块带有
+//! `language-*` 类名供前端语法高亮使用。渲染结果经过白名单过滤，用户输入的 HTML
+//! 不能注入脚本或事件处理器。渲染后的 HTML 保存在每个修订中，读取文章时不再重复渲染。
+
+use ammonia::Builder;
+use pulldown_cmark::{Options, Parser, html};
+use sqlx::SqlitePool;
+use std::borrow::Cow;
+
+/// 正文中 `id` 属性的前缀，避免与页面中的元素重名 (DOM clobbering)
+const ID_PREFIX: &str = "user-content-";
+
+lazy_static::lazy_static! {
+    static ref SANITIZER: Builder<'static> = {
+        let mut builder = Builder::default();
+        builder
+            .add_tags(["input"])
+            .add_tag_attributes("code", ["class"])
+            .add_tag_attributes("div", ["class", "id"])
+            .add_tag_attributes("sup", ["class"])
+            .add_tag_attributes("th", ["style"])
+            .add_tag_attributes("td", ["style"])
+            .add_tag_attributes("input", ["type", "checked", "disabled"])
+            .id_prefix(Some(ID_PREFIX))
+            .attribute_filter(filter_attribute);
+        builder
+    };
+}
+
+/// 将 Markdown 渲染为经过过滤的 HTML
+pub fn render_markdown(source: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut unsafe_html = String::with_capacity(source.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, Parser::new_ext(source, options));
+
+    SANITIZER.clean(&unsafe_html).to_string()
+}
+
+/// 进一步限制白名单中属性的取值，只保留渲染器自己会生成的形式
+fn filter_attribute<'a>(element: &str, attribute: &str, value: &'a str) -> Option<Cow<'a, str>> {
+    let allowed = match (element, attribute) {
+        ("code", "class") => value.strip_prefix("language-").is_some_and(|language| {
+            !language.is_empty()
+                && language
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || "-_+#".contains(ch))
+        }),
+        ("div", "class") => value == "footnote-definition",
+        ("sup", "class") => matches!(value, "footnote-reference" | "footnote-definition-label"),
+        ("th" | "td", "style") => matches!(
+            value,
+            "text-align: left" | "text-align: center" | "text-align: right"
+        ),
+        ("input", "type") => value == "checkbox",
+        // 脚注引用指向加了前缀的 id
+        ("a", "href") => {
+            if let Some(fragment) = value.strip_prefix('#') {
+                return Some(Cow::Owned(format!("#{}{}", ID_PREFIX, fragment)));
+            }
+            true
+        }
+        _ => true,
+    };
+    allowed.then_some(Cow::Borrowed(value))
+}
+
+/// 为升级前创建、还没有渲染结果的修订生成 HTML
+pub async fn ensure_rendered_revisions(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let revisions: Vec<(i64, String)> =
+        sqlx::query_as("SELECT id, content FROM post_revisions WHERE content_html IS NULL")
+            .fetch_all(pool)
+            .await?;
+    if revisions.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("Rendering {} post revisions......", revisions.len());
+    let mut tx = pool.begin().await?;
+    for (id, content) in revisions {
+        sqlx::query("UPDATE post_revisions SET content_html = ? WHERE id = ?")
+            .bind(render_markdown(&content))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
@@ -1,7 +1,11 @@
 use crate::config::Config;
+use crate::mail::Mailer;
+use crate::permissions::{Permission, Role, TokenScope};
+use crate::spam::SpamFilter;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
 use utoipa::ToSchema;
 use validator::Validate;
 
@@ -14,6 +18,10 @@ lazy_static::lazy_static! {
 pub struct AppState {
     pub pool: SqlitePool,
     pub config: Config,
+    /// 新评论使用的垃圾评论检测流程
+    pub spam_filter: Arc<SpamFilter>,
+    /// 通过发送队列投递邮件
+    pub mailer: Arc<Mailer>,
 }
 
 /// 分页查询参数
@@ -37,17 +45,63 @@ pub fn default_page_size() -> u64 {
     10
 }
 
+/// 文章的发布状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum PostStatus {
+    /// 草稿，仅作者和编辑可见
+    Draft,
+    /// 定时发布，到达 `published_at` 后自动发布
+    Scheduled,
+    /// 已发布，所有人可见
+    Published,
+    /// 已归档，不再出现在公开列表中
+    Archived,
+}
+
+/// 新评论的审核方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ModerationMode {
+    /// 评论直接公开
+    Open,
+    /// 从未有评论通过审核的用户需要等待审核
+    FirstTime,
+    /// 所有评论都需要等待审核
+    All,
+}
+
+impl FromStr for ModerationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(ModerationMode::Open),
+            "first_time" => Ok(ModerationMode::FirstTime),
+            "all" => Ok(ModerationMode::All),
+            other => Err(format!("未知的审核方式: {}", other)),
+        }
+    }
+}
+
 /// 文章的数据模型
 #[derive(Serialize, Deserialize, Clone, sqlx::FromRow, ToSchema)]
 pub struct Post {
     pub id: i64,
     pub title: String,
+    /// 由标题生成的唯一 slug，可用于 `/posts/by-slug/{slug}`
+    pub slug: String,
     pub author_id: i64,
     pub content: String,
-    pub tags: String,
     pub copyright: String,
     pub created_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub status: PostStatus,
+    pub published_at: Option<DateTime<Utc>>,
+    /// 该文章的评论审核方式，为空表示沿用站点设置
+    pub comment_moderation: Option<ModerationMode>,
 }
 
 /// 用于API响应的文章结构，包含作者用户名
@@ -55,25 +109,182 @@ pub struct Post {
 pub struct PostResponse {
     pub id: i64,
     pub title: String,
+    #[schema(example = "guan-xi-xing-shu-ju-ku-she-ji")]
+    pub slug: String,
     pub author: String,
+    /// Markdown 原文
     pub content: String,
-    pub tags: String,
+    /// 渲染并过滤后的 HTML
+    #[schema(example = "<p>文章内容</p>")]
+    pub content_html: String,
+    /// 标签名称，按 slug 排序
+    #[sqlx(json)]
+    pub tags: Vec<String>,
     pub copyright: String,
     pub created_at: DateTime<Utc>,
+    pub status: PostStatus,
+    pub published_at: Option<DateTime<Utc>>,
+    /// 该文章的评论审核方式，为空表示沿用站点设置
+    pub comment_moderation: Option<ModerationMode>,
+    /// 因举报被隐藏，仅作者本人和编辑可见
+    pub hidden: bool,
+    /// 各种表情回应的数量，没有人回应的表情不出现
+    #[sqlx(json)]
+    pub reactions: BTreeMap<ReactionKind, i64>,
 }
 
-impl From<(Post, User)> for PostResponse {
-    fn from((post, user): (Post, User)) -> Self {
-        Self {
-            id: post.id,
-            title: post.title,
-            author: user.username,
-            content: post.content,
-            tags: post.tags,
-            copyright: post.copyright,
-            created_at: post.created_at,
-        }
-    }
+/// 修改文章的评论审核方式时接收的数据
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateCommentModeration {
+    /// 为空表示沿用站点设置
+    pub mode: Option<ModerationMode>,
+}
+
+/// 列出文章时的筛选条件
+#[derive(Deserialize, ToSchema)]
+pub struct PostFilter {
+    /// 按发布状态筛选，默认只列出已发布的文章
+    pub status: Option<PostStatus>,
+    /// 按标签的 slug 筛选
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub sort: PostSort,
+}
+
+/// 文章列表的排序方式
+#[derive(Deserialize, ToSchema, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PostSort {
+    /// 最早创建的在前
+    #[default]
+    Oldest,
+    /// 表情回应最多的在前
+    Top,
+}
+
+/// 标签及其下已发布文章的数量
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
+pub struct TagResponse {
+    #[schema(example = "Web Dev")]
+    pub name: String,
+    #[schema(example = "web-dev")]
+    pub slug: String,
+    pub post_count: i64,
+}
+
+/// 标签页面，包含标签信息和该标签下分页的文章
+#[derive(Serialize, ToSchema)]
+pub struct TagPageResponse {
+    pub tag: TagResponse,
+    pub posts: PaginatedResponse<PostResponse>,
+}
+
+/// 重命名标签时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct RenameTag {
+    #[validate(length(min = 1, max = 30, message = "标签长度必须在 1-30 字符之间"))]
+    pub name: String,
+}
+
+/// 文章修订的数据模型，保存某一时刻文章内容的完整快照
+#[derive(Clone, sqlx::FromRow)]
+pub struct PostRevision {
+    pub id: i64,
+    pub post_id: i64,
+    pub revision_number: i64,
+    /// 修改者，用户被删除后为空
+    pub editor_id: Option<i64>,
+    pub title: String,
+    pub content: String,
+    pub content_html: String,
+    /// 标签名称，按 slug 排序
+    #[sqlx(json)]
+    pub tags: Vec<String>,
+    pub copyright: String,
+    pub restored_from: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 修订列表中的摘要信息，不包含正文
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
+pub struct PostRevisionSummary {
+    pub revision_number: i64,
+    pub editor: Option<String>,
+    pub title: String,
+    /// 如果该修订由回滚产生，记录被恢复的修订号
+    pub restored_from: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 单个修订的完整内容
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
+pub struct PostRevisionResponse {
+    pub post_id: i64,
+    pub revision_number: i64,
+    pub editor: Option<String>,
+    pub title: String,
+    pub content: String,
+    /// 标签名称，按 slug 排序
+    #[sqlx(json)]
+    pub tags: Vec<String>,
+    pub copyright: String,
+    pub restored_from: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 比较两个修订时的查询参数
+#[derive(Deserialize, ToSchema)]
+pub struct RevisionDiffQuery {
+    /// 旧修订号
+    pub from: i64,
+    /// 新修订号
+    pub to: i64,
+}
+
+/// 差异行的类型
+#[derive(Serialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// 差异中的一行
+#[derive(Serialize, ToSchema)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// 单个字段的逐行差异
+#[derive(Serialize, ToSchema)]
+pub struct FieldDiff {
+    pub field: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// 两个修订之间的差异，只包含有变化的字段
+#[derive(Serialize, ToSchema)]
+pub struct RevisionDiffResponse {
+    pub from: i64,
+    pub to: i64,
+    pub changes: Vec<FieldDiff>,
+}
+
+/// 评论的审核状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum CommentStatus {
+    /// 等待审核，仅评论作者本人可见
+    Pending,
+    /// 已通过，所有人可见
+    Approved,
+    /// 已拒绝
+    Rejected,
+    /// 已标记为垃圾评论
+    Spam,
 }
 
 /// 评论的数据模型
@@ -86,6 +297,11 @@ pub struct Comment {
     pub content: String,
     pub created_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// 回复的评论，顶层评论为空
+    pub parent_id: Option<i64>,
+    /// 嵌套层数，顶层评论为 0
+    pub depth: i64,
+    pub status: CommentStatus,
 }
 
 /// 用于API响应的评论结构，包含作者用户名
@@ -93,9 +309,17 @@ pub struct Comment {
 pub struct CommentResponse {
     pub id: i64,
     pub post_id: i64,
+    pub parent_id: Option<i64>,
+    pub depth: i64,
+    /// 评论已删除但仍有回复时，作者和内容显示为 `[deleted]`
+    pub deleted: bool,
+    pub status: CommentStatus,
     pub author: String,
     pub content: String,
     pub created_at: DateTime<Utc>,
+    /// 各种表情回应的数量，没有人回应的表情不出现
+    #[sqlx(json)]
+    pub reactions: BTreeMap<ReactionKind, i64>,
 }
 
 impl From<(Comment, User)> for CommentResponse {
@@ -103,13 +327,185 @@ impl From<(Comment, User)> for CommentResponse {
         Self {
             id: comment.id,
             post_id: comment.post_id,
+            parent_id: comment.parent_id,
+            depth: comment.depth,
+            deleted: comment.deleted_at.is_some(),
+            status: comment.status,
             author: user.username,
             content: comment.content,
             created_at: comment.created_at,
+            reactions: BTreeMap::new(),
         }
     }
 }
 
+/// 评论的排序方式，只作用于顶层评论，回复始终按时间先后排列
+#[derive(Deserialize, ToSchema, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentSort {
+    /// 最早发表的在前
+    #[default]
+    Oldest,
+    /// 最新发表的在前
+    Newest,
+    /// 表情回应最多的在前，数量相同时回复多的在前
+    Top,
+}
+
+/// 列出评论时的查询参数
+#[derive(Deserialize, ToSchema)]
+pub struct CommentQuery {
+    #[serde(default)]
+    pub sort: CommentSort,
+}
+
+/// 审核队列的筛选条件
+#[derive(Deserialize, ToSchema)]
+pub struct ModerationFilter {
+    /// 按审核状态筛选，默认只列出等待审核的评论
+    pub status: Option<CommentStatus>,
+    /// 只列出该文章下的评论
+    pub post_id: Option<i64>,
+}
+
+/// 批量审核评论时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ModerateComments {
+    #[schema(example = json!([1, 2, 3]))]
+    #[validate(length(min = 1, max = 100, message = "每次审核的评论数量必须在 1-100 之间"))]
+    pub comment_ids: Vec<i64>,
+    /// 审核结果，不能改回 `pending`
+    pub status: CommentStatus,
+}
+
+/// 批量审核的结果
+#[derive(Serialize, ToSchema)]
+pub struct ModerationResult {
+    /// 实际更新的评论数量，不存在或已删除的评论会被跳过
+    pub updated: u64,
+}
+
+/// 屏蔽列表条目的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum BlocklistKind {
+    /// 评论中出现该词时更可能是垃圾评论
+    Word,
+    /// 评论中链接指向该域名或其子域名时视为垃圾评论
+    Domain,
+}
+
+/// 屏蔽列表中的一个条目
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
+pub struct BlocklistEntry {
+    pub id: i64,
+    pub kind: BlocklistKind,
+    #[schema(example = "cheap-pills.example")]
+    pub value: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 添加屏蔽列表条目时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateBlocklistEntry {
+    pub kind: BlocklistKind,
+    #[schema(example = "cheap-pills.example")]
+    #[validate(length(min = 1, max = 100, message = "屏蔽内容长度必须在 1-100 字符之间"))]
+    pub value: String,
+}
+
+/// 被举报内容的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum ReportTarget {
+    Post,
+    Comment,
+}
+
+/// 举报原因的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ReportReason {
+    /// 垃圾广告
+    Spam,
+    /// 骚扰或人身攻击
+    Harassment,
+    /// 仇恨言论
+    HateSpeech,
+    /// 虚假信息
+    Misinformation,
+    /// 侵犯版权
+    Copyright,
+    /// 其他原因，需要填写说明
+    Other,
+}
+
+/// 举报的处理状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum ReportStatus {
+    /// 等待处理
+    Open,
+    /// 举报成立，内容保持隐藏
+    Resolved,
+    /// 举报不成立，内容恢复显示
+    Dismissed,
+}
+
+/// 举报文章或评论时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateReport {
+    pub reason: ReportReason,
+    /// 补充说明，原因为 `other` 时必填
+    #[schema(example = "评论中包含钓鱼链接")]
+    #[validate(length(max = 1000, message = "举报说明不能超过 1000 字符"))]
+    pub details: Option<String>,
+}
+
+/// 举报的处理结果说明
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ReportDecision {
+    #[schema(example = "已删除相关链接")]
+    #[validate(length(max = 1000, message = "处理说明不能超过 1000 字符"))]
+    pub note: Option<String>,
+}
+
+/// 举报信息
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
+pub struct ReportResponse {
+    pub id: i64,
+    pub target_type: ReportTarget,
+    pub target_id: i64,
+    /// 被举报内容所属的文章，内容已被彻底删除时为空
+    pub post_id: Option<i64>,
+    pub reporter: String,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    /// 同一内容尚未处理的举报数量
+    pub open_reports: i64,
+    /// 被举报的内容当前是否已被隐藏
+    pub target_hidden: bool,
+    pub created_at: DateTime<Utc>,
+    /// 处理该举报的编辑或管理员
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolution_note: Option<String>,
+}
+
+/// 举报列表的筛选条件
+#[derive(Deserialize, ToSchema)]
+pub struct ReportFilter {
+    /// 按处理状态筛选，默认只列出等待处理的举报
+    pub status: Option<ReportStatus>,
+    /// 按被举报内容的类型筛选
+    pub target_type: Option<ReportTarget>,
+}
+
 /// 创建新文章时接收的数据
 #[derive(Deserialize, Clone, ToSchema, Validate)]
 pub struct CreatePost {
@@ -117,10 +513,35 @@ pub struct CreatePost {
     pub title: String,
     #[validate(length(min = 1, max = 10000, message = "内容长度必须在 1-10000 字符之间"))]
     pub content: String,
-    #[validate(length(max = 200, message = "标签长度不能超过 200 字符"))]
-    pub tags: String,
+    #[schema(example = json!(["Rust", "Web Dev"]))]
+    #[serde(default)]
+    #[validate(
+        length(max = 10, message = "标签数量不能超过 10 个"),
+        custom(function = "validate_tags")
+    )]
+    pub tags: Vec<String>,
     #[validate(length(max = 200, message = "版权信息长度不能超过 200 字符"))]
     pub copyright: String,
+    /// 发布状态，创建时默认为已发布，更新时默认保持不变
+    pub status: Option<PostStatus>,
+    /// 定时发布的时间，仅在状态为 `scheduled` 时使用
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+/// 检查每个标签的长度，并且必须包含可以生成 slug 的字符
+fn validate_tags(tags: &[String]) -> Result<(), validator::ValidationError> {
+    for tag in tags {
+        let length = tag.trim().chars().count();
+        if !(1..=30).contains(&length) {
+            return Err(validator::ValidationError::new("tag_length")
+                .with_message("每个标签长度必须在 1-30 字符之间".into()));
+        }
+        if crate::utils::slugify(tag).is_empty() {
+            return Err(validator::ValidationError::new("tag_slug")
+                .with_message("标签必须包含字母、数字或文字".into()));
+        }
+    }
+    Ok(())
 }
 
 /// 创建新评论时接收的数据
@@ -128,6 +549,8 @@ pub struct CreatePost {
 pub struct CreateComment {
     #[validate(length(min = 1, max = 1000, message = "评论内容长度必须在 1-1000 字符之间"))]
     pub content: String,
+    /// 要回复的评论 ID，为空表示顶层评论；修改评论时忽略
+    pub parent_id: Option<i64>,
 }
 
 // --- 新增的用户认证相关模型 ---
@@ -141,6 +564,98 @@ pub struct User {
     pub username: String,
     pub password_hash: String,
     pub role: String,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
+    /// 注册时间，升级前注册的用户为空
+    pub created_at: Option<DateTime<Utc>>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub website: Option<String>,
+    /// 用于找回密码的邮箱，保存为小写
+    pub email: Option<String>,
+    /// 邮箱通过验证的时间，未验证或更换邮箱后为空
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// 已启用的两步验证密钥 (Base32)
+    pub totp_secret: Option<String>,
+    /// 等待用户确认的两步验证密钥
+    pub totp_pending_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    /// 最近一次使用的验证码所在的时间步
+    pub totp_last_step: Option<i64>,
+    /// 在此之前签发的访问令牌全部失效
+    pub tokens_valid_after: Option<DateTime<Utc>>,
+}
+
+impl User {
+    /// 判断用户当前是否处于停用状态，到期的临时停用视为已解除
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some() && self.suspended_until.is_none_or(|until| until > Utc::now())
+    }
+}
+
+/// 管理员查看的用户信息，不包含密码哈希
+#[derive(Serialize, ToSchema)]
+pub struct AdminUserResponse {
+    pub id: i64,
+    pub username: String,
+    pub role: String,
+    pub suspended: bool,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
+    pub two_factor_enabled: bool,
+}
+
+impl From<User> for AdminUserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            suspended: user.is_suspended(),
+            two_factor_enabled: user.totp_enabled_at.is_some(),
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            suspended_at: user.suspended_at,
+            suspended_until: user.suspended_until,
+            suspension_reason: user.suspension_reason,
+        }
+    }
+}
+
+/// 用户的账户状态
+#[derive(Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    Active,
+    Suspended,
+}
+
+/// 管理员列出用户时的筛选条件
+#[derive(Deserialize, ToSchema)]
+pub struct UserFilter {
+    /// 只列出该角色的用户
+    pub role: Option<Role>,
+    /// 只列出该状态的用户
+    pub status: Option<UserStatus>,
+    /// 用户名包含的关键字
+    pub q: Option<String>,
+}
+
+/// 修改用户角色时接收的数据
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateUserRole {
+    pub role: Role,
+}
+
+/// 停用用户时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SuspendUser {
+    #[schema(example = "发布垃圾广告")]
+    #[validate(length(min = 1, max = 500, message = "停用原因长度必须在 1-500 字符之间"))]
+    pub reason: String,
+    /// 停用截止时间，为空表示永久封禁
+    pub until: Option<DateTime<Utc>>,
 }
 
 /// 用户注册时接收的数据
@@ -153,6 +668,13 @@ pub struct RegisterUser {
     #[schema(example = "password123")]
     #[validate(length(min = 6, max = 100, message = "密码长度必须在 6-100 字符之间"))]
     pub password: String,
+    /// 用于找回密码，可以不填，填写后会收到验证邮件
+    #[schema(example = "new_user@example.com")]
+    #[validate(
+        email(message = "邮箱格式不正确"),
+        length(max = 254, message = "邮箱不能超过 254 字符")
+    )]
+    pub email: Option<String>,
 }
 
 /// 用户登录时接收的数据
@@ -166,11 +688,37 @@ pub struct LoginUser {
     pub password: String,
 }
 
-/// 登录成功后返回的 JWT 令牌
+/// 登录或刷新成功后返回的令牌对
 #[derive(Serialize, ToSchema)]
 pub struct TokenResponse {
+    /// 短期有效的 JWT 访问令牌
     #[schema(example = "a.very.long.jwt.token.string")]
     pub token: String,
+    /// 用于换取新访问令牌的刷新令牌，每次使用后都会轮换
+    #[schema(example = "3kFq9w0Zr8sVbN1xT2yLhJ5uPaC7dGeM4iKoQ6tW")]
+    pub refresh_token: String,
+    /// 访问令牌的有效期（秒）
+    #[schema(example = 900)]
+    pub expires_in: i64,
+}
+
+/// 刷新令牌或注销时提交的刷新令牌
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct RefreshTokenRequest {
+    #[validate(length(min = 1, max = 200, message = "刷新令牌不能为空且不能超过 200 字符"))]
+    pub refresh_token: String,
+}
+
+/// 刷新令牌的数据模型，直接映射数据库的 `refresh_tokens` 表
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RefreshToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub token_hash: String,
+    pub access_jti: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -178,6 +726,77 @@ pub struct Claims {
     pub sub: String,
     pub role: String,
     pub exp: usize,
+    /// 令牌的唯一 ID，用于注销时加入黑名单
+    pub jti: String,
+    /// 签发时间，精确到毫秒；升级前签发的令牌没有该字段
+    #[serde(default)]
+    pub iat: f64,
+    /// 角色要求启用两步验证但用户尚未启用，不写入令牌，每次请求时重新判断
+    #[serde(skip)]
+    pub two_factor_pending: bool,
+    /// 通过个人访问令牌认证时的授权范围，使用 JWT 时为空
+    #[serde(skip)]
+    pub token_scopes: Option<Vec<TokenScope>>,
+}
+
+impl Claims {
+    /// 按毫秒精度把时间转换为 JWT 使用的秒数
+    pub fn timestamp(time: DateTime<Utc>) -> f64 {
+        time.timestamp_millis() as f64 / 1000.0
+    }
+
+    /// 解析令牌中的角色，无法识别的角色按普通用户处理
+    pub fn role(&self) -> Role {
+        self.role.parse().unwrap_or(Role::User)
+    }
+
+    /// 判断令牌持有者是否拥有指定权限，尚未按要求启用两步验证时最多只拥有作者的权限
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        let allowed = self.role().has_permission(permission);
+        if self.two_factor_pending {
+            // 只能收回权限，角色低于作者时不能因此获得作者的权限
+            return allowed && Role::Author.has_permission(permission);
+        }
+        allowed
+    }
+}
+
+/// 搜索范围
+#[derive(Deserialize, ToSchema, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchScope {
+    #[default]
+    All,
+    Posts,
+    Comments,
+}
+
+/// 搜索的查询参数
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SearchQuery {
+    #[validate(length(min = 1, max = 200, message = "搜索关键字长度必须在 1-200 字符之间"))]
+    pub q: String,
+    #[serde(default)]
+    pub scope: SearchScope,
+}
+
+/// 单条搜索结果
+#[derive(Serialize, ToSchema)]
+pub struct SearchResult {
+    /// 结果类型: `post` 或 `comment`
+    #[schema(example = "post")]
+    pub kind: String,
+    pub post_id: i64,
+    pub comment_id: Option<i64>,
+    /// 文章标题，评论结果为所属文章的标题
+    pub title: String,
+    pub author: String,
+    /// 已做 HTML 转义的摘要，匹配的关键字用 `<mark>` 标出
+    #[schema(example = "关于 <mark>Rust</mark> 的一篇文章")]
+    pub snippet: String,
+    /// 相关度得分，越大越相关
+    pub score: f64,
+    pub created_at: DateTime<Utc>,
 }
 
 /// 分页响应结构
@@ -189,3 +808,327 @@ pub struct PaginatedResponse<T> {
     pub total: u64,
     pub total_pages: u64,
 }
+
+/// 读者可以对文章和评论使用的表情回应
+///
+/// 对应的表情依次为 👍 ❤️ 😄 😮 😢 😠
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum ReactionKind {
+    Like,
+    Love,
+    Laugh,
+    Wow,
+    Sad,
+    Angry,
+}
+
+/// 某篇文章或某条评论的表情回应汇总
+#[derive(Serialize, ToSchema)]
+pub struct ReactionSummary {
+    /// 各种表情回应的数量，没有人回应的表情不出现
+    pub reactions: BTreeMap<ReactionKind, i64>,
+    /// 当前用户使用过的表情
+    pub mine: Vec<ReactionKind>,
+}
+
+/// 用户的公开资料
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
+pub struct UserProfile {
+    pub username: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub website: Option<String>,
+    /// 注册时间，升级前注册的用户为空
+    pub created_at: Option<DateTime<Utc>>,
+    /// 已发布的文章数量
+    pub post_count: i64,
+    /// 关注该用户的人数
+    pub follower_count: i64,
+    /// 该用户关注的人数
+    pub following_count: i64,
+}
+
+/// 关注动态的查询参数
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct FeedQuery {
+    /// 上一页返回的 `next_cursor`，为空时从最新的文章开始
+    pub before: Option<i64>,
+    #[serde(default = "default_page_size")]
+    #[validate(range(min = 1, max = 100, message = "每页数量必须在 1-100 之间"))]
+    pub limit: u64,
+}
+
+/// 关注动态的一页，按游标而不是页码翻页
+#[derive(Serialize, ToSchema)]
+pub struct FeedResponse {
+    pub data: Vec<PostResponse>,
+    /// 获取下一页时作为 `before` 传入，没有更多文章时为空
+    pub next_cursor: Option<i64>,
+}
+
+/// 当前登录用户的资料
+#[derive(Serialize, ToSchema)]
+pub struct MeResponse {
+    pub username: String,
+    pub role: String,
+    pub email: Option<String>,
+    /// 邮箱是否已通过验证
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub website: Option<String>,
+    /// 注册时间，升级前注册的用户为空
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for MeResponse {
+    fn from(user: User) -> Self {
+        Self {
+            username: user.username,
+            role: user.role,
+            email: user.email,
+            email_verified: user.email_verified_at.is_some(),
+            two_factor_enabled: user.totp_enabled_at.is_some(),
+            display_name: user.display_name,
+            bio: user.bio,
+            avatar_url: user.avatar_url,
+            website: user.website,
+            created_at: user.created_at,
+        }
+    }
+}
+
+/// 修改个人资料时接收的数据，省略的字段保持不变，传入 `null` 清空该字段
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateProfile {
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[schema(value_type = Option<String>)]
+    #[validate(length(min = 1, max = 50, message = "显示名称长度必须在 1-50 字符之间"))]
+    pub display_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[schema(value_type = Option<String>)]
+    #[validate(length(max = 500, message = "个人简介不能超过 500 字符"))]
+    pub bio: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[schema(value_type = Option<String>, example = "https://example.com/avatar.png")]
+    #[validate(
+        length(max = 300, message = "头像地址不能超过 300 字符"),
+        custom(function = "validate_http_url")
+    )]
+    pub avatar_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[schema(value_type = Option<String>, example = "https://example.com")]
+    #[validate(
+        length(max = 300, message = "个人网站地址不能超过 300 字符"),
+        custom(function = "validate_http_url")
+    )]
+    pub website: Option<Option<String>>,
+}
+
+/// 区分省略的字段 (`None`) 和显式传入的 `null` (`Some(None)`)
+fn deserialize_nullable<'de, D>(deserializer: D) -> Result<Option<Option<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(Some)
+}
+
+/// 链接只接受 http 和 https 地址，避免在页面上渲染出 `javascript:` 之类的链接
+fn validate_http_url(url: &str) -> Result<(), validator::ValidationError> {
+    let valid = ["http://", "https://"].iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+            && url.len() > scheme.len()
+    }) && !url.chars().any(char::is_whitespace);
+    if !valid {
+        return Err(validator::ValidationError::new("http_url")
+            .with_message("必须是以 http:// 或 https:// 开头的地址".into()));
+    }
+    Ok(())
+}
+
+/// 修改密码时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ChangePassword {
+    #[validate(length(min = 1, max = 100, message = "当前密码不能为空且不能超过 100 字符"))]
+    pub current_password: String,
+    #[validate(length(min = 6, max = 100, message = "密码长度必须在 6-100 字符之间"))]
+    pub new_password: String,
+}
+
+/// 申请重置密码时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ForgotPassword {
+    #[schema(example = "new_user@example.com")]
+    #[validate(length(min = 1, max = 254, message = "邮箱不能为空且不能超过 254 字符"))]
+    pub email: String,
+}
+
+/// 使用重置令牌设置新密码时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ResetPassword {
+    #[validate(length(min = 1, max = 200, message = "重置令牌不能为空且不能超过 200 字符"))]
+    pub token: String,
+    #[validate(length(min = 6, max = 100, message = "密码长度必须在 6-100 字符之间"))]
+    pub new_password: String,
+}
+
+/// 修改邮箱时接收的数据，传入 `null` 删除邮箱
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateEmail {
+    #[schema(example = "new_user@example.com")]
+    #[validate(
+        email(message = "邮箱格式不正确"),
+        length(max = 254, message = "邮箱不能超过 254 字符")
+    )]
+    pub email: Option<String>,
+}
+
+/// 验证邮箱时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct VerifyEmail {
+    #[validate(length(min = 1, max = 200, message = "验证令牌不能为空且不能超过 200 字符"))]
+    pub token: String,
+}
+
+/// 登录的结果：未启用两步验证时直接返回令牌，否则返回登录挑战
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Tokens(TokenResponse),
+    TwoFactor(TwoFactorChallenge),
+}
+
+/// 密码验证通过但还需要提交两步验证码
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorChallenge {
+    /// 始终为 `true`，便于客户端区分两种登录结果
+    pub two_factor_required: bool,
+    /// 提交验证码时使用的一次性令牌
+    pub challenge_token: String,
+    /// 登录挑战的有效期（秒）
+    #[schema(example = 300)]
+    pub expires_in: i64,
+}
+
+/// 完成两步验证登录时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TwoFactorLogin {
+    #[validate(length(min = 1, max = 200, message = "登录挑战不能为空且不能超过 200 字符"))]
+    pub challenge_token: String,
+    /// 验证器应用中的 6 位验证码，或者一个未使用的恢复码
+    #[schema(example = "123456")]
+    #[validate(length(min = 1, max = 50, message = "验证码不能为空且不能超过 50 字符"))]
+    pub code: String,
+}
+
+/// 开始启用两步验证时返回的密钥
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorSetupResponse {
+    /// Base32 编码的密钥，供无法扫码时手动输入
+    pub secret: String,
+    /// 供验证器应用扫描的 `otpauth://` 地址
+    pub otpauth_uri: String,
+}
+
+/// 只包含一个验证码的请求
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TwoFactorCode {
+    #[schema(example = "123456")]
+    #[validate(length(min = 1, max = 50, message = "验证码不能为空且不能超过 50 字符"))]
+    pub code: String,
+}
+
+/// 关闭两步验证时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct DisableTwoFactor {
+    #[validate(length(min = 1, max = 100, message = "密码不能为空且不能超过 100 字符"))]
+    pub password: String,
+    /// 当前的验证码或一个未使用的恢复码
+    #[validate(length(min = 1, max = 50, message = "验证码不能为空且不能超过 50 字符"))]
+    pub code: String,
+}
+
+/// 新生成的恢复码，只在生成时返回一次
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// 数据库中的个人访问令牌
+#[derive(sqlx::FromRow)]
+pub struct PersonalAccessToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub token_prefix: String,
+    pub token_hash: String,
+    #[sqlx(json)]
+    pub scopes: Vec<TokenScope>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 返回给用户的个人访问令牌信息，不包含令牌本身
+#[derive(Serialize, ToSchema)]
+pub struct AccessTokenResponse {
+    pub id: i64,
+    pub name: String,
+    /// 令牌的开头几个字符，用于辨认令牌
+    #[schema(example = "inkpat_3kFq9")]
+    pub token_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    /// 最近一次使用的时间，精确到分钟
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// 过期时间，为空表示永不过期
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PersonalAccessToken> for AccessTokenResponse {
+    fn from(token: PersonalAccessToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            last_used_at: token.last_used_at,
+            expires_at: token.expires_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+/// 新创建的个人访问令牌，令牌原文只在这里返回一次
+#[derive(Serialize, ToSchema)]
+pub struct CreatedAccessToken {
+    #[serde(flatten)]
+    pub info: AccessTokenResponse,
+    /// 令牌原文，作为 `Authorization: Bearer` 使用
+    #[schema(example = "inkpat_3kFq9w0Zr8sVbN1xT2yLhJ5uPaC7dGeM4iKoQ6tW")]
+    pub token: String,
+}
+
+/// 创建个人访问令牌时接收的数据
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateAccessToken {
+    #[schema(example = "CI release notes")]
+    #[validate(length(min = 1, max = 100, message = "令牌名称长度必须在 1-100 字符之间"))]
+    pub name: String,
+    #[schema(example = json!(["posts:write"]))]
+    #[validate(length(min = 1, message = "至少需要一个授权范围"))]
+    pub scopes: Vec<TokenScope>,
+    /// 有效天数，省略表示永不过期
+    #[schema(example = 90)]
+    #[validate(range(min = 1, max = 3650, message = "有效天数必须在 1-3650 之间"))]
+    pub expires_in_days: Option<i64>,
+}
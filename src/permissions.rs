@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+
+/// 用户角色，对应 `users.role` 列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// 普通用户，只能发表和管理自己的评论
+    User,
+    /// 作者，可以发表和管理自己的文章
+    Author,
+    /// 编辑，可以管理所有文章和评论
+    Editor,
+    /// 管理员，拥有全部权限
+    Admin,
+}
+
+/// 需要在路由或处理器中检查的权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// 发表文章
+    CreatePost,
+    /// 修改或删除任何人的文章
+    ManageAnyPost,
+    /// 发表评论
+    CreateComment,
+    /// 修改或删除任何人的评论
+    ManageAnyComment,
+    /// 处理读者的举报
+    HandleReports,
+    /// 管理用户账户
+    ManageUsers,
+}
+
+/// 个人访问令牌的授权范围，使用令牌时只能执行范围内的写操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub enum TokenScope {
+    /// 发表、修改和删除文章
+    #[serde(rename = "posts:write")]
+    PostsWrite,
+    /// 发表、修改和删除评论
+    #[serde(rename = "comments:write")]
+    CommentsWrite,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::PostsWrite => "posts:write",
+            TokenScope::CommentsWrite => "comments:write",
+        }
+    }
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Author => "author",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// 判断该角色是否拥有指定权限
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::CreateComment => true,
+            Permission::CreatePost => matches!(self, Role::Author | Role::Editor | Role::Admin),
+            Permission::ManageAnyPost
+            | Permission::ManageAnyComment
+            | Permission::HandleReports => {
+                matches!(self, Role::Editor | Role::Admin)
+            }
+            Permission::ManageUsers => matches!(self, Role::Admin),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Role::User),
+            "author" => Ok(Role::Author),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("未知的角色: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
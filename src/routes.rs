@@ -1,8 +1,10 @@
 use crate::handlers::*;
 use crate::models::AppState;
+use crate::permissions::Permission;
 use axum::{
     Router,
-    routing::{get, post, put},
+    middleware::from_fn,
+    routing::{delete, get, post, put},
 };
 
 #[utoipa::path(
@@ -17,14 +19,120 @@ pub async fn root() -> &'static str {
 
 /// 创建一个总路由函数，供 main.rs 调用
 pub fn create_router(app_state: AppState) -> Router<AppState> {
-    let protected_routes = Router::new()
+    // 需要特定权限的路由，权限检查在认证之后执行
+    let author_routes = Router::new()
         .route("/posts", post(create_post))
-        .route("/posts/{id}", put(update_post).delete(delete_post))
+        .route_layer(from_fn(|req, next| {
+            require_permission(Permission::CreatePost, req, next)
+        }));
+
+    let commenter_routes = Router::new()
         .route("/posts/{id}/comments", post(create_comment_for_post))
+        .route_layer(from_fn(|req, next| {
+            require_permission(Permission::CreateComment, req, next)
+        }));
+
+    let editor_routes = Router::new()
+        .route("/tags/{slug}", put(rename_tag))
+        .route_layer(from_fn(|req, next| {
+            require_permission(Permission::ManageAnyPost, req, next)
+        }));
+
+    let moderator_routes = Router::new()
+        .route(
+            "/comments/moderation",
+            get(get_moderation_queue).post(moderate_comments),
+        )
+        .route(
+            "/comments/blocklist",
+            get(get_blocklist).post(create_blocklist_entry),
+        )
+        .route("/comments/blocklist/{id}", delete(delete_blocklist_entry))
+        .route_layer(from_fn(|req, next| {
+            require_permission(Permission::ManageAnyComment, req, next)
+        }));
+
+    let report_routes = Router::new()
+        .route("/admin/reports", get(list_reports))
+        .route("/admin/reports/{id}/resolve", post(resolve_report))
+        .route("/admin/reports/{id}/dismiss", post(dismiss_report))
+        .route_layer(from_fn(|req, next| {
+            require_permission(Permission::HandleReports, req, next)
+        }));
+
+    let admin_routes = Router::new()
+        .route("/admin/users", get(list_users))
+        .route("/admin/users/{id}", get(get_user).delete(delete_user))
+        .route("/admin/users/{id}/role", put(update_user_role))
+        .route("/admin/users/{id}/suspend", post(suspend_user))
+        .route("/admin/users/{id}/unsuspend", post(unsuspend_user))
+        .route("/admin/users/{id}/unlock", post(unlock_user))
+        .route("/admin/users/{id}/2fa", delete(reset_user_two_factor))
+        .route_layer(from_fn(|req, next| {
+            require_permission(Permission::ManageUsers, req, next)
+        }));
+
+    let protected_routes = Router::new()
+        .route("/logout", post(logout))
+        .route("/logout/all", post(logout_all))
+        .route("/posts/{id}", put(update_post).delete(delete_post))
+        .route(
+            "/posts/{id}/comment-moderation",
+            put(update_comment_moderation),
+        )
+        .route("/me", get(get_me).patch(update_me))
+        .route("/me/password", put(change_password))
+        .route("/me/email", put(update_email))
+        .route("/me/email/verification", post(resend_verification_email))
+        .route(
+            "/me/tokens",
+            get(get_access_tokens).post(create_access_token),
+        )
+        .route("/me/tokens/{id}", delete(delete_access_token))
+        .route("/me/2fa", delete(disable_two_factor))
+        .route("/me/2fa/setup", post(setup_two_factor))
+        .route("/me/2fa/confirm", post(confirm_two_factor))
+        .route("/me/2fa/recovery-codes", post(regenerate_recovery_codes))
+        .route("/me/bookmarks", get(get_bookmarks))
+        .route("/me/feed", get(get_feed))
+        .route(
+            "/users/{username}/follow",
+            put(follow_user).delete(unfollow_user),
+        )
+        .route(
+            "/posts/{id}/bookmark",
+            put(add_bookmark).delete(remove_bookmark),
+        )
+        .route("/posts/{id}/report", post(report_post))
+        .route(
+            "/posts/{id}/reactions/{kind}",
+            put(add_post_reaction).delete(remove_post_reaction),
+        )
+        .route(
+            "/posts/{post_id}/comments/{comment_id}/reactions/{kind}",
+            put(add_comment_reaction).delete(remove_comment_reaction),
+        )
+        .route(
+            "/posts/{post_id}/comments/{comment_id}/report",
+            post(report_comment),
+        )
+        .route("/posts/{id}/revisions", get(get_post_revisions))
+        .route("/posts/{id}/revisions/diff", get(diff_post_revisions))
+        .route("/posts/{id}/revisions/{revision}", get(get_post_revision))
+        .route(
+            "/posts/{id}/revisions/{revision}/restore",
+            post(restore_post_revision),
+        )
         .route(
             "/posts/{post_id}/comments/{comment_id}",
             put(update_comment).delete(delete_comment),
         )
+        .merge(author_routes)
+        .merge(commenter_routes)
+        .merge(editor_routes)
+        .merge(moderator_routes)
+        .merge(report_routes)
+        .merge(admin_routes)
         .layer(axum::middleware::from_fn_with_state(
             app_state.clone(),
             auth_middleware,
@@ -34,9 +142,24 @@ pub fn create_router(app_state: AppState) -> Router<AppState> {
         .route("/", get(root))
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/login/2fa", post(login_two_factor))
+        .route("/token/refresh", post(refresh_token))
+        .route("/password/forgot", post(forgot_password))
+        .route("/password/reset", post(reset_password))
+        .route("/email/verify", post(verify_email))
         .route("/posts", get(get_posts))
         .route("/posts/{id}", get(get_post_by_id))
-        .route("/posts/{id}/comments", get(get_comments_for_post));
+        .route("/posts/by-slug/{slug}", get(get_post_by_slug))
+        .route("/posts/{id}/comments", get(get_comments_for_post))
+        .route("/tags", get(get_tags))
+        .route("/tags/{slug}", get(get_tag))
+        .route("/search", get(search))
+        .route("/users/{username}", get(get_user_profile))
+        .route("/users/{username}/posts", get(get_user_posts))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            optional_auth_middleware,
+        ));
 
     public_routes.merge(protected_routes)
 }
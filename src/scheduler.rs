@@ -0,0 +1,30 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// 将到期的定时文章标记为已发布，返回本次发布的文章数量
+pub async fn publish_due_posts(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE posts SET status = 'published' WHERE status = 'scheduled' AND published_at <= ? AND deleted_at IS NULL",
+    )
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// 启动后台任务，按固定间隔发布到期的定时文章
+pub fn spawn_post_scheduler(pool: SqlitePool, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match publish_due_posts(&pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "已发布到期的定时文章"),
+                Err(e) => tracing::error!(error = ?e, "发布定时文章失败"),
+            }
+        }
+    })
+}
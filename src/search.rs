@@ -0,0 +1,273 @@
+//! 全文搜索的分词与索引维护
+//!
+//! FTS5 自带的 `unicode61` 分词器会把一整句中文当作一个词，因此写入索引和查询之前
+//! 先在这里分词：连续的 CJK 字符切分为重叠的二元组 (bigram)，并在每段末尾补一个单字，
+//! 其他文字保持原样交给 `unicode61` 处理。
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// 判断字符是否属于需要按二元组切分的 CJK 文字 (汉字、假名和韩文)
+pub fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32,
+        0x3040..=0x30FF      // 平假名、片假名
+        | 0x3400..=0x4DBF    // CJK 扩展 A
+        | 0x4E00..=0x9FFF    // CJK 统一汉字
+        | 0xAC00..=0xD7AF    // 韩文音节
+        | 0xF900..=0xFAFF    // CJK 兼容汉字
+        | 0x20000..=0x2FFFF) // CJK 扩展 B 及以后
+}
+
+/// 文本中连续的一段 CJK 或非 CJK 字符
+enum Segment<'a> {
+    Cjk(Vec<char>),
+    Other(&'a str),
+}
+
+fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut other_start = 0;
+    let mut cjk_run = Vec::new();
+
+    for (index, ch) in text.char_indices() {
+        if is_cjk(ch) {
+            if cjk_run.is_empty() && other_start < index {
+                segments.push(Segment::Other(&text[other_start..index]));
+            }
+            cjk_run.push(ch);
+        } else {
+            if !cjk_run.is_empty() {
+                segments.push(Segment::Cjk(std::mem::take(&mut cjk_run)));
+                other_start = index;
+            }
+        }
+    }
+
+    if !cjk_run.is_empty() {
+        segments.push(Segment::Cjk(cjk_run));
+    } else if other_start < text.len() {
+        segments.push(Segment::Other(&text[other_start..]));
+    }
+
+    segments
+}
+
+/// 将一段 CJK 字符切分为二元组，`with_tail` 为真时在末尾补上最后一个单字
+fn cjk_tokens(run: &[char], with_tail: bool) -> Vec<String> {
+    let mut tokens: Vec<String> = run.windows(2).map(|pair| pair.iter().collect()).collect();
+    if with_tail || run.len() == 1 {
+        tokens.push(run[run.len() - 1].to_string());
+    }
+    tokens
+}
+
+/// 将文本转换为写入 FTS 索引的形式
+///
+/// 每段 CJK 文字末尾的单字保证任何一个字都是某个词元的开头，从而单字查询可以用前缀匹配。
+pub fn tokenize_for_index(text: &str) -> String {
+    let mut output = String::with_capacity(text.len() * 2);
+    for segment in segments(text) {
+        match segment {
+            Segment::Cjk(run) => {
+                output.push(' ');
+                output.push_str(&cjk_tokens(&run, true).join(" "));
+                output.push(' ');
+            }
+            Segment::Other(other) => output.push_str(other),
+        }
+    }
+    output
+}
+
+/// 将用户输入转换为 FTS5 查询表达式
+///
+/// 每个以空白分隔的关键字都转换为一个短语，多个关键字之间为“与”的关系。
+/// 关键字以 CJK 文字结尾时，末尾的单字在正文中可能还有后续字符，因此不补单字；
+/// 如果结尾只剩一个字，则改用前缀匹配。
+pub fn build_match_query(input: &str) -> Option<String> {
+    let phrases: Vec<String> = input
+        .split_whitespace()
+        .filter_map(|term| {
+            let segments = segments(term);
+            let last = segments.len().saturating_sub(1);
+            let mut tokens = Vec::new();
+            let mut prefix = false;
+
+            for (index, segment) in segments.iter().enumerate() {
+                match segment {
+                    Segment::Cjk(run) if index == last && run.len() == 1 => {
+                        tokens.push(run[0].to_string());
+                        prefix = true;
+                    }
+                    Segment::Cjk(run) => tokens.extend(cjk_tokens(run, index != last)),
+                    Segment::Other(other) => tokens.push(other.to_string()),
+                }
+            }
+
+            let phrase = tokens.join(" ");
+            if phrase.trim().is_empty() {
+                return None;
+            }
+            let quoted = format!("\"{}\"", phrase.replace('"', "\"\""));
+            Some(if prefix {
+                format!("{} *", quoted)
+            } else {
+                quoted
+            })
+        })
+        .collect();
+
+    if phrases.is_empty() {
+        None
+    } else {
+        Some(phrases.join(" "))
+    }
+}
+
+/// 更新一篇文章在索引中的内容
+pub async fn index_post(
+    conn: &mut SqliteConnection,
+    id: i64,
+    title: &str,
+    content: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM posts_fts WHERE rowid = ?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("INSERT INTO posts_fts (rowid, title, content) VALUES (?, ?, ?)")
+        .bind(id)
+        .bind(tokenize_for_index(title))
+        .bind(tokenize_for_index(content))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 更新一条评论在索引中的内容
+pub async fn index_comment(
+    conn: &mut SqliteConnection,
+    id: i64,
+    content: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM comments_fts WHERE rowid = ?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("INSERT INTO comments_fts (rowid, content) VALUES (?, ?)")
+        .bind(id)
+        .bind(tokenize_for_index(content))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 索引与原表的行数不一致时重建整个索引 (例如刚升级分词方式或数据被直接修改过)
+pub async fn ensure_search_index(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let (posts, indexed_posts): (i64, i64) =
+        sqlx::query_as("SELECT (SELECT COUNT(*) FROM posts), (SELECT COUNT(*) FROM posts_fts)")
+            .fetch_one(pool)
+            .await?;
+    let (comments, indexed_comments): (i64, i64) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM comments), (SELECT COUNT(*) FROM comments_fts)",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if posts == indexed_posts && comments == indexed_comments {
+        return Ok(());
+    }
+
+    tracing::info!("Rebuilding search index......");
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM posts_fts")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM comments_fts")
+        .execute(&mut *tx)
+        .await?;
+
+    let posts: Vec<(i64, String, String)> = sqlx::query_as("SELECT id, title, content FROM posts")
+        .fetch_all(&mut *tx)
+        .await?;
+    for (id, title, content) in posts {
+        index_post(&mut tx, id, &title, &content).await?;
+    }
+
+    let comments: Vec<(i64, String)> = sqlx::query_as("SELECT comment_id, content FROM comments")
+        .fetch_all(&mut *tx)
+        .await?;
+    for (id, content) in comments {
+        index_comment(&mut tx, id, &content).await?;
+    }
+
+    tx.commit().await
+}
+
+/// 从原文中截取包含关键字的摘要，HTML 转义后用 `<mark>` 标出匹配的部分
+///
+/// 匹配忽略大小写。找不到关键字时返回原文开头的一段。
+pub fn make_snippet(text: &str, query: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let lowered: Vec<char> = chars.iter().map(|ch| lowercase(*ch)).collect();
+    let terms: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|term| term.chars().map(lowercase).collect())
+        .collect();
+
+    // 标记每个字符是否落在某个关键字的匹配范围内
+    let mut marked = vec![false; chars.len()];
+    for term in terms.iter().filter(|term| !term.is_empty()) {
+        for start in 0..lowered.len().saturating_sub(term.len() - 1) {
+            if lowered[start..start + term.len()] == term[..] {
+                marked[start..start + term.len()].fill(true);
+            }
+        }
+    }
+
+    let first_match = marked.iter().position(|is_marked| *is_marked).unwrap_or(0);
+    let start = first_match.saturating_sub(max_chars / 4);
+    let end = (start + max_chars).min(chars.len());
+
+    let mut html = String::new();
+    if start > 0 {
+        html.push('…');
+    }
+    let mut in_mark = false;
+    for index in start..end {
+        if marked[index] != in_mark {
+            html.push_str(if marked[index] { "<mark>" } else { "</mark>" });
+            in_mark = marked[index];
+        }
+        push_escaped(&mut html, chars[index]);
+    }
+    if in_mark {
+        html.push_str("</mark>");
+    }
+    if end < chars.len() {
+        html.push('…');
+    }
+    html
+}
+
+/// 判断文本是否包含任意一个关键字 (忽略大小写)
+pub fn contains_any_term(text: &str, query: &str) -> bool {
+    let text = text.to_lowercase();
+    query
+        .split_whitespace()
+        .any(|term| text.contains(&term.to_lowercase()))
+}
+
+fn lowercase(ch: char) -> char {
+    ch.to_lowercase().next().unwrap_or(ch)
+}
+
+fn push_escaped(html: &mut String, ch: char) {
+    match ch {
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        '"' => html.push_str("&quot;"),
+        '\'' => html.push_str("&#39;"),
+        _ => html.push(ch),
+    }
+}
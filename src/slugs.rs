@@ -0,0 +1,106 @@
+//! 文章 slug 的生成与维护
+//!
+//! slug 由标题音译为 ASCII 后生成 (中文转换为不带声调的拼音)，与已有文章或
+//! 其他文章的旧 slug 冲突时依次追加 `-2`、`-3` 等后缀。
+
+use crate::utils::slugify;
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// slug 的最大长度，超出时在单词边界处截断
+const MAX_SLUG_LENGTH: usize = 80;
+
+/// 根据标题生成 slug 的基础部分，不检查是否重复
+pub fn post_slug_base(title: &str) -> String {
+    let mut slug = slugify(&deunicode::deunicode(title));
+    if slug.len() > MAX_SLUG_LENGTH {
+        let cut = slug[..MAX_SLUG_LENGTH]
+            .rfind('-')
+            .unwrap_or(MAX_SLUG_LENGTH);
+        slug.truncate(cut);
+    }
+    if slug.is_empty() {
+        slug.push_str("post");
+    }
+    slug
+}
+
+/// 为文章生成一个未被占用的 slug，`post_id` 为该文章自身时允许沿用它自己的旧 slug
+pub async fn unique_post_slug(
+    conn: &mut SqliteConnection,
+    title: &str,
+    post_id: Option<i64>,
+) -> Result<String, sqlx::Error> {
+    let base = post_slug_base(title);
+    let mut candidate = base.clone();
+    let mut suffix = 1;
+
+    loop {
+        let taken = sqlx::query(
+            "SELECT 1 FROM posts WHERE slug = ?1 AND (?2 IS NULL OR id <> ?2) \
+             UNION ALL SELECT 1 FROM post_slug_redirects WHERE slug = ?1 AND (?2 IS NULL OR post_id <> ?2)",
+        )
+        .bind(&candidate)
+        .bind(post_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if taken.is_none() {
+            return Ok(candidate);
+        }
+        suffix += 1;
+        candidate = format!("{}-{}", base, suffix);
+    }
+}
+
+/// 标题变化后为文章生成新的 slug，并把旧的 slug 记录为重定向
+pub async fn update_post_slug(
+    conn: &mut SqliteConnection,
+    post_id: i64,
+    old_slug: &str,
+    title: &str,
+) -> Result<String, sqlx::Error> {
+    let slug = unique_post_slug(conn, title, Some(post_id)).await?;
+    if slug == old_slug {
+        return Ok(slug);
+    }
+
+    // 改回曾经用过的标题时，对应的重定向不再需要
+    sqlx::query("DELETE FROM post_slug_redirects WHERE slug = ?")
+        .bind(&slug)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("INSERT OR REPLACE INTO post_slug_redirects (slug, post_id) VALUES (?, ?)")
+        .bind(old_slug)
+        .bind(post_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE posts SET slug = ? WHERE id = ?")
+        .bind(&slug)
+        .bind(post_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(slug)
+}
+
+/// 为还没有 slug 的文章 (升级前创建的) 生成 slug
+pub async fn ensure_post_slugs(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let posts: Vec<(i64, String)> =
+        sqlx::query_as("SELECT id, title FROM posts WHERE slug IS NULL ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+    if posts.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("Generating slugs for {} posts......", posts.len());
+    let mut tx = pool.begin().await?;
+    for (id, title) in posts {
+        let slug = unique_post_slug(&mut tx, &title, Some(id)).await?;
+        sqlx::query("UPDATE posts SET slug = ? WHERE id = ?")
+            .bind(&slug)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
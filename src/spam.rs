@@ -0,0 +1,450 @@
+//! 评论的垃圾内容检测
+//!
+//! 新发表和修改后的评论依次经过一组检查，每项检查给出一个分数 (正数表示更像垃圾评论，负数表示更像
+//! 正常评论)，总分达到阈值的评论进入审核队列或直接标记为垃圾评论。检查项通过
+//! [`SpamCheck`] 扩展，默认包含链接数量、屏蔽词和屏蔽域名、重复内容、账户注册时间
+//! 以及朴素贝叶斯分类器。分类器由审核员标记垃圾评论和通过评论的决定训练。
+
+use crate::{
+    models::{BlocklistKind, CommentStatus},
+    search::tokenize_for_index,
+    utils::hash_token,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::{collections::BTreeSet, future::Future, pin::Pin};
+
+/// 总分达到该值的评论需要等待审核
+pub const HOLD_THRESHOLD: f64 = 3.0;
+
+/// 总分达到该值的评论直接标记为垃圾评论
+pub const SPAM_THRESHOLD: f64 = 6.0;
+
+/// 第一条之后每多一条链接增加的分数
+const EXTRA_LINK_SCORE: f64 = 1.5;
+/// 链接数量最多贡献的分数
+const MAX_LINK_SCORE: f64 = 4.5;
+/// 每个命中的屏蔽词增加的分数
+const BLOCKED_WORD_SCORE: f64 = 3.0;
+/// 链接指向屏蔽域名时增加的分数，单独即可判定为垃圾评论
+const BLOCKED_DOMAIN_SCORE: f64 = SPAM_THRESHOLD;
+/// 近期出现过相同内容时增加的分数
+const DUPLICATE_SCORE: f64 = 3.0;
+/// 短于该字符数的评论 (例如“谢谢分享”) 重复很正常，不做重复检测
+const MIN_DUPLICATE_CHARS: usize = 20;
+/// 注册不到一小时的账户增加的分数
+const NEW_ACCOUNT_SCORE: f64 = 2.0;
+/// 注册不到一天的账户增加的分数
+const RECENT_ACCOUNT_SCORE: f64 = 1.0;
+/// 分类器给出的分数范围为 ±BAYES_WEIGHT
+const BAYES_WEIGHT: f64 = 4.0;
+/// 两个类别的样本都达到该数量后分类器才参与评分
+const MIN_TRAINING_DOCUMENTS: i64 = 5;
+/// 每条评论最多使用的词元数量
+const MAX_TOKENS: usize = 200;
+
+lazy_static::lazy_static! {
+    static ref LINK_REGEX: regex::Regex =
+        regex::Regex::new(r#"(?i)(?:https?://|\bwww\.)([^\s/?#:<>"'()\[\]]+)"#).unwrap();
+}
+
+/// 待检测的评论
+pub struct SpamInput {
+    /// 编辑已有评论时为该评论的 ID，重复检测不与它自身比较
+    pub comment_id: Option<i64>,
+    pub author_id: i64,
+    pub content: String,
+    /// 作者的注册时间，升级前注册的用户为空
+    pub author_created_at: Option<DateTime<Utc>>,
+}
+
+/// 检查项返回的 Future
+pub type CheckFuture<'a> = Pin<Box<dyn Future<Output = Result<f64, sqlx::Error>> + Send + 'a>>;
+
+/// 一项垃圾评论检查
+pub trait SpamCheck: Send + Sync {
+    /// 检查项的名称，用于日志
+    fn name(&self) -> &'static str;
+
+    /// 计算评论在这一项上的分数
+    fn score<'a>(&'a self, pool: &'a SqlitePool, input: &'a SpamInput) -> CheckFuture<'a>;
+}
+
+/// 检测结果
+pub struct SpamVerdict {
+    pub score: f64,
+    /// 分数不为零的检查项及其分数
+    pub signals: Vec<(&'static str, f64)>,
+}
+
+impl SpamVerdict {
+    /// 根据总分给出评论应处的状态，为空表示交由审核方式决定
+    pub fn status(&self) -> Option<CommentStatus> {
+        if self.score >= SPAM_THRESHOLD {
+            Some(CommentStatus::Spam)
+        } else if self.score >= HOLD_THRESHOLD {
+            Some(CommentStatus::Pending)
+        } else {
+            None
+        }
+    }
+}
+
+/// 由多项检查组成的检测流程
+pub struct SpamFilter {
+    checks: Vec<Box<dyn SpamCheck>>,
+}
+
+impl SpamFilter {
+    /// 创建不包含任何检查的流程
+    pub fn empty() -> Self {
+        Self { checks: Vec::new() }
+    }
+
+    /// 在流程末尾添加一项检查
+    pub fn with_check(mut self, check: impl SpamCheck + 'static) -> Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// 依次执行所有检查并汇总分数
+    pub async fn evaluate(
+        &self,
+        pool: &SqlitePool,
+        input: &SpamInput,
+    ) -> Result<SpamVerdict, sqlx::Error> {
+        let mut signals = Vec::new();
+        for check in &self.checks {
+            let score = check.score(pool, input).await?;
+            if score != 0.0 {
+                signals.push((check.name(), score));
+            }
+        }
+
+        Ok(SpamVerdict {
+            score: signals.iter().map(|(_, score)| score).sum(),
+            signals,
+        })
+    }
+}
+
+impl Default for SpamFilter {
+    /// 包含全部内置检查的流程
+    fn default() -> Self {
+        Self::empty()
+            .with_check(LinkCheck)
+            .with_check(BlocklistCheck)
+            .with_check(DuplicateCheck)
+            .with_check(AccountAgeCheck)
+            .with_check(BayesCheck)
+    }
+}
+
+/// 链接数量：一条链接很正常，之后每多一条都更可疑
+pub struct LinkCheck;
+
+impl SpamCheck for LinkCheck {
+    fn name(&self) -> &'static str {
+        "links"
+    }
+
+    fn score<'a>(&'a self, _pool: &'a SqlitePool, input: &'a SpamInput) -> CheckFuture<'a> {
+        let links = LINK_REGEX.find_iter(&input.content).count();
+        let score = (links.saturating_sub(1) as f64 * EXTRA_LINK_SCORE).min(MAX_LINK_SCORE);
+        Box::pin(std::future::ready(Ok(score)))
+    }
+}
+
+/// 屏蔽词和屏蔽域名
+pub struct BlocklistCheck;
+
+impl SpamCheck for BlocklistCheck {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    fn score<'a>(&'a self, pool: &'a SqlitePool, input: &'a SpamInput) -> CheckFuture<'a> {
+        Box::pin(async move {
+            let entries: Vec<(BlocklistKind, String)> =
+                sqlx::query_as("SELECT kind, value FROM spam_blocklist")
+                    .fetch_all(pool)
+                    .await?;
+            if entries.is_empty() {
+                return Ok(0.0);
+            }
+
+            let text = input.content.to_lowercase();
+            let hosts = link_hosts(&text);
+            let mut score = 0.0;
+            for (kind, value) in entries {
+                let matched = match kind {
+                    BlocklistKind::Word => contains_word(&text, &value),
+                    BlocklistKind::Domain => hosts
+                        .iter()
+                        .any(|host| host == &value || host.ends_with(&format!(".{}", value))),
+                };
+                if matched {
+                    score += match kind {
+                        BlocklistKind::Word => BLOCKED_WORD_SCORE,
+                        BlocklistKind::Domain => BLOCKED_DOMAIN_SCORE,
+                    };
+                }
+            }
+            Ok(score.min(SPAM_THRESHOLD))
+        })
+    }
+}
+
+/// 重复内容：最近一周内有人发表过完全相同的评论
+pub struct DuplicateCheck;
+
+impl SpamCheck for DuplicateCheck {
+    fn name(&self) -> &'static str {
+        "duplicate"
+    }
+
+    fn score<'a>(&'a self, pool: &'a SqlitePool, input: &'a SpamInput) -> CheckFuture<'a> {
+        Box::pin(async move {
+            if input.content.trim().chars().count() < MIN_DUPLICATE_CHARS {
+                return Ok(0.0);
+            }
+
+            let duplicate = sqlx::query(
+                "SELECT 1 FROM comments WHERE content_hash = ? \
+                 AND created_at >= datetime('now', '-7 days') AND comment_id IS NOT ? LIMIT 1",
+            )
+            .bind(content_hash(&input.content))
+            .bind(input.comment_id)
+            .fetch_optional(pool)
+            .await?;
+            Ok(if duplicate.is_some() {
+                DUPLICATE_SCORE
+            } else {
+                0.0
+            })
+        })
+    }
+}
+
+/// 账户注册时间：新注册的账户更可能是垃圾账户
+pub struct AccountAgeCheck;
+
+impl SpamCheck for AccountAgeCheck {
+    fn name(&self) -> &'static str {
+        "account_age"
+    }
+
+    fn score<'a>(&'a self, _pool: &'a SqlitePool, input: &'a SpamInput) -> CheckFuture<'a> {
+        let score = match input
+            .author_created_at
+            .map(|created_at| Utc::now() - created_at)
+        {
+            Some(age) if age < Duration::hours(1) => NEW_ACCOUNT_SCORE,
+            Some(age) if age < Duration::days(1) => RECENT_ACCOUNT_SCORE,
+            _ => 0.0,
+        };
+        Box::pin(std::future::ready(Ok(score)))
+    }
+}
+
+/// 朴素贝叶斯分类器，样本来自审核员的决定
+pub struct BayesCheck;
+
+impl SpamCheck for BayesCheck {
+    fn name(&self) -> &'static str {
+        "bayes"
+    }
+
+    fn score<'a>(&'a self, pool: &'a SqlitePool, input: &'a SpamInput) -> CheckFuture<'a> {
+        Box::pin(async move {
+            let documents: Vec<(String, i64)> =
+                sqlx::query_as("SELECT label, count FROM spam_documents")
+                    .fetch_all(pool)
+                    .await?;
+            let count_of = |label: &str| {
+                documents
+                    .iter()
+                    .find(|(name, _)| name == label)
+                    .map_or(0, |(_, count)| *count)
+            };
+            let (spam_documents, ham_documents) = (count_of("spam"), count_of("ham"));
+            if spam_documents < MIN_TRAINING_DOCUMENTS || ham_documents < MIN_TRAINING_DOCUMENTS {
+                return Ok(0.0);
+            }
+
+            let tokens = classifier_tokens(&input.content);
+            let counts: Vec<(i64, i64)> = sqlx::query_as(
+                "SELECT spam_count, ham_count FROM spam_tokens \
+                 WHERE token IN (SELECT value FROM json_each(?))",
+            )
+            .bind(serde_json::to_string(&tokens).unwrap_or_default())
+            .fetch_all(pool)
+            .await?;
+
+            // 在对数空间中累加各词元的似然比，并做加一平滑
+            let (spam_total, ham_total) = (spam_documents as f64, ham_documents as f64);
+            let mut log_odds = (spam_total / ham_total).ln();
+            for (spam_count, ham_count) in counts {
+                let in_spam = (spam_count as f64 + 1.0) / (spam_total + 2.0);
+                let in_ham = (ham_count as f64 + 1.0) / (ham_total + 2.0);
+                log_odds += (in_spam / in_ham).ln();
+            }
+            let probability = 1.0 / (1.0 + (-log_odds).exp());
+            Ok((probability - 0.5) * 2.0 * BAYES_WEIGHT)
+        })
+    }
+}
+
+/// 计算评论内容的摘要，去掉首尾空白并转为小写后相同的内容摘要相同
+pub fn content_hash(content: &str) -> String {
+    hash_token(&content.trim().to_lowercase())
+}
+
+/// 分类器的训练标签
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamLabel {
+    Spam,
+    Ham,
+}
+
+impl SpamLabel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpamLabel::Spam => "spam",
+            SpamLabel::Ham => "ham",
+        }
+    }
+}
+
+/// 以评论为样本训练分类器，评论之前以另一个标签训练过时先撤销那次训练
+pub async fn train_comment(
+    conn: &mut SqliteConnection,
+    comment_id: i64,
+    label: SpamLabel,
+) -> Result<(), sqlx::Error> {
+    let (content, previous): (String, Option<String>) =
+        sqlx::query_as("SELECT content, spam_label FROM comments WHERE comment_id = ?")
+            .bind(comment_id)
+            .fetch_one(&mut *conn)
+            .await?;
+    if previous.as_deref() == Some(label.as_str()) {
+        return Ok(());
+    }
+
+    let tokens = serde_json::to_string(&classifier_tokens(&content)).unwrap_or_default();
+    if let Some(previous) = previous {
+        adjust_counts(conn, &tokens, &previous, -1).await?;
+    }
+    adjust_counts(conn, &tokens, label.as_str(), 1).await?;
+
+    sqlx::query("UPDATE comments SET spam_label = ? WHERE comment_id = ?")
+        .bind(label.as_str())
+        .bind(comment_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 撤销以评论为样本的训练，评论内容修改前调用
+pub async fn untrain_comment(
+    conn: &mut SqliteConnection,
+    comment_id: i64,
+) -> Result<(), sqlx::Error> {
+    let (content, previous): (String, Option<String>) =
+        sqlx::query_as("SELECT content, spam_label FROM comments WHERE comment_id = ?")
+            .bind(comment_id)
+            .fetch_one(&mut *conn)
+            .await?;
+    let Some(previous) = previous else {
+        return Ok(());
+    };
+
+    let tokens = serde_json::to_string(&classifier_tokens(&content)).unwrap_or_default();
+    adjust_counts(conn, &tokens, &previous, -1).await?;
+    sqlx::query("UPDATE comments SET spam_label = NULL WHERE comment_id = ?")
+        .bind(comment_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+async fn adjust_counts(
+    conn: &mut SqliteConnection,
+    tokens: &str,
+    label: &str,
+    delta: i64,
+) -> Result<(), sqlx::Error> {
+    let column = if label == "spam" {
+        "spam_count"
+    } else {
+        "ham_count"
+    };
+    sqlx::query(&format!(
+        "INSERT INTO spam_tokens (token, {column}) SELECT value, MAX(?2, 0) FROM json_each(?1) WHERE true \
+         ON CONFLICT (token) DO UPDATE SET {column} = MAX({column} + ?2, 0)"
+    ))
+    .bind(tokens)
+    .bind(delta)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query("UPDATE spam_documents SET count = MAX(count + ?, 0) WHERE label = ?")
+        .bind(delta)
+        .bind(label)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 将评论切分为分类器使用的词元，CJK 文字按二元组切分，同一词元只计一次
+fn classifier_tokens(content: &str) -> BTreeSet<String> {
+    tokenize_for_index(&content.to_lowercase())
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|token| (2..=30).contains(&token.chars().count()))
+        .map(str::to_string)
+        .take(MAX_TOKENS)
+        .collect()
+}
+
+/// 取出文本中所有链接的主机名
+fn link_hosts(text: &str) -> Vec<String> {
+    LINK_REGEX
+        .captures_iter(text)
+        .map(|captures| captures[1].trim_end_matches('.').to_lowercase())
+        .collect()
+}
+
+/// 判断文本中是否出现屏蔽词，由 ASCII 字母和数字组成的词需要完整匹配
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_word_char = |ch: char| ch.is_ascii_alphanumeric();
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+/// 规范化屏蔽列表中的值，无效时返回 `None`
+///
+/// 屏蔽词转为小写并合并空白；屏蔽域名去掉协议、`www.` 前缀和路径。
+pub fn normalize_blocklist_value(kind: BlocklistKind, value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    let normalized = match kind {
+        BlocklistKind::Word => value.split_whitespace().collect::<Vec<_>>().join(" "),
+        BlocklistKind::Domain => {
+            let host = value
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .trim_start_matches("www.");
+            let host = host.split(['/', '?', '#', ':']).next().unwrap_or_default();
+            let valid = host.contains('.')
+                && host.split('.').all(|label| {
+                    !label.is_empty() && label.chars().all(|ch| ch.is_alphanumeric() || ch == '-')
+                });
+            if !valid {
+                return None;
+            }
+            host.to_string()
+        }
+    };
+    (!normalized.is_empty()).then_some(normalized)
+}
@@ -0,0 +1,96 @@
+//! TOTP 两步验证
+//!
+//! 验证码按 RFC 6238 生成：SHA-1、6 位数字、30 秒一个时间步，允许前后各偏差一个时间步。
+//! 每次验证成功后记录所用的时间步，同一个验证码不能再次使用。
+
+use chrono::{DateTime, Utc};
+use rand::{Rng, RngCore};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// 每个时间步的长度（秒）
+const STEP_SECONDS: u64 = 30;
+
+/// 允许客户端时钟偏差的时间步数量
+const SKEW_STEPS: u8 = 1;
+
+/// 启用两步验证时生成的恢复码数量
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// 生成一个新的 160 位密钥，返回 Base32 编码
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut secret);
+    Secret::Raw(secret.to_vec()).to_encoded().to_string()
+}
+
+/// 根据 Base32 编码的密钥创建生成器，`issuer` 和 `account` 显示在验证器应用中
+pub fn totp(secret: &str, issuer: &str, account: &str) -> Option<TOTP> {
+    let bytes = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        SKEW_STEPS,
+        STEP_SECONDS,
+        bytes,
+        Some(issuer.to_string()),
+        account.to_string(),
+    )
+    .ok()
+}
+
+/// 供验证器应用扫描的 `otpauth://` 地址
+pub fn provisioning_uri(secret: &str, issuer: &str, account: &str) -> Option<String> {
+    totp(secret, issuer, account).map(|totp| totp.get_url())
+}
+
+/// 检查验证码，返回其所在的时间步；`last_step` 及之前的时间步视为已使用
+pub fn verify_code(
+    secret: &str,
+    code: &str,
+    last_step: Option<i64>,
+    now: DateTime<Utc>,
+) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != 6 || !code.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    let totp = totp(secret, "", "")?;
+    let current = now.timestamp().max(0) as u64 / STEP_SECONDS;
+    let skew = SKEW_STEPS as u64;
+
+    (current.saturating_sub(skew)..=current + skew)
+        .filter(|step| last_step.is_none_or(|last| *step as i64 > last))
+        .find(|step| totp.generate(step * STEP_SECONDS) == code)
+        .map(|step| step as i64)
+}
+
+/// 生成指定时间的验证码，用于测试和排查时钟问题
+pub fn generate_code(secret: &str, time: DateTime<Utc>) -> Option<String> {
+    totp(secret, "", "").map(|totp| totp.generate(time.timestamp().max(0) as u64))
+}
+
+/// 生成一组恢复码，格式为 `xxxx-xxxx-xxxx-xxxx`
+pub fn generate_recovery_codes() -> Vec<String> {
+    const CHARSET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: Vec<char> = (0..16)
+                .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+                .collect();
+            chars
+                .chunks(4)
+                .map(|chunk| chunk.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// 恢复码不区分大小写，忽略空白和连字符
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|ch| !ch.is_whitespace() && *ch != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -1,6 +1,12 @@
-use crate::errors::AppError;
+use crate::{config::Config, errors::AppError};
+use argon2::{
+    Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{Salt, SaltString},
+};
 use axum::{Json, http::StatusCode, response::IntoResponse};
+use rand::RngCore;
 use serde::Serialize;
+use std::{str::FromStr, sync::OnceLock};
 
 /// 检查删除操作的结果，如果没有行被影响则返回 NotFound 错误
 pub fn check_delete_result(
@@ -22,19 +28,180 @@ where
     (StatusCode::CREATED, Json(data))
 }
 
-/// 执行密码哈希操作
-pub async fn hash_password(password: &str) -> Result<String, AppError> {
+/// 新密码使用的哈希算法，可以通过 `PASSWORD_HASH_ALGORITHM` 选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordAlgorithm {
+    Argon2id,
+    /// 旧版本使用的算法，已有的 bcrypt 哈希始终可以验证
+    Bcrypt,
+}
+
+impl FromStr for PasswordAlgorithm {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "argon2id" => Ok(Self::Argon2id),
+            "bcrypt" => Ok(Self::Bcrypt),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 执行密码哈希操作，算法和参数由配置决定
+pub async fn hash_password(config: &Config, password: &str) -> Result<String, AppError> {
     let password_clone = password.to_string();
-    tokio::task::spawn_blocking(move || bcrypt::hash(&password_clone, bcrypt::DEFAULT_COST))
-        .await?
-        .map_err(|_| AppError::PasswordHash)
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        hash_password_blocking(&config, config.password_hash_algorithm, &password_clone)
+    })
+    .await?
 }
 
-/// 验证密码
+/// 验证密码，根据哈希的格式自动识别 Argon2 或 bcrypt
 pub async fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
     let password_clone = password.to_string();
     let hash_clone = hash.to_string();
-    tokio::task::spawn_blocking(move || bcrypt::verify(&password_clone, &hash_clone))
+    tokio::task::spawn_blocking(move || verify_password_blocking(&password_clone, &hash_clone))
         .await?
-        .map_err(|_| AppError::PasswordHash)
+}
+
+/// 哈希使用的算法或参数与当前配置不一致时返回 true，登录成功后应当用新参数重新哈希
+pub fn password_needs_rehash(config: &Config, hash: &str) -> bool {
+    match config.password_hash_algorithm {
+        PasswordAlgorithm::Argon2id => {
+            let Ok(parsed) = PasswordHash::new(hash) else {
+                return true;
+            };
+            let Ok(params) = Params::try_from(&parsed) else {
+                return true;
+            };
+            parsed.algorithm != argon2::Algorithm::Argon2id.ident()
+                || parsed.version != Some(Version::V0x13 as u32)
+                || params.m_cost() != config.argon2_memory_kib
+                || params.t_cost() != config.argon2_iterations
+                || params.p_cost() != config.argon2_parallelism
+        }
+        PasswordAlgorithm::Bcrypt => hash
+            .parse::<bcrypt::HashParts>()
+            .map_or(true, |parts| parts.get_cost() != config.bcrypt_cost),
+    }
+}
+
+/// 与当前配置的参数相同的固定哈希，用来在没有真实哈希可比对时消耗同样的时间；
+/// 配置在进程内不会变化，只需计算一次
+static DUMMY_ARGON2_HASH: OnceLock<Option<String>> = OnceLock::new();
+static DUMMY_BCRYPT_HASH: OnceLock<Option<String>> = OnceLock::new();
+
+/// 哈希使用的算法
+pub fn password_algorithm(hash: &str) -> PasswordAlgorithm {
+    if hash.starts_with("$argon2") {
+        PasswordAlgorithm::Argon2id
+    } else {
+        PasswordAlgorithm::Bcrypt
+    }
+}
+
+/// 登录时验证密码，`hash` 为 `None` 表示用户不存在
+///
+/// 用户不存在时与一个按当前配置计算的固定哈希比对一次，耗时与验证新算法的真实哈希相同，
+/// 无法据此判断用户名是否存在。每次登录都只验证一个哈希。
+pub async fn verify_login_password(
+    config: &Config,
+    password: &str,
+    hash: Option<&str>,
+) -> Result<bool, AppError> {
+    let password_clone = password.to_string();
+    let hash_clone = hash.map(str::to_string);
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Some(hash) = &hash_clone {
+            return verify_password_blocking(&password_clone, hash);
+        }
+
+        let algorithm = config.password_hash_algorithm;
+        let dummy = match algorithm {
+            PasswordAlgorithm::Argon2id => &DUMMY_ARGON2_HASH,
+            PasswordAlgorithm::Bcrypt => &DUMMY_BCRYPT_HASH,
+        };
+        let dummy = dummy
+            .get_or_init(|| {
+                hash_password_blocking(&config, algorithm, "inkwell-dummy-password").ok()
+            })
+            .as_ref()
+            .ok_or(AppError::PasswordHash)?;
+        verify_password_blocking(&password_clone, dummy)?;
+        Ok(false)
+    })
+    .await?
+}
+
+fn hash_password_blocking(
+    config: &Config,
+    algorithm: PasswordAlgorithm,
+    password: &str,
+) -> Result<String, AppError> {
+    match algorithm {
+        PasswordAlgorithm::Argon2id => {
+            let params = Params::new(
+                config.argon2_memory_kib,
+                config.argon2_iterations,
+                config.argon2_parallelism,
+                None,
+            )
+            .map_err(|_| AppError::PasswordHash)?;
+            let mut salt = [0u8; Salt::RECOMMENDED_LENGTH];
+            rand::thread_rng().fill_bytes(&mut salt);
+            let salt = SaltString::encode_b64(&salt).map_err(|_| AppError::PasswordHash)?;
+            Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|_| AppError::PasswordHash)
+        }
+        PasswordAlgorithm::Bcrypt => {
+            bcrypt::hash(password, config.bcrypt_cost).map_err(|_| AppError::PasswordHash)
+        }
+    }
+}
+
+fn verify_password_blocking(password: &str, hash: &str) -> Result<bool, AppError> {
+    if password_algorithm(hash) == PasswordAlgorithm::Bcrypt {
+        return bcrypt::verify(password, hash).map_err(|_| AppError::PasswordHash);
+    }
+    let parsed = PasswordHash::new(hash).map_err(|_| AppError::PasswordHash)?;
+    // 参数从哈希本身读取，修改配置后旧的 Argon2 哈希仍然可以验证
+    match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(_) => Err(AppError::PasswordHash),
+    }
+}
+
+/// 生成一个随机的不透明令牌 (用于刷新令牌等)
+pub fn generate_token() -> String {
+    use rand::{Rng, distributions::Alphanumeric};
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect()
+}
+
+/// 计算令牌的 SHA-256 摘要，数据库中只保存摘要而不保存令牌原文
+pub fn hash_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// 生成 URL 中使用的 slug：字母转为小写，字母、数字和汉字等文字保留，其他字符合并为一个连字符
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for ch in text.chars().flat_map(char::to_lowercase) {
+        if ch.is_alphanumeric() {
+            slug.push(ch);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
 }
@@ -0,0 +1,300 @@
+mod common;
+use common::{
+    TestUser, create_authenticated_user, create_user_with_role, login, spawn_app,
+    spawn_app_with_pool,
+};
+
+async fn create_token(
+    client: &reqwest::Client,
+    app_address: &str,
+    user: &TestUser,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/me/tokens", app_address))
+        .bearer_auth(&user.token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn create_post(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&serde_json::json!({
+            "title": "Release notes", "content": "v1.2.3", "tags": ["release"], "copyright": "c"
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn me_status(client: &reqwest::Client, app_address: &str, token: &str) -> u16 {
+    client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn tokens_are_shown_once_and_limited_to_their_scopes() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let response = create_token(
+        &client,
+        &app_address,
+        &user,
+        serde_json::json!({ "name": "CI", "scopes": ["posts:write", "posts:write"] }),
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    let created: serde_json::Value = response.json().await.unwrap();
+    let token = created["token"].as_str().unwrap().to_string();
+    assert!(token.starts_with("inkpat_"));
+    assert!(token.starts_with(created["token_prefix"].as_str().unwrap()));
+    assert_eq!(serde_json::json!(["posts:write"]), created["scopes"]);
+    assert!(created["expires_at"].is_null());
+
+    // 令牌可以读取和发表文章，文章的作者是令牌的所有者
+    assert_eq!(200, me_status(&client, &app_address, &token).await);
+    let response = create_post(&client, &app_address, &token).await;
+    assert_eq!(201, response.status().as_u16());
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(user.username, post["author"]);
+
+    // 授权范围之外的写操作被拒绝
+    let response = client
+        .post(format!("{}/posts/{}/comments", app_address, post["id"]))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "content": "评论" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("comments:write"));
+
+    let response = client
+        .put(format!("{}/posts/{}/bookmark", app_address, post["id"]))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    // 令牌不能管理令牌
+    let response = client
+        .get(format!("{}/me/tokens", app_address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+    let response = client
+        .post(format!("{}/me/tokens", app_address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "name": "escalate", "scopes": ["comments:write"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/me/tokens", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let tokens: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, tokens.as_array().unwrap().len());
+    assert_eq!("CI", tokens[0]["name"]);
+    assert!(tokens[0].get("token").is_none());
+    assert!(tokens[0]["last_used_at"].is_string());
+}
+
+#[tokio::test]
+async fn revoked_and_expired_tokens_are_rejected() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let other = create_authenticated_user(&client, &app_address).await;
+
+    let response = create_token(
+        &client,
+        &app_address,
+        &user,
+        serde_json::json!({ "name": "Empty", "scopes": [] }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+    let response = create_token(
+        &client,
+        &app_address,
+        &user,
+        serde_json::json!({ "name": "Forever", "scopes": ["posts:write"], "expires_in_days": 0 }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = create_token(
+        &client,
+        &app_address,
+        &user,
+        serde_json::json!({ "name": "Revoked", "scopes": ["posts:write"] }),
+    )
+    .await;
+    let revoked: serde_json::Value = response.json().await.unwrap();
+    let revoked_token = revoked["token"].as_str().unwrap();
+    assert_eq!(200, me_status(&client, &app_address, revoked_token).await);
+
+    // 只能吊销自己的令牌
+    let revoke = |owner: &TestUser| {
+        client
+            .delete(format!("{}/me/tokens/{}", app_address, revoked["id"]))
+            .bearer_auth(&owner.token)
+            .send()
+    };
+    assert_eq!(404, revoke(&other).await.unwrap().status().as_u16());
+    assert_eq!(204, revoke(&user).await.unwrap().status().as_u16());
+    assert_eq!(404, revoke(&user).await.unwrap().status().as_u16());
+    assert_eq!(401, me_status(&client, &app_address, revoked_token).await);
+
+    let response = create_token(
+        &client,
+        &app_address,
+        &user,
+        serde_json::json!({ "name": "Short", "scopes": ["posts:write"], "expires_in_days": 7 }),
+    )
+    .await;
+    let expiring: serde_json::Value = response.json().await.unwrap();
+    assert!(expiring["expires_at"].is_string());
+    let expiring_token = expiring["token"].as_str().unwrap();
+    assert_eq!(200, me_status(&client, &app_address, expiring_token).await);
+    sqlx::query("UPDATE personal_access_tokens SET expires_at = datetime('now', '-1 minute')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(401, me_status(&client, &app_address, expiring_token).await);
+
+    // 停用账户后令牌同样失效
+    let response = create_token(
+        &client,
+        &app_address,
+        &user,
+        serde_json::json!({ "name": "Suspended", "scopes": ["posts:write"] }),
+    )
+    .await;
+    let created: serde_json::Value = response.json().await.unwrap();
+    sqlx::query("UPDATE users SET suspended_at = CURRENT_TIMESTAMP WHERE username = ?")
+        .bind(&user.username)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let response = create_post(&client, &app_address, created["token"].as_str().unwrap()).await;
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn changing_the_password_revokes_tokens_but_logging_out_does_not() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let response = create_token(
+        &client,
+        &app_address,
+        &user,
+        serde_json::json!({ "name": "CI", "scopes": ["posts:write"] }),
+    )
+    .await;
+    let created: serde_json::Value = response.json().await.unwrap();
+    let token = created["token"].as_str().unwrap();
+
+    // 注销全部会话只影响登录会话，自动化脚本使用的令牌需要单独吊销
+    let response = client
+        .post(format!("{}/logout/all", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+    assert_eq!(401, me_status(&client, &app_address, &user.token).await);
+    assert_eq!(200, me_status(&client, &app_address, token).await);
+
+    // 修改密码说明密码可能已经泄露，令牌全部吊销
+    let session = login(&client, &app_address, &user.username, "password123").await;
+    let response = client
+        .put(format!("{}/me/password", app_address))
+        .bearer_auth(&session.token)
+        .json(&serde_json::json!({ "current_password": "password123", "new_password": "new-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(401, me_status(&client, &app_address, token).await);
+    let response = client
+        .get(format!("{}/me/tokens", app_address))
+        .bearer_auth(
+            response.json::<serde_json::Value>().await.unwrap()["token"]
+                .as_str()
+                .unwrap(),
+        )
+        .send()
+        .await
+        .unwrap();
+    let tokens: serde_json::Value = response.json().await.unwrap();
+    assert!(tokens.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn tokens_cannot_read_privileged_endpoints() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let response = create_token(
+        &client,
+        &app_address,
+        &admin,
+        serde_json::json!({ "name": "CI", "scopes": ["posts:write", "comments:write"] }),
+    )
+    .await;
+    let created: serde_json::Value = response.json().await.unwrap();
+    let token = created["token"].as_str().unwrap();
+
+    // 管理员本人可以访问，同一账户的令牌即使只是读取也会被拒绝
+    for path in [
+        "/admin/users",
+        "/admin/reports",
+        "/comments/moderation",
+        "/comments/blocklist",
+    ] {
+        let get = |bearer: &str| {
+            client
+                .get(format!("{}{}", app_address, path))
+                .bearer_auth(bearer)
+                .send()
+        };
+        assert_eq!(200, get(&admin.token).await.unwrap().status().as_u16());
+        assert_eq!(
+            403,
+            get(token).await.unwrap().status().as_u16(),
+            "令牌读取了 {}",
+            path
+        );
+    }
+
+    // 普通的读取不受影响
+    assert_eq!(200, me_status(&client, &app_address, token).await);
+}
@@ -0,0 +1,294 @@
+mod common;
+use common::{create_authenticated_user, create_user_with_role, login, spawn_app_with_pool};
+
+async fn find_user_id(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    username: &str,
+) -> i64 {
+    let response = client
+        .get(format!("{}/admin/users?q={}", app_address, username))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+    let json_body: serde_json::Value = response.json().await.unwrap();
+    json_body["data"][0]["id"].as_i64().unwrap()
+}
+
+#[tokio::test]
+async fn admin_routes_return_a_403_for_non_admin_users() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+
+    let response = client
+        .get(format!("{}/admin/users", &app_address))
+        .bearer_auth(&editor.token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn list_users_filters_by_role() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    create_authenticated_user(&client, &app_address).await;
+    create_user_with_role(&client, &app_address, &pool, "editor").await;
+
+    let response = client
+        .get(format!("{}/admin/users?role=editor", &app_address))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(200, response.status().as_u16());
+    let json_body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, json_body["total"]);
+    assert_eq!("editor", json_body["data"][0]["role"]);
+    assert!(json_body["data"][0].get("password_hash").is_none());
+}
+
+#[tokio::test]
+async fn update_user_role_takes_effect_immediately() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let user = create_authenticated_user(&client, &app_address).await;
+    let user_id = find_user_id(&client, &app_address, &admin.token, &user.username).await;
+
+    let response = client
+        .put(format!("{}/admin/users/{}/role", &app_address, user_id))
+        .bearer_auth(&admin.token)
+        .json(&serde_json::json!({ "role": "user" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn suspended_user_is_rejected_at_login_and_with_existing_token() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let user = create_authenticated_user(&client, &app_address).await;
+    let user_id = find_user_id(&client, &app_address, &admin.token, &user.username).await;
+
+    let response = client
+        .post(format!("{}/admin/users/{}/suspend", &app_address, user_id))
+        .bearer_auth(&admin.token)
+        .json(&serde_json::json!({ "reason": "spam" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let json_body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(true, json_body["suspended"]);
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let login_body = serde_json::json!({ "username": &user.username, "password": "password123" });
+    let response = client
+        .post(format!("{}/login", &app_address))
+        .json(&login_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = client
+        .post(format!(
+            "{}/admin/users/{}/unsuspend",
+            &app_address, user_id
+        ))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    login(&client, &app_address, &user.username, "password123").await;
+}
+
+#[tokio::test]
+async fn suspend_user_returns_a_400_for_an_expiry_in_the_past() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let user = create_authenticated_user(&client, &app_address).await;
+    let user_id = find_user_id(&client, &app_address, &admin.token, &user.username).await;
+
+    let response = client
+        .post(format!("{}/admin/users/{}/suspend", &app_address, user_id))
+        .bearer_auth(&admin.token)
+        .json(&serde_json::json!({ "reason": "spam", "until": "2000-01-01T00:00:00Z" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn delete_user_removes_the_user_and_their_posts() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let user = create_authenticated_user(&client, &app_address).await;
+    let user_id = find_user_id(&client, &app_address, &admin.token, &user.username).await;
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+
+    let response = client
+        .delete(format!("{}/admin/users/{}", &app_address, user_id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/admin/users/{}", &app_address, user_id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/posts", &app_address))
+        .send()
+        .await
+        .unwrap();
+    let json_body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(0, json_body["total"]);
+}
+
+#[tokio::test]
+async fn delete_user_keeps_replies_from_other_users() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let user = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let user_id = find_user_id(&client, &app_address, &admin.token, &user.username).await;
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&reader.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    let post_id = response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap();
+
+    // 读者 -> 被删除的用户 -> 读者 -> 读者
+    let mut parent_id = None;
+    let mut ids = Vec::new();
+    for author in [&reader, &user, &reader, &reader] {
+        let response = client
+            .post(format!("{}/posts/{}/comments", &app_address, post_id))
+            .bearer_auth(&author.token)
+            .json(&serde_json::json!({ "content": "reply", "parent_id": parent_id }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(201, response.status().as_u16());
+        let id = response.json::<serde_json::Value>().await.unwrap()["id"]
+            .as_i64()
+            .unwrap();
+        parent_id = Some(id);
+        ids.push(id);
+    }
+
+    let response = client
+        .delete(format!("{}/admin/users/{}", &app_address, user_id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/posts/{}/comments", &app_address, post_id))
+        .send()
+        .await
+        .unwrap();
+    let page: serde_json::Value = response.json().await.unwrap();
+    let thread: Vec<(i64, Option<i64>, i64)> = page["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comment| {
+            (
+                comment["id"].as_i64().unwrap(),
+                comment["parent_id"].as_i64(),
+                comment["depth"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (ids[0], None, 0),
+            (ids[2], Some(ids[0]), 1),
+            (ids[3], Some(ids[2]), 2)
+        ],
+        thread
+    );
+}
+
+#[tokio::test]
+async fn admin_cannot_delete_themselves() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let admin_id = find_user_id(&client, &app_address, &admin.token, &admin.username).await;
+
+    let response = client
+        .delete(format!("{}/admin/users/{}", &app_address, admin_id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(409, response.status().as_u16());
+}
@@ -1,5 +1,5 @@
 mod common;
-use common::spawn_app;
+use common::{create_authenticated_user, spawn_app};
 
 #[tokio::test]
 async fn register_returns_a_201_for_valid_form_data() {
@@ -12,7 +12,7 @@ async fn register_returns_a_201_for_valid_form_data() {
     });
 
     let response = client
-        .post(&format!("{}/register", app_address))
+        .post(format!("{}/register", app_address))
         .header("Content-Type", "application/json")
         .json(&body)
         .send()
@@ -34,7 +34,7 @@ async fn register_returns_a_422_when_fields_are_missing() {
 
     for (invalid_body, error_message) in test_cases {
         let response = client
-            .post(&format!("{}/register", app_address))
+            .post(format!("{}/register", app_address))
             .header("Content-Type", "application/json")
             .json(&invalid_body)
             .send()
@@ -67,7 +67,7 @@ async fn register_returns_a_400_when_fields_are_invalid() {
 
     for (invalid_body, error_message) in test_cases {
         let response = client
-            .post(&format!("{}/register", app_address))
+            .post(format!("{}/register", app_address))
             .header("Content-Type", "application/json")
             .json(&invalid_body)
             .send()
@@ -93,7 +93,7 @@ async fn register_returns_a_409_when_username_is_taken() {
     });
 
     let response1 = client
-        .post(&format!("{}/register", app_address))
+        .post(format!("{}/register", app_address))
         .header("Content-Type", "application/json")
         .json(&body)
         .send()
@@ -103,7 +103,7 @@ async fn register_returns_a_409_when_username_is_taken() {
     assert_eq!(response1.status().as_u16(), 201);
 
     let response2 = client
-        .post(&format!("{}/register", app_address))
+        .post(format!("{}/register", app_address))
         .header("Content-Type", "application/json")
         .json(&body)
         .send()
@@ -127,7 +127,7 @@ async fn login_returns_a_200_and_token_for_valid_credentials() {
     });
 
     let response_register = client
-        .post(&format!("{}/register", &app_address))
+        .post(format!("{}/register", &app_address))
         .header("Content-Type", "application/json")
         .json(&register_body)
         .send()
@@ -141,7 +141,7 @@ async fn login_returns_a_200_and_token_for_valid_credentials() {
     });
 
     let response_login = client
-        .post(&format!("{}/login", &app_address))
+        .post(format!("{}/login", &app_address))
         .header("Content-Type", "application/json")
         .json(&login_body)
         .send()
@@ -173,7 +173,7 @@ async fn login_returns_a_401_for_invalid_credentials() {
         "password": password
     });
     let response_register = client
-        .post(&format!("{}/register", &app_address))
+        .post(format!("{}/register", &app_address))
         .header("Content-Type", "application/json")
         .json(&register_body)
         .send()
@@ -198,7 +198,7 @@ async fn login_returns_a_401_for_invalid_credentials() {
 
     for (invalid_body, error_message) in test_cases {
         let response = client
-            .post(&format!("{}/login", &app_address))
+            .post(format!("{}/login", &app_address))
             .header("Content-Type", "application/json")
             .json(&invalid_body)
             .send()
@@ -213,3 +213,200 @@ async fn login_returns_a_401_for_invalid_credentials() {
         );
     }
 }
+
+#[tokio::test]
+async fn refresh_returns_a_new_token_pair_and_rotates_the_refresh_token() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let response = client
+        .post(format!("{}/token/refresh", &app_address))
+        .json(&serde_json::json!({ "refresh_token": &user.refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let json_body: serde_json::Value = response.json().await.unwrap();
+    let new_token = json_body["token"].as_str().unwrap();
+    let new_refresh_token = json_body["refresh_token"].as_str().unwrap();
+    assert_ne!(new_refresh_token, user.refresh_token);
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(new_token)
+        .json(&post_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(201, response.status().as_u16());
+
+    // 旧的刷新令牌已经轮换，不能再次使用
+    let response = client
+        .post(format!("{}/token/refresh", &app_address))
+        .json(&serde_json::json!({ "refresh_token": &user.refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn reusing_a_rotated_refresh_token_revokes_all_sessions() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let response = client
+        .post(format!("{}/token/refresh", &app_address))
+        .json(&serde_json::json!({ "refresh_token": &user.refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let json_body: serde_json::Value = response.json().await.unwrap();
+    let new_refresh_token = json_body["refresh_token"].as_str().unwrap();
+
+    let response = client
+        .post(format!("{}/token/refresh", &app_address))
+        .json(&serde_json::json!({ "refresh_token": &user.refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(format!("{}/token/refresh", &app_address))
+        .json(&serde_json::json!({ "refresh_token": new_refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn logout_revokes_the_access_token_and_refresh_token() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let response = client
+        .post(format!("{}/logout", &app_address))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "refresh_token": &user.refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&post_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(format!("{}/token/refresh", &app_address))
+        .json(&serde_json::json!({ "refresh_token": &user.refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn logout_all_revokes_every_session_of_the_user() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    // 同一用户在另一台设备上登录
+    let login_body = serde_json::json!({ "username": &user.username, "password": "password123" });
+    let response = client
+        .post(format!("{}/login", &app_address))
+        .json(&login_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let other_session: serde_json::Value = response.json().await.unwrap();
+    let other_token = other_session["token"].as_str().unwrap();
+    let other_refresh_token = other_session["refresh_token"].as_str().unwrap();
+
+    let response = client
+        .post(format!("{}/logout/all", &app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    for token in [user.token.as_str(), other_token] {
+        let response = client
+            .post(format!("{}/logout/all", &app_address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    for refresh_token in [user.refresh_token.as_str(), other_refresh_token] {
+        let response = client
+            .post(format!("{}/token/refresh", &app_address))
+            .json(&serde_json::json!({ "refresh_token": refresh_token }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(401, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn logout_all_revokes_access_tokens_issued_before_a_refresh() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    // 轮换刷新令牌后，旧的访问令牌在过期前仍然可以使用
+    let response = client
+        .post(format!("{}/token/refresh", &app_address))
+        .json(&serde_json::json!({ "refresh_token": &user.refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let rotated: serde_json::Value = response.json().await.unwrap();
+    let new_token = rotated["token"].as_str().unwrap();
+
+    let response = client
+        .post(format!("{}/logout/all", &app_address))
+        .bearer_auth(new_token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/me", &app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    // 之后重新登录得到的令牌不受影响
+    let user = common::login(&client, &app_address, &user.username, "password123").await;
+    let response = client
+        .get(format!("{}/me", &app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
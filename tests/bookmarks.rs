@@ -0,0 +1,181 @@
+mod common;
+use common::{create_authenticated_user, spawn_app};
+
+async fn create_post(client: &reqwest::Client, app_address: &str, token: &str, title: &str) -> i64 {
+    let post_body = serde_json::json!({ "title": title, "content": "c", "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn bookmark(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+) -> reqwest::Response {
+    client
+        .put(format!("{}/posts/{}/bookmark", app_address, post_id))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn bookmarked_titles(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+) -> Vec<String> {
+    let page: serde_json::Value = client
+        .get(format!("{}/me/bookmarks", app_address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        page["total"].as_u64().unwrap() as usize,
+        page["data"].as_array().unwrap().len()
+    );
+    page["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|post| post["title"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn bookmarks_are_listed_newest_first_without_deleted_posts() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let first = create_post(&client, &app_address, &author.token, "First").await;
+    let second = create_post(&client, &app_address, &author.token, "Second").await;
+
+    let response = client
+        .get(format!("{}/me/bookmarks", app_address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let response = bookmark(&client, &app_address, &reader.token, 999999).await;
+    assert_eq!(404, response.status().as_u16());
+
+    for post_id in [first, second, first] {
+        let response = bookmark(&client, &app_address, &reader.token, post_id).await;
+        assert_eq!(204, response.status().as_u16());
+    }
+    assert_eq!(
+        vec!["Second", "First"],
+        bookmarked_titles(&client, &app_address, &reader.token).await
+    );
+    assert!(
+        bookmarked_titles(&client, &app_address, &author.token)
+            .await
+            .is_empty()
+    );
+
+    // 文章被删除后从收藏列表中消失，而不是报错
+    let response = client
+        .delete(format!("{}/posts/{}", app_address, second))
+        .bearer_auth(&author.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+    assert_eq!(
+        vec!["First"],
+        bookmarked_titles(&client, &app_address, &reader.token).await
+    );
+    let response = bookmark(&client, &app_address, &reader.token, second).await;
+    assert_eq!(404, response.status().as_u16());
+
+    let unbookmark = || {
+        client
+            .delete(format!("{}/posts/{}/bookmark", app_address, first))
+            .bearer_auth(&reader.token)
+            .send()
+    };
+    assert_eq!(204, unbookmark().await.unwrap().status().as_u16());
+    assert_eq!(404, unbookmark().await.unwrap().status().as_u16());
+    assert!(
+        bookmarked_titles(&client, &app_address, &reader.token)
+            .await
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn bookmarking_missing_deleted_or_hidden_posts_returns_not_found() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let deleted = create_post(&client, &app_address, &author.token, "Deleted").await;
+    let draft: serde_json::Value = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(&author.token)
+        .json(&serde_json::json!({
+            "title": "Draft",
+            "content": "c",
+            "copyright": "c",
+            "status": "draft",
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let draft = draft["id"].as_i64().unwrap();
+
+    let response = client
+        .put(format!("{}/posts/{}/bookmark", app_address, deleted))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .delete(format!("{}/posts/{}", app_address, deleted))
+        .bearer_auth(&author.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+
+    // 不存在、已删除以及无权查看的草稿都与不存在的文章一样返回 404
+    for post_id in [999999, deleted, draft] {
+        let response = bookmark(&client, &app_address, &reader.token, post_id).await;
+        assert_eq!(404, response.status().as_u16(), "{}", post_id);
+        let response = client
+            .delete(format!("{}/posts/{}/bookmark", app_address, post_id))
+            .bearer_auth(&reader.token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(404, response.status().as_u16(), "{}", post_id);
+    }
+    assert!(
+        bookmarked_titles(&client, &app_address, &reader.token)
+            .await
+            .is_empty()
+    );
+
+    // 作者可以收藏自己的草稿
+    let response = bookmark(&client, &app_address, &author.token, draft).await;
+    assert_eq!(204, response.status().as_u16());
+}
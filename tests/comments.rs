@@ -1,11 +1,10 @@
 mod common;
-use common::{create_authenticated_user, spawn_app};
+use common::{create_authenticated_user, create_user_with_role, spawn_app, spawn_app_with_pool};
 
 async fn create_post(client: &reqwest::Client, app_address: &str, token: &str) -> i64 {
-    let post_body =
-        serde_json::json!({ "title": "Test Post", "content": "c", "tags": "t", "copyright": "c" });
+    let post_body = serde_json::json!({ "title": "Test Post", "content": "c", "tags": ["t"], "copyright": "c" });
     let response = client
-        .post(&format!("{}/posts", app_address))
+        .post(format!("{}/posts", app_address))
         .bearer_auth(token)
         .json(&post_body)
         .send()
@@ -24,7 +23,7 @@ async fn create_comment(
 ) -> i64 {
     let comment_body = serde_json::json!({ "content": "Test comment" });
     let response = client
-        .post(&format!("{}/posts/{}/comments", app_address, post_id))
+        .post(format!("{}/posts/{}/comments", app_address, post_id))
         .bearer_auth(token)
         .json(&comment_body)
         .send()
@@ -44,7 +43,7 @@ async fn create_comment_returns_a_201_for_valid_authenticated_request() {
 
     let comment_body = serde_json::json!({ "content": "A great comment" });
     let response = client
-        .post(&format!("{}/posts/{}/comments", &app_address, post_id))
+        .post(format!("{}/posts/{}/comments", &app_address, post_id))
         .bearer_auth(&user.token)
         .json(&comment_body)
         .send()
@@ -65,7 +64,7 @@ async fn create_comment_returns_a_401_for_unauthenticated_request() {
 
     let comment_body = serde_json::json!({ "content": "This should fail" });
     let response = client
-        .post(&format!("{}/posts/{}/comments", &app_address, post_id))
+        .post(format!("{}/posts/{}/comments", &app_address, post_id))
         .json(&comment_body)
         .send()
         .await
@@ -88,7 +87,7 @@ async fn create_comment_returns_a_400_for_invalid_data() {
 
     for (invalid_body, error_message) in test_cases {
         let response = client
-            .post(&format!("{}/posts/{}/comments", &app_address, post_id))
+            .post(format!("{}/posts/{}/comments", &app_address, post_id))
             .bearer_auth(&user.token)
             .json(&invalid_body)
             .send()
@@ -114,13 +113,14 @@ async fn get_comments_returns_a_list_of_comments_for_a_post() {
     create_comment(&client, &app_address, &user.token, post_id).await;
 
     let response = client
-        .get(&format!("{}/posts/{}/comments", &app_address, post_id))
+        .get(format!("{}/posts/{}/comments", &app_address, post_id))
         .send()
         .await
         .unwrap();
     assert_eq!(200, response.status().as_u16());
-    let comments: Vec<serde_json::Value> = response.json().await.unwrap();
-    assert_eq!(2, comments.len());
+    let comments: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(2, comments["total"]);
+    assert_eq!(2, comments["data"].as_array().unwrap().len());
 }
 
 #[tokio::test]
@@ -133,7 +133,7 @@ async fn update_comment_returns_a_200_for_valid_authenticated_request() {
 
     let updated_body = serde_json::json!({ "content": "Updated comment" });
     let response = client
-        .put(&format!(
+        .put(format!(
             "{}/posts/{}/comments/{}",
             &app_address, post_id, comment_id
         ))
@@ -159,7 +159,7 @@ async fn update_comment_returns_a_403_for_forbidden_request() {
 
     let updated_body = serde_json::json!({ "content": "Hacked comment" });
     let response = client
-        .put(&format!(
+        .put(format!(
             "{}/posts/{}/comments/{}",
             &app_address, post_id, comment_id
         ))
@@ -180,7 +180,7 @@ async fn delete_comment_returns_a_204_for_valid_authenticated_request() {
     let post_id = create_post(&client, &app_address, &user.token).await;
     let comment_id = create_comment(&client, &app_address, &user.token, post_id).await;
     let response = client
-        .delete(&format!(
+        .delete(format!(
             "{}/posts/{}/comments/{}",
             &app_address, post_id, comment_id
         ))
@@ -190,12 +190,13 @@ async fn delete_comment_returns_a_204_for_valid_authenticated_request() {
         .unwrap();
     assert_eq!(204, response.status().as_u16());
     let get_response = client
-        .get(&format!("{}/posts/{}/comments", &app_address, post_id))
+        .get(format!("{}/posts/{}/comments", &app_address, post_id))
         .send()
         .await
         .unwrap();
-    let comments: Vec<serde_json::Value> = get_response.json().await.unwrap();
-    assert!(comments.is_empty());
+    let comments: serde_json::Value = get_response.json().await.unwrap();
+    assert_eq!(0, comments["total"]);
+    assert!(comments["data"].as_array().unwrap().is_empty());
 }
 
 #[tokio::test]
@@ -206,7 +207,7 @@ async fn update_comment_returns_a_404_if_comment_is_soft_deleted() {
     let post_id = create_post(&client, &app_address, &user.token).await;
     let comment_id = create_comment(&client, &app_address, &user.token, post_id).await;
     client
-        .delete(&format!(
+        .delete(format!(
             "{}/posts/{}/comments/{}",
             &app_address, post_id, comment_id
         ))
@@ -216,7 +217,7 @@ async fn update_comment_returns_a_404_if_comment_is_soft_deleted() {
         .unwrap();
     let updated_body = serde_json::json!({ "content": "Should not update" });
     let update_response = client
-        .put(&format!(
+        .put(format!(
             "{}/posts/{}/comments/{}",
             &app_address, post_id, comment_id
         ))
@@ -238,7 +239,7 @@ async fn delete_comment_returns_a_403_for_forbidden_request() {
     let attacker = create_authenticated_user(&client, &app_address).await;
 
     let response = client
-        .delete(&format!(
+        .delete(format!(
             "{}/posts/{}/comments/{}",
             &app_address, post_id, comment_id
         ))
@@ -248,3 +249,306 @@ async fn delete_comment_returns_a_403_for_forbidden_request() {
         .unwrap();
     assert_eq!(403, response.status().as_u16());
 }
+
+#[tokio::test]
+async fn admin_can_delete_any_comment() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let commenter = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &commenter.token).await;
+    let comment_id = create_comment(&client, &app_address, &commenter.token, post_id).await;
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+
+    let response = client
+        .delete(format!(
+            "{}/posts/{}/comments/{}",
+            &app_address, post_id, comment_id
+        ))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn user_role_can_still_comment() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    let reader = create_user_with_role(&client, &app_address, &pool, "user").await;
+
+    let comment_body = serde_json::json!({ "content": "Nice post" });
+    let response = client
+        .post(format!("{}/posts/{}/comments", &app_address, post_id))
+        .bearer_auth(&reader.token)
+        .json(&comment_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+}
+
+async fn reply(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+    parent_id: i64,
+    content: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/posts/{}/comments", app_address, post_id))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "content": content, "parent_id": parent_id }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_comments(
+    client: &reqwest::Client,
+    app_address: &str,
+    post_id: i64,
+) -> Vec<serde_json::Value> {
+    get_comment_page(client, app_address, post_id, "").await["data"]
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+async fn get_comment_page(
+    client: &reqwest::Client,
+    app_address: &str,
+    post_id: i64,
+    query: &str,
+) -> serde_json::Value {
+    let response = client
+        .get(format!(
+            "{}/posts/{}/comments?{}",
+            app_address, post_id, query
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn replies_are_listed_depth_first_under_their_parent() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+
+    let first = create_comment(&client, &app_address, &user.token, post_id).await;
+    let second = create_comment(&client, &app_address, &user.token, post_id).await;
+    let response = reply(&client, &app_address, &user.token, post_id, first, "reply").await;
+    assert_eq!(201, response.status().as_u16());
+    let first_reply = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(1, first_reply["depth"]);
+    let first_reply = first_reply["id"].as_i64().unwrap();
+    reply(
+        &client,
+        &app_address,
+        &user.token,
+        post_id,
+        first_reply,
+        "nested",
+    )
+    .await;
+
+    let comments = get_comments(&client, &app_address, post_id).await;
+    let order: Vec<(i64, i64)> = comments
+        .iter()
+        .map(|c| (c["id"].as_i64().unwrap(), c["depth"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(4, order.len());
+    assert_eq!((first, 0), order[0]);
+    assert_eq!((first_reply, 1), order[1]);
+    assert_eq!(2, order[2].1);
+    assert_eq!((second, 0), order[3]);
+    assert_eq!(first_reply, comments[2]["parent_id"].as_i64().unwrap());
+}
+
+#[tokio::test]
+async fn replies_are_rejected_beyond_the_depth_limit_or_across_posts() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+    let other_post_id = create_post(&client, &app_address, &user.token).await;
+
+    let mut parent = create_comment(&client, &app_address, &user.token, post_id).await;
+    for _ in 0..5 {
+        let response = reply(&client, &app_address, &user.token, post_id, parent, "r").await;
+        assert_eq!(201, response.status().as_u16());
+        parent = response.json::<serde_json::Value>().await.unwrap()["id"]
+            .as_i64()
+            .unwrap();
+    }
+    let response = reply(&client, &app_address, &user.token, post_id, parent, "r").await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = reply(
+        &client,
+        &app_address,
+        &user.token,
+        other_post_id,
+        parent,
+        "r",
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn deleted_parent_is_shown_as_placeholder_while_replies_remain() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+    let parent = create_comment(&client, &app_address, &user.token, post_id).await;
+    let lonely = create_comment(&client, &app_address, &user.token, post_id).await;
+    reply(
+        &client,
+        &app_address,
+        &user.token,
+        post_id,
+        parent,
+        "still here",
+    )
+    .await;
+
+    for comment_id in [parent, lonely] {
+        client
+            .delete(format!(
+                "{}/posts/{}/comments/{}",
+                &app_address, post_id, comment_id
+            ))
+            .bearer_auth(&user.token)
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let comments = get_comments(&client, &app_address, post_id).await;
+    assert_eq!(2, comments.len());
+    assert_eq!(parent, comments[0]["id"].as_i64().unwrap());
+    assert_eq!(true, comments[0]["deleted"]);
+    assert_eq!("[deleted]", comments[0]["content"]);
+    assert_eq!("[deleted]", comments[0]["author"]);
+    assert_eq!("still here", comments[1]["content"]);
+
+    // 已删除的评论不能再被回复
+    let response = reply(&client, &app_address, &user.token, post_id, parent, "r").await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn comments_are_paged_by_thread_and_sortable() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+
+    let first = create_comment(&client, &app_address, &user.token, post_id).await;
+    let second = create_comment(&client, &app_address, &user.token, post_id).await;
+    let third = create_comment(&client, &app_address, &user.token, post_id).await;
+    for _ in 0..2 {
+        reply(&client, &app_address, &user.token, post_id, second, "r").await;
+    }
+
+    let ids = |page: &serde_json::Value| -> Vec<i64> {
+        page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|comment| comment["depth"] == 0)
+            .map(|comment| comment["id"].as_i64().unwrap())
+            .collect()
+    };
+
+    let page = get_comment_page(&client, &app_address, post_id, "page_size=2").await;
+    assert_eq!(3, page["total"]);
+    assert_eq!(2, page["total_pages"]);
+    assert_eq!(vec![first, second], ids(&page));
+    // 回复随所属的顶层评论一起返回，不占用分页数量
+    assert_eq!(4, page["data"].as_array().unwrap().len());
+
+    let page = get_comment_page(&client, &app_address, post_id, "sort=newest").await;
+    assert_eq!(vec![third, second, first], ids(&page));
+
+    let page = get_comment_page(&client, &app_address, post_id, "sort=top&page_size=1").await;
+    assert_eq!(vec![second], ids(&page));
+    assert_eq!(3, page["data"].as_array().unwrap().len());
+}
+
+#[tokio::test]
+async fn get_comments_returns_a_404_for_missing_or_deleted_post() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+    client
+        .delete(format!("{}/posts/{}", &app_address, post_id))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+
+    for id in [post_id, 999_999] {
+        let response = client
+            .get(format!("{}/posts/{}/comments", &app_address, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(404, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn comments_of_a_draft_post_follow_the_post_visibility() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let stranger = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    create_comment(&client, &app_address, &stranger.token, post_id).await;
+
+    // 文章改回草稿后，评论也只有作者本人和编辑能看到
+    let response = client
+        .put(format!("{}/posts/{}", &app_address, post_id))
+        .bearer_auth(&author.token)
+        .json(&serde_json::json!({ "title": "Test Post", "content": "c", "copyright": "c", "status": "draft" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    let url = format!("{}/posts/{}/comments", &app_address, post_id);
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(404, response.status().as_u16());
+    let response = client
+        .get(&url)
+        .bearer_auth(&stranger.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+
+    for viewer in [&author, &editor] {
+        let response = client
+            .get(&url)
+            .bearer_auth(&viewer.token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(200, response.status().as_u16());
+        let page: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(1, page["total"]);
+    }
+}
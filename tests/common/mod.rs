@@ -1,51 +1,209 @@
-use inkwell::{config::Config, models::AppState, routes::create_router};
-use sqlx::SqlitePool;
+#![allow(dead_code)]
+
+use inkwell::{
+    config::Config,
+    mail::{MailMessage, MailTransportKind, Mailer, MemoryOutbox},
+    models::{AppState, ModerationMode},
+    permissions::Role,
+    routes::create_router,
+    spam::SpamFilter,
+    utils::PasswordAlgorithm,
+};
+use sqlx::{SqlitePool, migrate::Migrator};
+use std::{net::SocketAddr, path::Path, sync::Arc};
 use tokio::net::TcpListener;
 
 pub async fn spawn_app() -> String {
+    spawn_app_with_pool().await.0
+}
+
+/// 启动测试服务，同时返回数据库连接池以便直接准备测试数据
+pub async fn spawn_app_with_pool() -> (String, SqlitePool) {
+    let (address, pool, _) = spawn_app_with_outbox().await;
+    (address, pool)
+}
+
+/// 启动测试服务，同时返回数据库连接池和保存已发送邮件的发件箱
+pub async fn spawn_app_with_outbox() -> (String, SqlitePool, Arc<MemoryOutbox>) {
+    spawn_app_with_config(|_| {}).await
+}
+
+/// 启动测试服务，`configure` 可以在默认的测试配置上做修改
+pub async fn spawn_app_with_config(
+    configure: impl FnOnce(&mut Config),
+) -> (String, SqlitePool, Arc<MemoryOutbox>) {
+    let pool = SqlitePool::connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to SQLite in-memory database.");
+    spawn_app_on(pool, configure).await
+}
+
+/// 在已有的数据库上执行剩余的迁移并启动测试服务
+pub async fn spawn_app_on(
+    pool: SqlitePool,
+    configure: impl FnOnce(&mut Config),
+) -> (String, SqlitePool, Arc<MemoryOutbox>) {
     let listener = TcpListener::bind("127.0.0.1:0")
         .await
         .expect("Failed to bind random port");
     let port = listener.local_addr().unwrap().port();
     let address = format!("http://127.0.0.1:{}", port);
 
-    let config = Config {
+    let mut config = Config {
         database_url: "sqlite::memory:".to_string(),
         jwt_secret: "test_secret".to_string(),
         server_host: "127.0.0.1".to_string(),
         server_port: port,
         db_max_connections: 1,
-        jwt_expiration_days: 1,
+        access_token_expiration_minutes: 15,
+        refresh_token_expiration_days: 30,
+        default_user_role: Role::Author,
+        scheduler_interval_seconds: 60,
+        comment_moderation: ModerationMode::Open,
+        report_hide_threshold: 3,
+        mail_transport: MailTransportKind::Memory,
+        mail_outbox_dir: "outbox".to_string(),
+        mail_from: "noreply@example.com".to_string(),
+        smtp_url: None,
+        mail_retry_interval_seconds: 60,
+        public_url: address.clone(),
+        password_reset_expiration_minutes: 30,
+        email_verification_expiration_hours: 24,
+        two_factor_required_roles: Vec::new(),
+        two_factor_challenge_expiration_minutes: 5,
+        totp_issuer: "Inkwell".to_string(),
+        login_max_failures_per_account: 5,
+        login_max_failures_per_ip: 20,
+        login_lockout_base_seconds: 60,
+        login_lockout_max_minutes: 60,
+        trust_proxy_headers: false,
+        // 测试中使用较小的参数，避免哈希拖慢测试
+        password_hash_algorithm: PasswordAlgorithm::Argon2id,
+        argon2_memory_kib: 1024,
+        argon2_iterations: 1,
+        argon2_parallelism: 1,
+        bcrypt_cost: 4,
     };
-
-    let pool = SqlitePool::connect(&config.database_url)
-        .await
-        .expect("Failed to connect to SQLite in-memory database.");
+    configure(&mut config);
 
     sqlx::migrate!("./migrations")
         .run(&pool)
         .await
         .expect("Failed to migrate the database.");
 
+    let outbox = Arc::new(MemoryOutbox::default());
     let app_state = AppState {
-        pool,
+        pool: pool.clone(),
         config: config.clone(),
+        spam_filter: Arc::new(SpamFilter::default()),
+        mailer: Arc::new(Mailer::new(outbox.clone())),
     };
 
     let app = create_router(app_state.clone()).with_state(app_state);
 
     tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
     });
 
-    address
+    (address, pool, outbox)
+}
+
+/// 只执行版本号小于 `version` 的迁移，用来按升级前的表结构准备数据
+pub async fn migrate_before(pool: &SqlitePool, version: i64) {
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+    let dir = std::env::temp_dir().join(format!("inkwell-migrations-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for entry in std::fs::read_dir(&source).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        let file_version: i64 = name.split('_').next().unwrap().parse().unwrap();
+        if file_version < version {
+            std::fs::copy(&path, dir.join(&name)).unwrap();
+        }
+    }
+
+    Migrator::new(dir.as_path())
+        .await
+        .unwrap()
+        .run(pool)
+        .await
+        .expect("Failed to migrate the database.");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 等待后台任务发出至少 `count` 封邮件，返回目前为止的全部邮件
+pub async fn wait_for_messages(outbox: &MemoryOutbox, count: usize) -> Vec<MailMessage> {
+    for _ in 0..100 {
+        let messages = outbox.messages();
+        if messages.len() >= count {
+            return messages;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!(
+        "Expected {} messages, got {}",
+        count,
+        outbox.messages().len()
+    );
 }
 
 pub struct TestUser {
     pub token: String,
+    pub refresh_token: String,
     pub username: String,
 }
 
+/// 创建一个指定角色的用户，并重新登录以获得包含该角色的令牌
+pub async fn create_user_with_role(
+    client: &reqwest::Client,
+    app_address: &str,
+    pool: &SqlitePool,
+    role: &str,
+) -> TestUser {
+    let user = create_authenticated_user(client, app_address).await;
+
+    sqlx::query("UPDATE users SET role = ? WHERE username = ?")
+        .bind(role)
+        .bind(&user.username)
+        .execute(pool)
+        .await
+        .expect("Failed to update user role.");
+
+    login(client, app_address, &user.username, "password123").await
+}
+
+pub async fn login(
+    client: &reqwest::Client,
+    app_address: &str,
+    username: &str,
+    password: &str,
+) -> TestUser {
+    let login_body = serde_json::json!({ "username": username, "password": password });
+    let login_response = client
+        .post(format!("{}/login", app_address))
+        .json(&login_body)
+        .send()
+        .await
+        .expect("Failed to login user during test setup.");
+    assert_eq!(
+        200,
+        login_response.status().as_u16(),
+        "User login failed in helper"
+    );
+
+    let login_json: serde_json::Value = login_response.json().await.unwrap();
+    TestUser {
+        token: login_json["token"].as_str().unwrap().to_string(),
+        refresh_token: login_json["refresh_token"].as_str().unwrap().to_string(),
+        username: username.to_string(),
+    }
+}
+
 pub async fn create_authenticated_user(client: &reqwest::Client, app_address: &str) -> TestUser {
     let username = format!(
         "user_{}",
@@ -55,7 +213,7 @@ pub async fn create_authenticated_user(client: &reqwest::Client, app_address: &s
 
     let register_body = serde_json::json!({ "username": &username, "password": &password });
     let register_response = client
-        .post(&format!("{}/register", app_address))
+        .post(format!("{}/register", app_address))
         .json(&register_body)
         .send()
         .await
@@ -70,21 +228,5 @@ pub async fn create_authenticated_user(client: &reqwest::Client, app_address: &s
         );
     }
 
-    let login_body = serde_json::json!({ "username": &username, "password": &password });
-    let login_response = client
-        .post(&format!("{}/login", app_address))
-        .json(&login_body)
-        .send()
-        .await
-        .expect("Failed to login user during test setup.");
-    assert_eq!(
-        200,
-        login_response.status().as_u16(),
-        "User login failed in helper"
-    );
-
-    let login_json: serde_json::Value = login_response.json().await.unwrap();
-    let token = login_json["token"].as_str().unwrap().to_string();
-
-    TestUser { token, username }
+    login(client, app_address, &username, password).await
 }
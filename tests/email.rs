@@ -0,0 +1,420 @@
+mod common;
+use common::{
+    create_authenticated_user, login, spawn_app_with_outbox, spawn_app_with_pool, wait_for_messages,
+};
+use inkwell::mail::{
+    MailError, MailMessage, MailTransport, Mailer, SendFuture, Template, queue::MAX_ATTEMPTS,
+};
+use sqlx::SqlitePool;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+async fn register(
+    client: &reqwest::Client,
+    app_address: &str,
+    username: &str,
+    email: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/register", app_address))
+        .json(&serde_json::json!({
+            "username": username,
+            "password": "password123",
+            "email": email,
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_me(client: &reqwest::Client, app_address: &str, token: &str) -> serde_json::Value {
+    client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+async fn verify(client: &reqwest::Client, app_address: &str, token: &str) -> u16 {
+    client
+        .post(format!("{}/email/verify", app_address))
+        .json(&serde_json::json!({ "token": token }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+async fn update_email(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    email: serde_json::Value,
+) -> reqwest::Response {
+    client
+        .put(format!("{}/me/email", app_address))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "email": email }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn resend(client: &reqwest::Client, app_address: &str, token: &str) -> u16 {
+    client
+        .post(format!("{}/me/email/verification", app_address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+/// 从邮件正文的验证链接中取出令牌
+fn verification_token(body: &str) -> String {
+    let start = body.find("token=").expect("邮件中没有验证链接") + "token=".len();
+    body[start..]
+        .chars()
+        .take_while(|ch| ch.is_ascii_alphanumeric())
+        .collect()
+}
+
+/// 前 `failures` 次投递失败，之后投递成功的发送方式
+struct FlakyTransport {
+    failures: usize,
+    calls: AtomicUsize,
+}
+
+impl MailTransport for FlakyTransport {
+    fn send<'a>(&'a self, _message: &'a MailMessage) -> SendFuture<'a> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        let failures = self.failures;
+        Box::pin(async move {
+            if call < failures {
+                return Err(MailError::from("connection refused"));
+            }
+            Ok(())
+        })
+    }
+}
+
+fn message() -> MailMessage {
+    MailMessage {
+        to: "someone@example.com".to_string(),
+        subject: "测试".to_string(),
+        text_body: "正文".to_string(),
+        html_body: Some("<p>正文</p>".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn registering_with_an_email_sends_a_verification_link() {
+    let (app_address, _, outbox) = spawn_app_with_outbox().await;
+    let client = reqwest::Client::new();
+
+    let response = register(&client, &app_address, "verify_me", "Verify.Me@Example.com").await;
+    assert_eq!(201, response.status().as_u16());
+    let user = login(&client, &app_address, "verify_me", "password123").await;
+
+    let messages = outbox.messages();
+    assert_eq!(1, messages.len());
+    assert_eq!("verify.me@example.com", messages[0].to);
+    let link = format!("{}/verify-email?token=", app_address);
+    assert!(messages[0].text_body.contains(&link));
+    assert!(messages[0].html_body.as_ref().unwrap().contains(&link));
+
+    let me = get_me(&client, &app_address, &user.token).await;
+    assert_eq!("verify.me@example.com", me["email"]);
+    assert_eq!(false, me["email_verified"]);
+
+    let token = verification_token(&messages[0].text_body);
+    assert_eq!(400, verify(&client, &app_address, "not-a-token").await);
+    assert_eq!(204, verify(&client, &app_address, &token).await);
+    assert_eq!(400, verify(&client, &app_address, &token).await);
+
+    let me = get_me(&client, &app_address, &user.token).await;
+    assert_eq!(true, me["email_verified"]);
+    assert_eq!(400, resend(&client, &app_address, &user.token).await);
+}
+
+#[tokio::test]
+async fn changing_the_email_requires_verifying_it_again() {
+    let (app_address, pool, outbox) = spawn_app_with_outbox().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    register(&client, &app_address, "email_owner", "taken@example.com").await;
+    let owner_token = verification_token(&outbox.messages().last().unwrap().text_body);
+    assert_eq!(204, verify(&client, &app_address, &owner_token).await);
+    let sent = outbox.messages().len();
+
+    // 没有邮箱时无法重新发送
+    assert_eq!(400, resend(&client, &app_address, &user.token).await);
+
+    let response = update_email(
+        &client,
+        &app_address,
+        &user.token,
+        "taken@example.com".into(),
+    )
+    .await;
+    assert_eq!(409, response.status().as_u16());
+    let response = update_email(&client, &app_address, &user.token, "not-an-email".into()).await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = update_email(
+        &client,
+        &app_address,
+        &user.token,
+        "first@example.com".into(),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let me: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("first@example.com", me["email"]);
+    assert_eq!(false, me["email_verified"]);
+    assert_eq!(sent + 1, outbox.messages().len());
+    let first_token = verification_token(&outbox.messages()[sent].text_body);
+
+    // 重新发送后旧链接失效
+    assert_eq!(202, resend(&client, &app_address, &user.token).await);
+    let resent_token = verification_token(&outbox.messages()[sent + 1].text_body);
+    assert_eq!(400, verify(&client, &app_address, &first_token).await);
+
+    // 更换邮箱后，发给旧邮箱的链接不能验证新邮箱
+    let response = update_email(
+        &client,
+        &app_address,
+        &user.token,
+        "second@example.com".into(),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(400, verify(&client, &app_address, &resent_token).await);
+
+    let messages = outbox.messages();
+    assert_eq!("second@example.com", messages[sent + 2].to);
+    let token = verification_token(&messages[sent + 2].text_body);
+    assert_eq!(204, verify(&client, &app_address, &token).await);
+
+    // 邮箱没有变化时保留验证状态
+    let response = update_email(
+        &client,
+        &app_address,
+        &user.token,
+        "Second@Example.com".into(),
+    )
+    .await;
+    let me: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(true, me["email_verified"]);
+    assert_eq!(sent + 3, outbox.messages().len());
+
+    let response = update_email(&client, &app_address, &user.token, serde_json::Value::Null).await;
+    let me: serde_json::Value = response.json().await.unwrap();
+    assert!(me["email"].is_null());
+    assert_eq!(false, me["email_verified"]);
+
+    // 过期的链接无法使用
+    update_email(
+        &client,
+        &app_address,
+        &user.token,
+        "third@example.com".into(),
+    )
+    .await;
+    let token = verification_token(&outbox.messages()[sent + 3].text_body);
+    sqlx::query("UPDATE email_verification_tokens SET expires_at = datetime('now', '-1 minute')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(400, verify(&client, &app_address, &token).await);
+}
+
+#[tokio::test]
+async fn unverified_emails_do_not_block_the_owner() {
+    let (app_address, pool, outbox) = spawn_app_with_outbox().await;
+    let client = reqwest::Client::new();
+
+    // 未验证的邮箱不阻止其他用户注册，也不能用来找回密码
+    let response = register(&client, &app_address, "squatter", "owner@example.com").await;
+    assert_eq!(201, response.status().as_u16());
+    let squatter_token = verification_token(&outbox.messages()[0].text_body);
+    let response = register(&client, &app_address, "real_owner", "owner@example.com").await;
+    assert_eq!(201, response.status().as_u16());
+    let owner_token = verification_token(&outbox.messages()[1].text_body);
+
+    let response = client
+        .post(format!("{}/password/forgot", app_address))
+        .json(&serde_json::json!({ "email": "owner@example.com" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(202, response.status().as_u16());
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert_eq!(2, outbox.messages().len());
+
+    // 一个用户验证后，其他用户的这个邮箱被清空，发给他们的验证链接失效
+    assert_eq!(204, verify(&client, &app_address, &owner_token).await);
+    assert_eq!(400, verify(&client, &app_address, &squatter_token).await);
+    let (email,): (Option<String>,) =
+        sqlx::query_as("SELECT email FROM users WHERE username = 'squatter'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(None, email);
+    let response = register(&client, &app_address, "late_comer", "owner@example.com").await;
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn accounts_are_kept_when_the_verification_email_cannot_be_queued() {
+    let (app_address, pool, outbox) = spawn_app_with_outbox().await;
+    let client = reqwest::Client::new();
+    sqlx::query(
+        "CREATE TRIGGER fail_verification BEFORE INSERT ON email_verification_tokens \
+         BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // 账户已经保存，邮件发送失败不影响注册结果，重试会得到 409 而不是重复创建
+    let response = register(&client, &app_address, "unlucky", "unlucky@example.com").await;
+    assert_eq!(201, response.status().as_u16());
+    let response = register(&client, &app_address, "unlucky", "unlucky@example.com").await;
+    assert_eq!(409, response.status().as_u16());
+    let user = login(&client, &app_address, "unlucky", "password123").await;
+    let response = update_email(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!("still.unlucky@example.com"),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    assert!(outbox.messages().is_empty());
+
+    // 恢复后重新发送验证邮件
+    sqlx::query("DROP TRIGGER fail_verification")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(202, resend(&client, &app_address, &user.token).await);
+    let messages = wait_for_messages(&outbox, 1).await;
+    assert_eq!("still.unlucky@example.com", messages[0].to);
+    let token = verification_token(&messages[0].text_body);
+    assert_eq!(204, verify(&client, &app_address, &token).await);
+}
+
+async fn stored_bodies(pool: &SqlitePool) -> (String, Option<String>) {
+    sqlx::query_as("SELECT text_body, html_body FROM mail_queue")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried_from_the_queue() {
+    let (_, pool) = spawn_app_with_pool().await;
+    let transport = Arc::new(FlakyTransport {
+        failures: 1,
+        calls: AtomicUsize::new(0),
+    });
+    let mailer = Mailer::new(transport.clone());
+
+    mailer.send(&pool, message()).await.unwrap();
+    let (status, attempts, last_error): (String, i64, Option<String>) =
+        sqlx::query_as("SELECT status, attempts, last_error FROM mail_queue")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!("pending", status);
+    assert_eq!(1, attempts);
+    assert_eq!(Some("connection refused".to_string()), last_error);
+    assert_eq!(
+        ("正文".to_string(), Some("<p>正文</p>".to_string())),
+        stored_bodies(&pool).await
+    );
+
+    // 还没到重试时间
+    assert_eq!(0, mailer.retry_due(&pool).await.unwrap());
+    assert_eq!(1, transport.calls.load(Ordering::SeqCst));
+
+    sqlx::query("UPDATE mail_queue SET next_attempt_at = datetime('now', '-1 minute')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(1, mailer.retry_due(&pool).await.unwrap());
+    let (status, attempts, last_error): (String, i64, Option<String>) =
+        sqlx::query_as("SELECT status, attempts, last_error FROM mail_queue")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!("sent", status);
+    assert_eq!(2, attempts);
+    assert_eq!(None, last_error);
+    // 投递成功后不再保存正文
+    assert_eq!(("".to_string(), None), stored_bodies(&pool).await);
+
+    // 已发送的邮件不再重试
+    assert_eq!(0, mailer.retry_due(&pool).await.unwrap());
+    assert_eq!(2, transport.calls.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn mail_is_marked_failed_after_the_last_attempt() {
+    let (_, pool) = spawn_app_with_pool().await;
+    let mailer = Mailer::new(Arc::new(FlakyTransport {
+        failures: usize::MAX,
+        calls: AtomicUsize::new(0),
+    }));
+
+    mailer.send(&pool, message()).await.unwrap();
+    for _ in 1..MAX_ATTEMPTS {
+        sqlx::query("UPDATE mail_queue SET next_attempt_at = datetime('now', '-1 minute')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(0, mailer.retry_due(&pool).await.unwrap());
+    }
+
+    let (status, attempts): (String, i64) =
+        sqlx::query_as("SELECT status, attempts FROM mail_queue")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!("failed", status);
+    assert_eq!(MAX_ATTEMPTS, attempts);
+    assert_eq!(("".to_string(), None), stored_bodies(&pool).await);
+}
+
+#[test]
+fn templates_render_text_and_escaped_html() {
+    let message = Template::PasswordReset {
+        username: "<b>tom</b>",
+        link: "https://example.com/reset-password?token=abc&x=1",
+        expires_in_minutes: 30,
+    }
+    .render("tom@example.com");
+
+    assert_eq!("tom@example.com", message.to);
+    assert!(message.text_body.starts_with("<b>tom</b>，你好"));
+    assert!(message.text_body.contains("30 分钟"));
+    assert!(
+        message
+            .text_body
+            .contains("https://example.com/reset-password?token=abc&x=1")
+    );
+
+    let html = message.html_body.unwrap();
+    assert!(html.contains("&lt;b&gt;tom&lt;/b&gt;"));
+    assert!(!html.contains("<b>tom</b>"));
+    assert!(html.contains("<a href=\"https://example.com/reset-password?token=abc&amp;x=1\">"));
+}
@@ -0,0 +1,274 @@
+mod common;
+use common::{create_authenticated_user, spawn_app};
+
+async fn create_post(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    title: &str,
+    status: &str,
+) -> i64 {
+    let post_body =
+        serde_json::json!({ "title": title, "content": "c", "copyright": "c", "status": status });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn follow(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    username: &str,
+    follow: bool,
+) -> u16 {
+    let url = format!("{}/users/{}/follow", app_address, username);
+    let request = if follow {
+        client.put(url)
+    } else {
+        client.delete(url)
+    };
+    request
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+async fn get_json(client: &reqwest::Client, url: String, token: &str) -> (u16, serde_json::Value) {
+    let response = client.get(url).bearer_auth(token).send().await.unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap_or_default())
+}
+
+#[tokio::test]
+async fn follows_update_profile_counts() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    create_post(&client, &app_address, &author.token, "Public", "published").await;
+    create_post(&client, &app_address, &author.token, "Draft", "draft").await;
+
+    assert_eq!(
+        400,
+        follow(&client, &app_address, &author.token, &author.username, true).await
+    );
+    assert_eq!(
+        404,
+        follow(&client, &app_address, &author.token, "nobody_here", true).await
+    );
+    for _ in 0..2 {
+        assert_eq!(
+            204,
+            follow(&client, &app_address, &reader.token, &author.username, true).await
+        );
+    }
+
+    let profile: serde_json::Value = client
+        .get(format!("{}/users/{}", app_address, author.username))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(author.username, profile["username"]);
+    assert_eq!(1, profile["post_count"]);
+    assert_eq!(1, profile["follower_count"]);
+    assert_eq!(0, profile["following_count"]);
+
+    let (_, profile) = get_json(
+        &client,
+        format!("{}/users/{}", app_address, reader.username),
+        &reader.token,
+    )
+    .await;
+    assert_eq!(0, profile["follower_count"]);
+    assert_eq!(1, profile["following_count"]);
+
+    assert_eq!(
+        204,
+        follow(
+            &client,
+            &app_address,
+            &reader.token,
+            &author.username,
+            false
+        )
+        .await
+    );
+    assert_eq!(
+        404,
+        follow(
+            &client,
+            &app_address,
+            &reader.token,
+            &author.username,
+            false
+        )
+        .await
+    );
+    let (status, _) = get_json(
+        &client,
+        format!("{}/users/nobody_here", app_address),
+        &reader.token,
+    )
+    .await;
+    assert_eq!(404, status);
+}
+
+#[tokio::test]
+async fn feed_pages_through_followed_authors_newest_first() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let followed = create_authenticated_user(&client, &app_address).await;
+    let other = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+
+    let mut expected = Vec::new();
+    for i in 0..3 {
+        let title = format!("Followed {}", i);
+        expected
+            .push(create_post(&client, &app_address, &followed.token, &title, "published").await);
+        create_post(&client, &app_address, &other.token, "Other", "published").await;
+    }
+    create_post(&client, &app_address, &followed.token, "Draft", "draft").await;
+    expected.reverse();
+
+    let feed_url = format!("{}/me/feed", app_address);
+    let (status, feed) = get_json(&client, feed_url.clone(), &reader.token).await;
+    assert_eq!(200, status);
+    assert!(feed["data"].as_array().unwrap().is_empty());
+    assert!(feed["next_cursor"].is_null());
+
+    follow(
+        &client,
+        &app_address,
+        &reader.token,
+        &followed.username,
+        true,
+    )
+    .await;
+
+    let mut seen = Vec::new();
+    let mut url = format!("{}?limit=2", feed_url);
+    loop {
+        let (status, feed) = get_json(&client, url, &reader.token).await;
+        assert_eq!(200, status);
+        seen.extend(
+            feed["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|post| post["id"].as_i64().unwrap()),
+        );
+        match feed["next_cursor"].as_i64() {
+            Some(cursor) => url = format!("{}?limit=2&before={}", feed_url, cursor),
+            None => break,
+        }
+    }
+    assert_eq!(expected, seen);
+
+    let (status, _) = get_json(
+        &client,
+        format!("{}?before=999999", feed_url),
+        &reader.token,
+    )
+    .await;
+    assert_eq!(400, status);
+    let (status, _) = get_json(&client, format!("{}?limit=0", feed_url), &reader.token).await;
+    assert_eq!(400, status);
+}
+
+#[tokio::test]
+async fn follow_and_feed_error_paths() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let oldest = create_post(&client, &app_address, &author.token, "Oldest", "published").await;
+    let newest = create_post(&client, &app_address, &author.token, "Newest", "published").await;
+    let draft = create_post(&client, &app_address, &author.token, "Draft", "draft").await;
+
+    let response = client
+        .put(format!("{}/users/{}/follow", app_address, author.username))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(
+        400,
+        follow(&client, &app_address, &reader.token, &reader.username, true).await
+    );
+    for follows in [true, false] {
+        assert_eq!(
+            404,
+            follow(&client, &app_address, &reader.token, "nobody", follows).await
+        );
+    }
+    // 取消关注自己或尚未关注的用户没有可删除的关注关系，返回 404
+    assert_eq!(
+        404,
+        follow(
+            &client,
+            &app_address,
+            &reader.token,
+            &reader.username,
+            false
+        )
+        .await
+    );
+    assert_eq!(
+        404,
+        follow(
+            &client,
+            &app_address,
+            &reader.token,
+            &author.username,
+            false
+        )
+        .await
+    );
+
+    assert_eq!(
+        204,
+        follow(&client, &app_address, &reader.token, &author.username, true).await
+    );
+    let feed_url = format!("{}/me/feed", app_address);
+    let (status, feed) = get_json(&client, format!("{}?limit=1", feed_url), &reader.token).await;
+    assert_eq!(200, status);
+    assert_eq!(newest, feed["data"][0]["id"].as_i64().unwrap());
+    assert_eq!(newest, feed["next_cursor"].as_i64().unwrap());
+
+    // 游标已经是最后一篇文章时返回空页，而不是错误
+    let (status, feed) = get_json(
+        &client,
+        format!("{}?before={}", feed_url, oldest),
+        &reader.token,
+    )
+    .await;
+    assert_eq!(200, status);
+    assert!(feed["data"].as_array().unwrap().is_empty());
+    assert!(feed["next_cursor"].is_null());
+
+    // 未发布的文章不能作为游标
+    let (status, _) = get_json(
+        &client,
+        format!("{}?before={}", feed_url, draft),
+        &reader.token,
+    )
+    .await;
+    assert_eq!(400, status);
+    let (status, _) = get_json(&client, format!("{}?before=abc", feed_url), &reader.token).await;
+    assert_eq!(400, status);
+}
@@ -0,0 +1,223 @@
+mod common;
+use common::{create_authenticated_user, create_user_with_role, spawn_app_with_config};
+
+async fn attempt_login(
+    client: &reqwest::Client,
+    app_address: &str,
+    username: &str,
+    password: &str,
+    ip: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/login", app_address))
+        .header("X-Forwarded-For", ip)
+        .json(&serde_json::json!({ "username": username, "password": password }))
+        .send()
+        .await
+        .unwrap()
+}
+
+fn retry_after(response: &reqwest::Response) -> u64 {
+    response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn accounts_are_locked_after_repeated_failures_and_unlocked_by_admins() {
+    let (app_address, pool, _) = spawn_app_with_config(|config| {
+        config.trust_proxy_headers = true;
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+
+    // 登录成功后清零计数
+    for _ in 0..4 {
+        let response = attempt_login(
+            &client,
+            &app_address,
+            &user.username,
+            "wrong",
+            "198.51.100.1",
+        )
+        .await;
+        assert_eq!(401, response.status().as_u16());
+    }
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "password123",
+        "198.51.100.1",
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+
+    // 换了客户端地址也无法绕过账户锁定，锁定期间正确的密码同样被拒绝
+    for i in 0..5 {
+        let ip = format!("198.51.100.{}", i + 10);
+        let response = attempt_login(&client, &app_address, &user.username, "wrong", &ip).await;
+        assert_eq!(401, response.status().as_u16());
+    }
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "password123",
+        "198.51.100.99",
+    )
+    .await;
+    assert_eq!(429, response.status().as_u16());
+    let first_lock = retry_after(&response);
+    assert!((1..=61).contains(&first_lock));
+
+    // 锁定结束后再次失败，锁定时间加倍
+    sqlx::query("UPDATE login_failures SET locked_until = datetime('now', '-1 second')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "wrong",
+        "198.51.100.1",
+    )
+    .await;
+    assert_eq!(401, response.status().as_u16());
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "wrong",
+        "198.51.100.1",
+    )
+    .await;
+    assert_eq!(429, response.status().as_u16());
+    assert!(retry_after(&response) > 61);
+
+    // 管理员解锁后可以正常登录
+    let user_id: (i64,) = sqlx::query_as("SELECT id FROM users WHERE username = ?")
+        .bind(&user.username)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let response = client
+        .post(format!("{}/admin/users/{}/unlock", app_address, user_id.0))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "password123",
+        "198.51.100.1",
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+
+    // 普通用户不能解锁
+    let response = client
+        .post(format!("{}/admin/users/{}/unlock", app_address, user_id.0))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unknown_usernames_are_locked_like_existing_ones() {
+    let (app_address, _, _) = spawn_app_with_config(|_| {}).await;
+    let client = reqwest::Client::new();
+
+    for _ in 0..5 {
+        let response =
+            attempt_login(&client, &app_address, "no_such_user", "wrong", "127.0.0.1").await;
+        assert_eq!(401, response.status().as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!("认证错误: 用户名或密码错误", body["error"]);
+    }
+    let response = attempt_login(&client, &app_address, "no_such_user", "wrong", "127.0.0.1").await;
+    assert_eq!(429, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn clients_are_locked_after_failures_across_accounts() {
+    let (app_address, _, _) = spawn_app_with_config(|config| {
+        config.trust_proxy_headers = true;
+        config.login_max_failures_per_ip = 3;
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    for i in 0..3 {
+        let username = format!("victim_{}", i);
+        let response =
+            attempt_login(&client, &app_address, &username, "wrong", "203.0.113.7").await;
+        assert_eq!(401, response.status().as_u16());
+    }
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "password123",
+        "203.0.113.7",
+    )
+    .await;
+    assert_eq!(429, response.status().as_u16());
+    assert!(response.headers().contains_key("retry-after"));
+
+    // 只有最后一个地址可信，伪造的前缀不能绕过锁定
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "password123",
+        "192.0.2.1, 203.0.113.7",
+    )
+    .await;
+    assert_eq!(429, response.status().as_u16());
+
+    let response = attempt_login(
+        &client,
+        &app_address,
+        &user.username,
+        "password123",
+        "203.0.113.8",
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn concurrent_attempts_cannot_exceed_the_limit() {
+    let (app_address, _, _) = spawn_app_with_config(|_| {}).await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    // 同时提交的猜测在验证密码之前就已计数，超过阈值的请求直接被拒绝
+    let mut attempts = tokio::task::JoinSet::new();
+    for _ in 0..10 {
+        let client = client.clone();
+        let app_address = app_address.clone();
+        let username = user.username.clone();
+        attempts.spawn(async move {
+            attempt_login(&client, &app_address, &username, "wrong", "127.0.0.1")
+                .await
+                .status()
+                .as_u16()
+        });
+    }
+    let statuses = attempts.join_all().await;
+    assert_eq!(5, statuses.iter().filter(|&&status| status == 401).count());
+    assert_eq!(5, statuses.iter().filter(|&&status| status == 429).count());
+}
@@ -0,0 +1,399 @@
+mod common;
+use common::{TestUser, create_authenticated_user, create_user_with_role, spawn_app_with_pool};
+
+async fn create_post(client: &reqwest::Client, app_address: &str, token: &str) -> i64 {
+    let post_body = serde_json::json!({ "title": "Moderated", "content": "c", "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn set_moderation(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+    mode: serde_json::Value,
+) -> reqwest::Response {
+    client
+        .put(format!(
+            "{}/posts/{}/comment-moderation",
+            app_address, post_id
+        ))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "mode": mode }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn comment(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+    content: &str,
+) -> serde_json::Value {
+    let response = client
+        .post(format!("{}/posts/{}/comments", app_address, post_id))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+/// 以指定用户的身份列出文章下的评论内容，`None` 表示匿名访问
+async fn visible_comments(
+    client: &reqwest::Client,
+    app_address: &str,
+    viewer: Option<&TestUser>,
+    post_id: i64,
+) -> Vec<String> {
+    let mut request = client.get(format!("{}/posts/{}/comments", app_address, post_id));
+    if let Some(viewer) = viewer {
+        request = request.bearer_auth(&viewer.token);
+    }
+    let page: serde_json::Value = request.send().await.unwrap().json().await.unwrap();
+    page["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comment| comment["content"].as_str().unwrap().to_string())
+        .collect()
+}
+
+async fn moderate(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    comment_ids: &[i64],
+    status: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/comments/moderation", app_address))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "comment_ids": comment_ids, "status": status }))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn first_time_commenters_are_held_until_approved() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let commenter = create_user_with_role(&client, &app_address, &pool, "user").await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+
+    let response = set_moderation(
+        &client,
+        &app_address,
+        &commenter.token,
+        post_id,
+        serde_json::json!("all"),
+    )
+    .await;
+    assert_eq!(403, response.status().as_u16());
+
+    let response = set_moderation(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        serde_json::json!("first_time"),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("first_time", post["comment_moderation"]);
+
+    let held = comment(&client, &app_address, &commenter.token, post_id, "first").await;
+    assert_eq!("pending", held["status"]);
+
+    // 等待审核的评论只有作者本人可见
+    assert!(
+        visible_comments(&client, &app_address, None, post_id)
+            .await
+            .is_empty()
+    );
+    assert!(
+        visible_comments(&client, &app_address, Some(&author), post_id)
+            .await
+            .is_empty()
+    );
+    assert_eq!(
+        vec!["first"],
+        visible_comments(&client, &app_address, Some(&commenter), post_id).await
+    );
+
+    let held_id = held["id"].as_i64().unwrap();
+    let response = moderate(&client, &app_address, &editor.token, &[held_id], "approved").await;
+    assert_eq!(200, response.status().as_u16());
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, result["updated"]);
+    assert_eq!(
+        vec!["first"],
+        visible_comments(&client, &app_address, None, post_id).await
+    );
+
+    // 已有评论通过审核后不再需要等待
+    let second = comment(&client, &app_address, &commenter.token, post_id, "second").await;
+    assert_eq!("approved", second["status"]);
+
+    // 改回沿用站点设置 (测试中为 open)
+    let response = set_moderation(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        serde_json::Value::Null,
+    )
+    .await;
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert!(post["comment_moderation"].is_null());
+    let newcomer = create_authenticated_user(&client, &app_address).await;
+    let open = comment(&client, &app_address, &newcomer.token, post_id, "open").await;
+    assert_eq!("approved", open["status"]);
+}
+
+#[tokio::test]
+async fn moderators_review_the_queue_in_bulk() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    let other_post_id = create_post(&client, &app_address, &author.token).await;
+    for id in [post_id, other_post_id] {
+        set_moderation(
+            &client,
+            &app_address,
+            &author.token,
+            id,
+            serde_json::json!("all"),
+        )
+        .await;
+    }
+
+    let mut held = Vec::new();
+    for content in ["spam link", "rude", "fine"] {
+        let comment = comment(&client, &app_address, &author.token, post_id, content).await;
+        held.push(comment["id"].as_i64().unwrap());
+    }
+    comment(
+        &client,
+        &app_address,
+        &author.token,
+        other_post_id,
+        "elsewhere",
+    )
+    .await;
+
+    // 拥有评论管理权限的用户不需要审核
+    let by_editor = comment(&client, &app_address, &editor.token, post_id, "editor").await;
+    assert_eq!("approved", by_editor["status"]);
+
+    let response = client
+        .get(format!("{}/comments/moderation", app_address))
+        .bearer_auth(&author.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let queue: serde_json::Value = client
+        .get(format!(
+            "{}/comments/moderation?post_id={}",
+            app_address, post_id
+        ))
+        .bearer_auth(&editor.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(3, queue["total"]);
+    let queued: Vec<i64> = queue["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comment| comment["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(held, queued);
+
+    let response = moderate(&client, &app_address, &editor.token, &held, "pending").await;
+    assert_eq!(400, response.status().as_u16());
+
+    moderate(&client, &app_address, &editor.token, &held[..1], "spam").await;
+    moderate(
+        &client,
+        &app_address,
+        &editor.token,
+        &held[1..2],
+        "rejected",
+    )
+    .await;
+    let response = moderate(
+        &client,
+        &app_address,
+        &editor.token,
+        &[held[2], 999_999],
+        "approved",
+    )
+    .await;
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, result["updated"]);
+
+    // 被拒绝和标记为垃圾的评论对作者本人也不可见
+    assert_eq!(
+        vec!["fine", "editor"],
+        visible_comments(&client, &app_address, Some(&author), post_id).await
+    );
+
+    let queue: serde_json::Value = client
+        .get(format!("{}/comments/moderation?status=spam", app_address))
+        .bearer_auth(&editor.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, queue["total"]);
+    assert_eq!("spam link", queue["data"][0]["content"]);
+
+    let results: serde_json::Value = client
+        .get(format!("{}/search?q=rude&scope=comments", app_address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(0, results["total"]);
+}
+
+async fn edit_comment(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+    comment_id: i64,
+    content: &str,
+) -> serde_json::Value {
+    let response = client
+        .put(format!(
+            "{}/posts/{}/comments/{}",
+            app_address, post_id, comment_id
+        ))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn edited_comments_are_moderated_again() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let commenter = create_user_with_role(&client, &app_address, &pool, "user").await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    set_moderation(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        serde_json::json!("all"),
+    )
+    .await;
+
+    let held = comment(&client, &app_address, &commenter.token, post_id, "harmless").await;
+    let comment_id = held["id"].as_i64().unwrap();
+    moderate(
+        &client,
+        &app_address,
+        &editor.token,
+        &[comment_id],
+        "approved",
+    )
+    .await;
+    assert_eq!(
+        vec!["harmless"],
+        visible_comments(&client, &app_address, None, post_id).await
+    );
+
+    // 通过审核后修改的内容需要重新审核，审核前其他人看不到
+    let edited = edit_comment(
+        &client,
+        &app_address,
+        &commenter.token,
+        post_id,
+        comment_id,
+        "changed after approval",
+    )
+    .await;
+    assert_eq!("pending", edited["status"]);
+    assert!(
+        visible_comments(&client, &app_address, None, post_id)
+            .await
+            .is_empty()
+    );
+
+    // 审核员修改评论不改变其状态
+    moderate(
+        &client,
+        &app_address,
+        &editor.token,
+        &[comment_id],
+        "approved",
+    )
+    .await;
+    let edited = edit_comment(
+        &client,
+        &app_address,
+        &editor.token,
+        post_id,
+        comment_id,
+        "tidied up by the editor",
+    )
+    .await;
+    assert_eq!("approved", edited["status"]);
+
+    // 被拒绝的评论不能通过修改重新进入审核队列
+    moderate(
+        &client,
+        &app_address,
+        &editor.token,
+        &[comment_id],
+        "rejected",
+    )
+    .await;
+    let edited = edit_comment(
+        &client,
+        &app_address,
+        &commenter.token,
+        post_id,
+        comment_id,
+        "please let me back in",
+    )
+    .await;
+    assert_eq!("rejected", edited["status"]);
+}
@@ -0,0 +1,399 @@
+mod common;
+use common::{
+    create_authenticated_user, login, spawn_app, spawn_app_with_config, spawn_app_with_outbox,
+    wait_for_messages,
+};
+use inkwell::utils::PasswordAlgorithm;
+use sqlx::SqlitePool;
+
+async fn register(
+    client: &reqwest::Client,
+    app_address: &str,
+    username: &str,
+    email: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/register", app_address))
+        .json(&serde_json::json!({
+            "username": username,
+            "password": "password123",
+            "email": email,
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn login_status(
+    client: &reqwest::Client,
+    app_address: &str,
+    username: &str,
+    password: &str,
+) -> u16 {
+    client
+        .post(format!("{}/login", app_address))
+        .json(&serde_json::json!({ "username": username, "password": password }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+async fn stored_hash(pool: &SqlitePool, username: &str) -> String {
+    let (hash,): (String,) = sqlx::query_as("SELECT password_hash FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    hash
+}
+
+async fn set_hash(pool: &SqlitePool, username: &str, hash: &str) {
+    sqlx::query("UPDATE users SET password_hash = ? WHERE username = ?")
+        .bind(hash)
+        .bind(username)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+async fn me_status(client: &reqwest::Client, app_address: &str, token: &str) -> u16 {
+    client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+async fn forgot(client: &reqwest::Client, app_address: &str, email: &str) -> u16 {
+    client
+        .post(format!("{}/password/forgot", app_address))
+        .json(&serde_json::json!({ "email": email }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+async fn reset(client: &reqwest::Client, app_address: &str, token: &str, password: &str) -> u16 {
+    client
+        .post(format!("{}/password/reset", app_address))
+        .json(&serde_json::json!({ "token": token, "new_password": password }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+/// 从邮件正文的重置链接中取出令牌
+fn reset_token(body: &str) -> String {
+    let start = body.find("token=").expect("邮件中没有重置链接") + "token=".len();
+    body[start..]
+        .chars()
+        .take_while(|ch| ch.is_ascii_alphanumeric())
+        .collect()
+}
+
+#[tokio::test]
+async fn change_password_requires_the_current_password() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let other_session = login(&client, &app_address, &user.username, "password123").await;
+
+    let change = |current: &str, new: &str| {
+        client
+            .put(format!("{}/me/password", app_address))
+            .bearer_auth(&user.token)
+            .json(&serde_json::json!({ "current_password": current, "new_password": new }))
+            .send()
+    };
+
+    let response = change("wrong-password", "new-password").await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+    let response = change("password123", "short").await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+
+    let response = change("password123", "new-password").await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let tokens: serde_json::Value = response.json().await.unwrap();
+
+    // 旧会话全部失效，只有新签发的令牌可用
+    assert_eq!(401, me_status(&client, &app_address, &user.token).await);
+    assert_eq!(
+        401,
+        me_status(&client, &app_address, &other_session.token).await
+    );
+    assert_eq!(
+        200,
+        me_status(&client, &app_address, tokens["token"].as_str().unwrap()).await
+    );
+
+    assert_eq!(
+        401,
+        login_status(&client, &app_address, &user.username, "password123").await
+    );
+    assert_eq!(
+        200,
+        login_status(&client, &app_address, &user.username, "new-password").await
+    );
+}
+
+#[tokio::test]
+async fn reset_tokens_are_single_use_and_invalidate_sessions() {
+    let (app_address, pool, outbox) = spawn_app_with_outbox().await;
+    let client = reqwest::Client::new();
+
+    let response = register(
+        &client,
+        &app_address,
+        "reset_user",
+        "Reset.User@Example.com",
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    // 只向验证过的邮箱发送重置链接
+    let response = client
+        .post(format!("{}/email/verify", app_address))
+        .json(&serde_json::json!({ "token": reset_token(&outbox.messages()[0].text_body) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+    let response = register(
+        &client,
+        &app_address,
+        "reset_clone",
+        "reset.user@example.com",
+    )
+    .await;
+    assert_eq!(409, response.status().as_u16());
+    let response = register(&client, &app_address, "bad_email", "not-an-email").await;
+    assert_eq!(400, response.status().as_u16());
+
+    let session = login(&client, &app_address, "reset_user", "password123").await;
+    let response = client
+        .post(format!("{}/me/tokens", app_address))
+        .bearer_auth(&session.token)
+        .json(&serde_json::json!({ "name": "CI", "scopes": ["posts:write"] }))
+        .send()
+        .await
+        .unwrap();
+    let access_token: serde_json::Value = response.json().await.unwrap();
+    let access_token = access_token["token"].as_str().unwrap();
+    assert_eq!(200, me_status(&client, &app_address, access_token).await);
+    // 注册时填写邮箱会收到一封验证邮件
+    assert_eq!(1, outbox.messages().len());
+
+    // 未注册的邮箱同样返回 202，但不会发送邮件；邮件在后台发送
+    assert_eq!(
+        202,
+        forgot(&client, &app_address, "nobody@example.com").await
+    );
+    assert_eq!(
+        202,
+        forgot(&client, &app_address, "reset.user@example.com").await
+    );
+    wait_for_messages(&outbox, 2).await;
+    assert_eq!(
+        202,
+        forgot(&client, &app_address, "RESET.USER@example.com").await
+    );
+    let messages = wait_for_messages(&outbox, 3).await[1..].to_vec();
+    assert_eq!(2, messages.len());
+    assert_eq!("reset.user@example.com", messages[0].to);
+    assert_eq!("reset.user@example.com", messages[1].to);
+    assert!(
+        messages[1]
+            .text_body
+            .contains(&format!("{}/reset-password?token=", app_address))
+    );
+
+    // 令牌只保存摘要，再次申请后旧链接失效
+    let first_token = reset_token(&messages[0].text_body);
+    let token = reset_token(&messages[1].text_body);
+    let stored: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM password_reset_tokens WHERE token_hash = ?")
+            .bind(&token)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(0, stored.0);
+    assert_eq!(
+        400,
+        reset(&client, &app_address, &first_token, "new-password").await
+    );
+
+    assert_eq!(400, reset(&client, &app_address, &token, "short").await);
+    assert_eq!(
+        204,
+        reset(&client, &app_address, &token, "new-password").await
+    );
+    assert_eq!(
+        400,
+        reset(&client, &app_address, &token, "another-password").await
+    );
+
+    assert_eq!(401, me_status(&client, &app_address, &session.token).await);
+    assert_eq!(401, me_status(&client, &app_address, access_token).await);
+    let response = client
+        .post(format!("{}/token/refresh", app_address))
+        .json(&serde_json::json!({ "refresh_token": session.refresh_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(
+        200,
+        login_status(&client, &app_address, "reset_user", "new-password").await
+    );
+
+    // 过期的令牌无法使用
+    assert_eq!(
+        202,
+        forgot(&client, &app_address, "reset.user@example.com").await
+    );
+    let token = reset_token(&wait_for_messages(&outbox, 4).await[3].text_body);
+    sqlx::query("UPDATE password_reset_tokens SET expires_at = datetime('now', '-1 minute')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        400,
+        reset(&client, &app_address, &token, "third-password").await
+    );
+}
+
+#[tokio::test]
+async fn legacy_bcrypt_hashes_are_upgraded_on_login() {
+    let (app_address, pool, _) = spawn_app_with_config(|_| {}).await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    assert!(
+        stored_hash(&pool, &user.username)
+            .await
+            .starts_with("$argon2id$")
+    );
+
+    let legacy = bcrypt::hash("password123", 4).unwrap();
+    set_hash(&pool, &user.username, &legacy).await;
+
+    // 密码错误时不会重新哈希
+    assert_eq!(
+        401,
+        login_status(&client, &app_address, &user.username, "wrong-password").await
+    );
+    assert_eq!(legacy, stored_hash(&pool, &user.username).await);
+
+    assert_eq!(
+        200,
+        login_status(&client, &app_address, &user.username, "password123").await
+    );
+    let upgraded = stored_hash(&pool, &user.username).await;
+    assert!(upgraded.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+
+    assert_eq!(
+        200,
+        login_status(&client, &app_address, &user.username, "password123").await
+    );
+    assert_eq!(upgraded, stored_hash(&pool, &user.username).await);
+}
+
+#[tokio::test]
+async fn unknown_users_take_as_long_as_the_configured_algorithm() {
+    let (app_address, _, _) = spawn_app_with_config(|config| {
+        config.password_hash_algorithm = PasswordAlgorithm::Bcrypt;
+        config.bcrypt_cost = 8;
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let hash = bcrypt::hash("password123", 8).unwrap();
+
+    // 不存在的用户名与当前配置的固定哈希比对，耗时与真实用户相同，否则可以据此判断用户名是否存在
+    let started = std::time::Instant::now();
+    bcrypt::verify("wrong-password", &hash).unwrap();
+    let bcrypt_time = started.elapsed();
+
+    let started = std::time::Instant::now();
+    assert_eq!(
+        401,
+        login_status(&client, &app_address, "no_such_user", "wrong-password").await
+    );
+    assert!(started.elapsed() >= bcrypt_time / 2);
+    assert_eq!(
+        401,
+        login_status(&client, &app_address, &user.username, "wrong-password").await
+    );
+}
+
+#[tokio::test]
+async fn hashes_with_outdated_parameters_are_rehashed() {
+    let client = reqwest::Client::new();
+    let (old_address, old_pool, _) = spawn_app_with_config(|_| {}).await;
+    let old_user = create_authenticated_user(&client, &old_address).await;
+    let old_hash = stored_hash(&old_pool, &old_user.username).await;
+    assert!(old_hash.contains("m=1024,t=1,p=1"));
+
+    let (app_address, pool, _) = spawn_app_with_config(|config| {
+        config.argon2_iterations = 2;
+    })
+    .await;
+    let user = create_authenticated_user(&client, &app_address).await;
+    assert!(
+        stored_hash(&pool, &user.username)
+            .await
+            .contains("m=1024,t=2,p=1")
+    );
+
+    // 修改参数前保存的哈希仍然可以验证，登录后更新为新参数
+    set_hash(&pool, &user.username, &old_hash).await;
+    assert_eq!(
+        200,
+        login_status(&client, &app_address, &user.username, "password123").await
+    );
+    assert!(
+        stored_hash(&pool, &user.username)
+            .await
+            .contains("m=1024,t=2,p=1")
+    );
+}
+
+#[tokio::test]
+async fn bcrypt_can_still_be_selected_for_new_hashes() {
+    let (app_address, pool, _) = spawn_app_with_config(|config| {
+        config.password_hash_algorithm = PasswordAlgorithm::Bcrypt;
+        config.bcrypt_cost = 5;
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    assert!(
+        stored_hash(&pool, &user.username)
+            .await
+            .starts_with("$2b$05$")
+    );
+
+    // 成本较低的旧哈希同样会升级
+    set_hash(
+        &pool,
+        &user.username,
+        &bcrypt::hash("password123", 4).unwrap(),
+    )
+    .await;
+    assert_eq!(
+        200,
+        login_status(&client, &app_address, &user.username, "password123").await
+    );
+    assert!(
+        stored_hash(&pool, &user.username)
+            .await
+            .starts_with("$2b$05$")
+    );
+}
@@ -1,13 +1,16 @@
 mod common;
-use common::{TestUser, create_authenticated_user, spawn_app};
+use common::{
+    TestUser, create_authenticated_user, create_user_with_role, login, migrate_before, spawn_app,
+    spawn_app_on, spawn_app_with_pool,
+};
 
 // 辅助函数：创建一个帖子并返回其 ID
 async fn create_post(client: &reqwest::Client, app_address: &str, token: &str) -> i64 {
     let post_body = serde_json::json!({
-        "title": "Test Post", "content": "Test content", "tags": "test", "copyright": "test"
+        "title": "Test Post", "content": "Test content", "tags": ["test"], "copyright": "test"
     });
     let response = client
-        .post(&format!("{}/posts", app_address))
+        .post(format!("{}/posts", app_address))
         .bearer_auth(token)
         .json(&post_body)
         .send()
@@ -35,11 +38,11 @@ async fn create_post_returns_a_201_for_valid_authenticated_request() {
     let user = create_authenticated_user(&client, &app_address).await;
 
     let post_body = serde_json::json!({
-        "title": "My First Post", "content": "Content", "tags": "tags", "copyright": "copyright"
+        "title": "My First Post", "content": "Content", "tags": ["tags"], "copyright": "copyright"
     });
 
     let response = client
-        .post(&format!("{}/posts", &app_address))
+        .post(format!("{}/posts", &app_address))
         .bearer_auth(&user.token)
         .json(&post_body)
         .send()
@@ -55,10 +58,10 @@ async fn create_post_returns_a_201_for_valid_authenticated_request() {
 async fn create_post_returns_a_401_for_unauthenticated_request() {
     let app_address = spawn_app().await;
     let client = reqwest::Client::new();
-    let post_body = serde_json::json!({ "title": "Unauthorized", "content": "c", "tags": "t", "copyright": "c" });
+    let post_body = serde_json::json!({ "title": "Unauthorized", "content": "c", "tags": ["t"], "copyright": "c" });
 
     let response = client
-        .post(&format!("{}/posts", &app_address))
+        .post(format!("{}/posts", &app_address))
         .json(&post_body)
         .send()
         .await
@@ -80,7 +83,7 @@ async fn create_post_returns_a_400_for_invalid_data() {
 
     for (invalid_body, error_message) in test_cases {
         let response = client
-            .post(&format!("{}/posts", &app_address))
+            .post(format!("{}/posts", &app_address))
             .bearer_auth(&user.token)
             .json(&invalid_body)
             .send()
@@ -106,7 +109,7 @@ async fn get_posts_returns_a_list_of_posts() {
     create_post(&client, &app_address, &user.token).await;
 
     let response = client
-        .get(&format!("{}/posts", &app_address))
+        .get(format!("{}/posts", &app_address))
         .send()
         .await
         .unwrap();
@@ -122,10 +125,10 @@ async fn update_post_returns_a_200_for_valid_authenticated_request() {
     let user = create_authenticated_user(&client, &app_address).await;
     let post_id = create_post(&client, &app_address, &user.token).await;
 
-    let updated_body = serde_json::json!({ "title": "Updated", "content": "Updated", "tags": "u", "copyright": "u" });
+    let updated_body = serde_json::json!({ "title": "Updated", "content": "Updated", "tags": ["u"], "copyright": "u" });
 
     let response = client
-        .put(&format!("{}/posts/{}", &app_address, post_id))
+        .put(format!("{}/posts/{}", &app_address, post_id))
         .bearer_auth(&user.token)
         .json(&updated_body)
         .send()
@@ -144,9 +147,9 @@ async fn update_post_returns_a_403_for_forbidden_request() {
     let (_author, post_id, attacker) = create_author_and_attacker(&client, &app_address).await;
 
     let updated_body =
-        serde_json::json!({ "title": "Hacked", "content": "h", "tags": "h", "copyright": "h" });
+        serde_json::json!({ "title": "Hacked", "content": "h", "tags": ["h"], "copyright": "h" });
     let response = client
-        .put(&format!("{}/posts/{}", &app_address, post_id))
+        .put(format!("{}/posts/{}", &app_address, post_id))
         .bearer_auth(&attacker.token)
         .json(&updated_body)
         .send()
@@ -164,7 +167,7 @@ async fn delete_post_returns_a_204_for_valid_authenticated_request() {
     let post_id = create_post(&client, &app_address, &user.token).await;
 
     let response = client
-        .delete(&format!("{}/posts/{}", &app_address, post_id))
+        .delete(format!("{}/posts/{}", &app_address, post_id))
         .bearer_auth(&user.token)
         .send()
         .await
@@ -173,7 +176,7 @@ async fn delete_post_returns_a_204_for_valid_authenticated_request() {
     assert_eq!(204, response.status().as_u16());
 
     let get_response = client
-        .get(&format!("{}/posts/{}", &app_address, post_id))
+        .get(format!("{}/posts/{}", &app_address, post_id))
         .send()
         .await
         .unwrap();
@@ -187,7 +190,7 @@ async fn delete_post_returns_a_403_for_forbidden_request() {
     let (_author, post_id, attacker) = create_author_and_attacker(&client, &app_address).await;
 
     let response = client
-        .delete(&format!("{}/posts/{}", &app_address, post_id))
+        .delete(format!("{}/posts/{}", &app_address, post_id))
         .bearer_auth(&attacker.token)
         .send()
         .await
@@ -204,16 +207,16 @@ async fn update_post_returns_a_404_if_post_is_soft_deleted() {
     let post_id = create_post(&client, &app_address, &user.token).await;
 
     client
-        .delete(&format!("{}/posts/{}", &app_address, post_id))
+        .delete(format!("{}/posts/{}", &app_address, post_id))
         .bearer_auth(&user.token)
         .send()
         .await
         .unwrap();
 
-    let updated_body = serde_json::json!({ "title": "Should not update", "content": "s", "tags": "s", "copyright": "s" });
+    let updated_body = serde_json::json!({ "title": "Should not update", "content": "s", "tags": ["s"], "copyright": "s" });
 
     let update_response = client
-        .put(&format!("{}/posts/{}", &app_address, post_id))
+        .put(format!("{}/posts/{}", &app_address, post_id))
         .bearer_auth(&user.token)
         .json(&updated_body)
         .send()
@@ -222,3 +225,402 @@ async fn update_post_returns_a_404_if_post_is_soft_deleted() {
 
     assert_eq!(404, update_response.status().as_u16());
 }
+
+#[tokio::test]
+async fn create_post_returns_a_403_for_user_without_author_role() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let reader = create_user_with_role(&client, &app_address, &pool, "user").await;
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&reader.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn users_created_before_roles_can_still_create_posts() {
+    // 按引入 author 默认角色之前的表结构写入一个旧账户
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    migrate_before(&pool, 20250824093518).await;
+    let hash = bcrypt::hash("password123", 4).unwrap();
+    sqlx::query("INSERT INTO users (username, password_hash) VALUES ('veteran', ?)")
+        .bind(&hash)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (app_address, pool, _) = spawn_app_on(pool, |_| {}).await;
+    let client = reqwest::Client::new();
+    let veteran = login(&client, &app_address, "veteran", "password123").await;
+
+    let post_body =
+        serde_json::json!({ "title": "t", "content": "c", "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&veteran.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+
+    sqlx::query("INSERT INTO users (username, password_hash) VALUES ('newcomer', ?)")
+        .bind(&hash)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let role: String = sqlx::query_scalar("SELECT role FROM users WHERE username = 'newcomer'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(role, "author");
+}
+
+#[tokio::test]
+async fn editor_can_update_any_post() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+
+    let updated_body =
+        serde_json::json!({ "title": "Edited", "content": "e", "tags": ["e"], "copyright": "e" });
+    let response = client
+        .put(format!("{}/posts/{}", &app_address, post_id))
+        .bearer_auth(&editor.token)
+        .json(&updated_body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(200, response.status().as_u16());
+    let updated_post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("Edited", updated_post["title"]);
+    assert_eq!(author.username, updated_post["author"]);
+}
+
+#[tokio::test]
+async fn admin_can_delete_any_post() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+
+    let response = client
+        .delete(format!("{}/posts/{}", &app_address, post_id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(204, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn draft_post_is_only_visible_to_its_author_and_editors() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let other = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+
+    let post_body = serde_json::json!({
+        "title": "Draft", "content": "c", "tags": ["t"], "copyright": "c", "status": "draft"
+    });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&author.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    let created_post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("draft", created_post["status"]);
+    assert!(created_post["published_at"].is_null());
+    let post_id = created_post["id"].as_i64().unwrap();
+
+    let response = client
+        .get(format!("{}/posts", &app_address))
+        .send()
+        .await
+        .unwrap();
+    let posts: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(0, posts["total"]);
+
+    for (token, expected_status) in [
+        (None, 404),
+        (Some(&other.token), 404),
+        (Some(&author.token), 200),
+        (Some(&editor.token), 200),
+    ] {
+        let mut request = client.get(format!("{}/posts/{}", &app_address, post_id));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.unwrap();
+        assert_eq!(expected_status, response.status().as_u16());
+    }
+
+    let response = client
+        .get(format!("{}/posts?status=draft", &app_address))
+        .bearer_auth(&author.token)
+        .send()
+        .await
+        .unwrap();
+    let drafts: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, drafts["total"]);
+
+    let response = client
+        .get(format!("{}/posts?status=draft", &app_address))
+        .bearer_auth(&other.token)
+        .send()
+        .await
+        .unwrap();
+    let drafts: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(0, drafts["total"]);
+}
+
+#[tokio::test]
+async fn create_post_returns_a_400_for_scheduled_post_in_the_past() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let post_body = serde_json::json!({
+        "title": "Late", "content": "c", "tags": ["t"], "copyright": "c",
+        "status": "scheduled", "published_at": "2000-01-01T00:00:00Z"
+    });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn scheduled_post_is_published_when_due() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let publish_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    let post_body = serde_json::json!({
+        "title": "Scheduled", "content": "c", "tags": ["t"], "copyright": "c",
+        "status": "scheduled", "published_at": publish_at
+    });
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    let post_id = response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap();
+
+    assert_eq!(
+        0,
+        inkwell::scheduler::publish_due_posts(&pool).await.unwrap()
+    );
+
+    // 模拟时间流逝：把计划发布时间改到过去
+    sqlx::query("UPDATE posts SET published_at = ? WHERE id = ?")
+        .bind(chrono::Utc::now() - chrono::Duration::minutes(1))
+        .bind(post_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        1,
+        inkwell::scheduler::publish_due_posts(&pool).await.unwrap()
+    );
+
+    let response = client
+        .get(format!("{}/posts/{}", &app_address, post_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("published", post["status"]);
+}
+
+#[tokio::test]
+async fn post_slug_is_transliterated_and_unique() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_body = serde_json::json!({
+        "title": "关系型数据库设计", "content": "c", "tags": ["t"], "copyright": "c"
+    });
+
+    let mut slugs = Vec::new();
+    for _ in 0..2 {
+        let response = client
+            .post(format!("{}/posts", &app_address))
+            .bearer_auth(&user.token)
+            .json(&post_body)
+            .send()
+            .await
+            .unwrap();
+        let post: serde_json::Value = response.json().await.unwrap();
+        slugs.push(post["slug"].as_str().unwrap().to_string());
+    }
+    assert_eq!(
+        vec![
+            "guan-xi-xing-shu-ju-ku-she-ji",
+            "guan-xi-xing-shu-ju-ku-she-ji-2"
+        ],
+        slugs
+    );
+
+    let response = client
+        .get(format!("{}/posts/by-slug/{}", &app_address, slugs[1]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(slugs[1], post["slug"]);
+}
+
+#[tokio::test]
+async fn old_slug_redirects_after_title_change() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let no_redirect_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+
+    let updated_body = serde_json::json!({
+        "title": "Renamed Post", "content": "c", "tags": ["t"], "copyright": "c"
+    });
+    let response = client
+        .put(format!("{}/posts/{}", &app_address, post_id))
+        .bearer_auth(&user.token)
+        .json(&updated_body)
+        .send()
+        .await
+        .unwrap();
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("renamed-post", post["slug"]);
+
+    let response = no_redirect_client
+        .get(format!("{}/posts/by-slug/test-post", &app_address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(308, response.status().as_u16());
+    assert_eq!(
+        "/posts/by-slug/renamed-post",
+        response.headers()["location"].to_str().unwrap()
+    );
+
+    let response = client
+        .get(format!("{}/posts/by-slug/test-post", &app_address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(post_id, post["id"].as_i64().unwrap());
+
+    // 新文章不能占用其他文章的旧 slug
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "title": "Test Post", "content": "c", "tags": [], "copyright": "c" }))
+        .send()
+        .await
+        .unwrap();
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("test-post-2", post["slug"]);
+}
+
+#[tokio::test]
+async fn post_content_is_rendered_as_sanitized_html() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let content = "# 标题\n\n| a | b |\n|:-|-:|\n| 1 | 2 |\n\n脚注[^1]\n\n[^1]: 说明\n\n```rust\nfn main() {}\n```\n\n- [x] 完成\n\n<script>alert(1)</script>\n<a href=\"javascript:alert(1)\" onclick=\"steal()\">链接</a>\n<img src=\"x.png\" onerror=\"steal()\">\n<div id=\"login-form\">覆盖</div>";
+    let post_body = serde_json::json!({ "title": "Markdown", "content": content, "tags": [], "copyright": "c" });
+
+    let response = client
+        .post(format!("{}/posts", &app_address))
+        .bearer_auth(&user.token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    let post: serde_json::Value = response.json().await.unwrap();
+    let html = post["content_html"].as_str().unwrap();
+
+    assert!(html.contains("<h1>标题</h1>"), "html: {}", html);
+    assert!(
+        html.contains("<th style=\"text-align: left\">a</th>"),
+        "html: {}",
+        html
+    );
+    assert!(
+        html.contains("class=\"footnote-definition\""),
+        "html: {}",
+        html
+    );
+    // 脚注的 id 加上前缀，引用链接随之修改
+    assert!(html.contains("id=\"user-content-1\""), "html: {}", html);
+    assert!(html.contains("href=\"#user-content-1\""), "html: {}", html);
+    assert!(
+        html.contains("<code class=\"language-rust\">"),
+        "html: {}",
+        html
+    );
+    assert!(html.contains("type=\"checkbox\""), "html: {}", html);
+    for forbidden in [
+        "<script",
+        "javascript:",
+        "onclick",
+        "onerror",
+        "id=\"login-form\"",
+    ] {
+        assert!(!html.contains(forbidden), "html: {}", html);
+    }
+    assert_eq!(content, post["content"]);
+
+    let post_id = post["id"].as_i64().unwrap();
+    let updated_body = serde_json::json!({ "title": "Markdown", "content": "**粗体**", "tags": [], "copyright": "c" });
+    client
+        .put(format!("{}/posts/{}", &app_address, post_id))
+        .bearer_auth(&user.token)
+        .json(&updated_body)
+        .send()
+        .await
+        .unwrap();
+
+    let response = client
+        .get(format!("{}/posts/{}", &app_address, post_id))
+        .send()
+        .await
+        .unwrap();
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("<p><strong>粗体</strong></p>\n", post["content_html"]);
+}
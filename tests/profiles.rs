@@ -0,0 +1,246 @@
+mod common;
+use common::{create_authenticated_user, create_user_with_role, spawn_app_with_pool};
+
+async fn create_post(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    title: &str,
+    status: &str,
+) {
+    let post_body =
+        serde_json::json!({ "title": title, "content": "c", "copyright": "c", "status": status });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+}
+
+async fn update_me(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    client
+        .patch(format!("{}/me", app_address))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn me_can_be_read_and_partially_updated() {
+    let (app_address, _) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let response = client
+        .get(format!("{}/me", app_address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let me: serde_json::Value = client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(user.username, me["username"]);
+    assert_eq!("author", me["role"]);
+    assert!(me["display_name"].is_null());
+    assert!(me.get("password_hash").is_none());
+
+    let response = update_me(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!({
+            "display_name": "Ada",
+            "bio": "Writes about Rust",
+            "website": "https://ada.example",
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let me: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("Ada", me["display_name"]);
+    assert_eq!("https://ada.example", me["website"]);
+
+    // 省略的字段保持不变，null 清空字段
+    let response = update_me(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!({ "bio": null, "avatar_url": "http://cdn.example/ada.png" }),
+    )
+    .await;
+    let me: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("Ada", me["display_name"]);
+    assert!(me["bio"].is_null());
+    assert_eq!("http://cdn.example/ada.png", me["avatar_url"]);
+
+    for body in [
+        serde_json::json!({ "website": "javascript:alert(1)" }),
+        serde_json::json!({ "avatar_url": "https://" }),
+        serde_json::json!({ "display_name": "" }),
+        serde_json::json!({ "bio": "x".repeat(501) }),
+    ] {
+        let response = update_me(&client, &app_address, &user.token, body.clone()).await;
+        assert_eq!(400, response.status().as_u16(), "{}", body);
+    }
+
+    let profile: serde_json::Value = client
+        .get(format!("{}/users/{}", app_address, user.username))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("Ada", profile["display_name"]);
+    assert_eq!("http://cdn.example/ada.png", profile["avatar_url"]);
+    assert!(profile.get("role").is_none());
+}
+
+#[tokio::test]
+async fn user_posts_only_show_drafts_to_the_author_and_editors() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let other = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    create_post(&client, &app_address, &author.token, "Mine", "published").await;
+    create_post(&client, &app_address, &author.token, "Draft", "draft").await;
+    create_post(&client, &app_address, &other.token, "Theirs", "published").await;
+
+    let posts_url = format!("{}/users/{}/posts", app_address, author.username);
+    let page: serde_json::Value = client
+        .get(&posts_url)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, page["total"]);
+    assert_eq!("Mine", page["data"][0]["title"]);
+
+    let drafts_url = format!("{}?status=draft", posts_url);
+    let response = client.get(&drafts_url).send().await.unwrap();
+    assert_eq!(401, response.status().as_u16());
+    let response = client
+        .get(&drafts_url)
+        .bearer_auth(&other.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+    for token in [&author.token, &editor.token] {
+        let page: serde_json::Value = client
+            .get(&drafts_url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(1, page["total"]);
+        assert_eq!("Draft", page["data"][0]["title"]);
+    }
+
+    let response = client
+        .get(format!("{}/users/nobody_here/posts", app_address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn invalid_profile_updates_change_nothing_and_null_clears_fields() {
+    let (app_address, _) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let response = client
+        .patch(format!("{}/me", app_address))
+        .json(&serde_json::json!({ "display_name": "Ada" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let response = update_me(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!({
+            "display_name": "Ada",
+            "bio": "Writes about Rust",
+            "website": "https://ada.example",
+            "avatar_url": "https://cdn.example/ada.png",
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+
+    // 任一字段无效时整个请求被拒绝，其他字段也不会被写入
+    for (body, expected) in [
+        (
+            serde_json::json!({ "display_name": "Grace", "website": "not a url" }),
+            400,
+        ),
+        (
+            serde_json::json!({ "display_name": "Grace", "avatar_url": "ftp://cdn.example/a.png" }),
+            400,
+        ),
+        // 类型错误由 JSON 提取器拒绝
+        (
+            serde_json::json!({ "display_name": "Grace", "website": 42 }),
+            422,
+        ),
+    ] {
+        let response = update_me(&client, &app_address, &user.token, body.clone()).await;
+        assert_eq!(expected, response.status().as_u16(), "{}", body);
+    }
+
+    let response = update_me(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!({ "website": null, "avatar_url": null }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let me: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("Ada", me["display_name"]);
+    assert_eq!("Writes about Rust", me["bio"]);
+    assert!(me["website"].is_null());
+    assert!(me["avatar_url"].is_null());
+
+    let me: serde_json::Value = client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("Ada", me["display_name"]);
+    assert!(me["website"].is_null());
+    assert!(me["avatar_url"].is_null());
+}
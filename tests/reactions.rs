@@ -0,0 +1,285 @@
+mod common;
+use common::{create_authenticated_user, create_user_with_role, spawn_app, spawn_app_with_pool};
+
+async fn create_post(client: &reqwest::Client, app_address: &str, token: &str, title: &str) -> i64 {
+    let post_body = serde_json::json!({ "title": title, "content": "c", "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn comment(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+    content: &str,
+) -> i64 {
+    let response = client
+        .post(format!("{}/posts/{}/comments", app_address, post_id))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+/// 对 `path` 下的内容添加 (`add`) 或撤销表情回应
+async fn react(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    path: &str,
+    kind: &str,
+    add: bool,
+) -> reqwest::Response {
+    let url = format!("{}{}/reactions/{}", app_address, path, kind);
+    let request = if add {
+        client.put(url)
+    } else {
+        client.delete(url)
+    };
+    request.bearer_auth(token).send().await.unwrap()
+}
+
+#[tokio::test]
+async fn reactions_are_counted_once_per_user_and_kind() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &author.token, "Liked").await;
+    let path = format!("/posts/{}", post_id);
+
+    let response = client
+        .put(format!("{}{}/reactions/like", app_address, path))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let response = react(&client, &app_address, &reader.token, &path, "meh", true).await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = react(
+        &client,
+        &app_address,
+        &reader.token,
+        "/posts/999999",
+        "like",
+        true,
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+
+    for _ in 0..2 {
+        let response = react(&client, &app_address, &reader.token, &path, "like", true).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+    react(&client, &app_address, &reader.token, &path, "love", true).await;
+    let response = react(&client, &app_address, &author.token, &path, "like", true).await;
+    let summary: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        serde_json::json!({ "like": 2, "love": 1 }),
+        summary["reactions"]
+    );
+    assert_eq!(serde_json::json!(["like"]), summary["mine"]);
+
+    let post: serde_json::Value = client
+        .get(format!("{}{}", app_address, path))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::json!({ "like": 2, "love": 1 }),
+        post["reactions"]
+    );
+
+    // 撤销是幂等的，没有回应过的表情也可以撤销
+    for _ in 0..2 {
+        let response = react(&client, &app_address, &reader.token, &path, "love", false).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+    let response = react(&client, &app_address, &reader.token, &path, "wow", false).await;
+    let summary: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(serde_json::json!({ "like": 2 }), summary["reactions"]);
+    assert_eq!(serde_json::json!(["like"]), summary["mine"]);
+
+    let comment_id = comment(&client, &app_address, &author.token, post_id, "First").await;
+    let comment_path = format!("{}/comments/{}", path, comment_id);
+    react(
+        &client,
+        &app_address,
+        &reader.token,
+        &comment_path,
+        "laugh",
+        true,
+    )
+    .await;
+    let page: serde_json::Value = client
+        .get(format!("{}{}/comments", app_address, path))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::json!({ "laugh": 1 }),
+        page["data"][0]["reactions"]
+    );
+
+    let response = client
+        .put(format!("{}{}", app_address, comment_path))
+        .bearer_auth(&author.token)
+        .json(&serde_json::json!({ "content": "First, edited" }))
+        .send()
+        .await
+        .unwrap();
+    let edited: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(serde_json::json!({ "laugh": 1 }), edited["reactions"]);
+
+    let response = react(
+        &client,
+        &app_address,
+        &reader.token,
+        &format!("/posts/999999/comments/{}", comment_id),
+        "laugh",
+        true,
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn top_sort_orders_by_reaction_count() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let mut readers = Vec::new();
+    for _ in 0..3 {
+        readers.push(create_authenticated_user(&client, &app_address).await);
+    }
+
+    let quiet = create_post(&client, &app_address, &author.token, "Quiet").await;
+    let popular = create_post(&client, &app_address, &author.token, "Popular").await;
+    let liked = create_post(&client, &app_address, &author.token, "Liked").await;
+    for reader in &readers {
+        let path = format!("/posts/{}", popular);
+        react(&client, &app_address, &reader.token, &path, "like", true).await;
+    }
+    let path = format!("/posts/{}", liked);
+    react(&client, &app_address, &readers[0].token, &path, "wow", true).await;
+    react(&client, &app_address, &readers[0].token, &path, "sad", true).await;
+
+    let posts: serde_json::Value = client
+        .get(format!("{}/posts?sort=top", app_address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let ids: Vec<i64> = posts["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|post| post["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(vec![popular, liked, quiet], ids);
+
+    // 顶层评论按回应数量排列，数量相同时回复多的在前
+    let first = comment(&client, &app_address, &author.token, quiet, "first").await;
+    let second = comment(&client, &app_address, &author.token, quiet, "second").await;
+    let third = comment(&client, &app_address, &author.token, quiet, "third").await;
+    client
+        .post(format!("{}/posts/{}/comments", app_address, quiet))
+        .bearer_auth(&author.token)
+        .json(&serde_json::json!({ "content": "reply", "parent_id": first }))
+        .send()
+        .await
+        .unwrap();
+    let path = format!("/posts/{}/comments/{}", quiet, third);
+    react(
+        &client,
+        &app_address,
+        &readers[0].token,
+        &path,
+        "love",
+        true,
+    )
+    .await;
+
+    let page: serde_json::Value = client
+        .get(format!(
+            "{}/posts/{}/comments?sort=top&page_size=10",
+            app_address, quiet
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let roots: Vec<i64> = page["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|comment| comment["parent_id"].is_null())
+        .map(|comment| comment["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(vec![third, first, second], roots);
+}
+
+#[tokio::test]
+async fn reactions_are_removed_with_the_deleted_content() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let reader_post_id = create_post(&client, &app_address, &reader.token, "Kept").await;
+    let author_post_id = create_post(&client, &app_address, &author.token, "Deleted").await;
+    let comment_id = comment(&client, &app_address, &author.token, reader_post_id, "Hi").await;
+
+    for path in [
+        format!("/posts/{}", author_post_id),
+        format!("/posts/{}/comments/{}", reader_post_id, comment_id),
+    ] {
+        let response = react(&client, &app_address, &reader.token, &path, "like", true).await;
+        assert!(response.status().is_success());
+    }
+
+    let (author_id,): (i64,) = sqlx::query_as("SELECT id FROM users WHERE username = ?")
+        .bind(&author.username)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let response = client
+        .delete(format!("{}/admin/users/{}", app_address, author_id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+
+    // 内容被物理删除后，回应不能留下来计入之后重新使用同一 ID 的内容
+    let (remaining,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM reactions")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(0, remaining);
+}
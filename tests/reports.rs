@@ -0,0 +1,351 @@
+mod common;
+use common::{create_authenticated_user, create_user_with_role, spawn_app_with_pool};
+
+async fn create_post(client: &reqwest::Client, app_address: &str, token: &str) -> i64 {
+    let post_body = serde_json::json!({ "title": "Reported", "content": "c", "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn report(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    path: &str,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    client
+        .post(format!("{}{}/report", app_address, path))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_json(
+    client: &reqwest::Client,
+    url: String,
+    token: Option<&str>,
+) -> (u16, serde_json::Value) {
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap_or_default())
+}
+
+async fn decide(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    report_id: i64,
+    action: &str,
+    note: &str,
+) -> reqwest::Response {
+    client
+        .post(format!(
+            "{}/admin/reports/{}/{}",
+            app_address, report_id, action
+        ))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "note": note }))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn reports_are_deduplicated_and_hide_posts_at_the_threshold() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    let path = format!("/posts/{}", post_id);
+    let spam = serde_json::json!({ "reason": "spam" });
+
+    let response = report(&client, &app_address, &author.token, &path, spam.clone()).await;
+    assert_eq!(400, response.status().as_u16());
+
+    let mut readers = Vec::new();
+    for _ in 0..3 {
+        readers.push(create_authenticated_user(&client, &app_address).await);
+    }
+
+    let response = report(
+        &client,
+        &app_address,
+        &readers[0].token,
+        &path,
+        serde_json::json!({ "reason": "other" }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = report(
+        &client,
+        &app_address,
+        &readers[0].token,
+        &path,
+        spam.clone(),
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    let first: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("open", first["status"]);
+    assert_eq!(false, first["target_hidden"]);
+
+    let response = report(
+        &client,
+        &app_address,
+        &readers[0].token,
+        &path,
+        spam.clone(),
+    )
+    .await;
+    assert_eq!(409, response.status().as_u16());
+
+    report(
+        &client,
+        &app_address,
+        &readers[1].token,
+        &path,
+        spam.clone(),
+    )
+    .await;
+    let (status, _) = get_json(&client, format!("{}{}", app_address, path), None).await;
+    assert_eq!(200, status);
+
+    // 第三个举报达到阈值，文章被隐藏，只有作者和编辑还能看到
+    report(
+        &client,
+        &app_address,
+        &readers[2].token,
+        &path,
+        spam.clone(),
+    )
+    .await;
+    let (status, _) = get_json(&client, format!("{}{}", app_address, path), None).await;
+    assert_eq!(404, status);
+    let (status, post) = get_json(
+        &client,
+        format!("{}{}", app_address, path),
+        Some(&author.token),
+    )
+    .await;
+    assert_eq!(200, status);
+    assert_eq!(true, post["hidden"]);
+    let (_, posts) = get_json(&client, format!("{}/posts", app_address), None).await;
+    assert_eq!(0, posts["total"]);
+
+    let (status, _) = get_json(
+        &client,
+        format!("{}/admin/reports", app_address),
+        Some(&author.token),
+    )
+    .await;
+    assert_eq!(403, status);
+
+    let (_, inbox) = get_json(
+        &client,
+        format!("{}/admin/reports?target_type=post", app_address),
+        Some(&editor.token),
+    )
+    .await;
+    assert_eq!(3, inbox["total"]);
+    assert_eq!(3, inbox["data"][0]["open_reports"]);
+    assert_eq!(true, inbox["data"][0]["target_hidden"]);
+    assert_eq!(post_id, inbox["data"][0]["post_id"]);
+
+    // 驳回一个举报即驳回该文章的全部待处理举报，文章恢复显示
+    let report_id = first["id"].as_i64().unwrap();
+    let response = decide(
+        &client,
+        &app_address,
+        &editor.token,
+        report_id,
+        "dismiss",
+        "内容没有问题",
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let decided: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("dismissed", decided["status"]);
+    assert_eq!(editor.username, decided["resolved_by"]);
+    assert_eq!("内容没有问题", decided["resolution_note"]);
+    assert_eq!(false, decided["target_hidden"]);
+
+    let (status, _) = get_json(&client, format!("{}{}", app_address, path), None).await;
+    assert_eq!(200, status);
+
+    let response = decide(
+        &client,
+        &app_address,
+        &editor.token,
+        report_id,
+        "resolve",
+        "",
+    )
+    .await;
+    assert_eq!(409, response.status().as_u16());
+
+    let (_, inbox) = get_json(
+        &client,
+        format!("{}/admin/reports", app_address),
+        Some(&editor.token),
+    )
+    .await;
+    assert_eq!(0, inbox["total"]);
+    let (_, inbox) = get_json(
+        &client,
+        format!("{}/admin/reports?status=dismissed", app_address),
+        Some(&editor.token),
+    )
+    .await;
+    assert_eq!(3, inbox["total"]);
+}
+
+#[tokio::test]
+async fn resolving_a_comment_report_hides_the_comment() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+
+    let response = client
+        .post(format!("{}/posts/{}/comments", app_address, post_id))
+        .bearer_auth(&author.token)
+        .json(&serde_json::json!({ "content": "You are all idiots" }))
+        .send()
+        .await
+        .unwrap();
+    let comment_id = response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    let path = format!("/posts/{}/comments/{}", post_id, comment_id);
+
+    let response = report(
+        &client,
+        &app_address,
+        &reader.token,
+        &format!("/posts/{}/comments/999999", post_id),
+        serde_json::json!({ "reason": "harassment" }),
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+
+    let response = report(
+        &client,
+        &app_address,
+        &reader.token,
+        &path,
+        serde_json::json!({ "reason": "harassment", "details": "人身攻击" }),
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    let created: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("comment", created["target_type"]);
+    assert_eq!("人身攻击", created["details"]);
+
+    let comments_url = format!("{}/posts/{}/comments", app_address, post_id);
+    let (_, page) = get_json(&client, comments_url.clone(), None).await;
+    assert_eq!(1, page["total"]);
+
+    let response = decide(
+        &client,
+        &app_address,
+        &editor.token,
+        created["id"].as_i64().unwrap(),
+        "resolve",
+        "违反社区规范",
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let decided: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("resolved", decided["status"]);
+    assert_eq!(true, decided["target_hidden"]);
+
+    let (_, page) = get_json(&client, comments_url, Some(&author.token)).await;
+    assert_eq!(0, page["total"]);
+
+    let other_reader = create_authenticated_user(&client, &app_address).await;
+    let response = report(
+        &client,
+        &app_address,
+        &other_reader.token,
+        &path,
+        serde_json::json!({ "reason": "harassment" }),
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn reports_are_removed_with_the_deleted_content() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let reader = create_authenticated_user(&client, &app_address).await;
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+    let reader_post_id = create_post(&client, &app_address, &reader.token).await;
+    let author_post_id = create_post(&client, &app_address, &author.token).await;
+
+    let response = client
+        .post(format!("{}/posts/{}/comments", app_address, reader_post_id))
+        .bearer_auth(&author.token)
+        .json(&serde_json::json!({ "content": "Buy cheap watches" }))
+        .send()
+        .await
+        .unwrap();
+    let comment_id = response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap();
+
+    for path in [
+        format!("/posts/{}", author_post_id),
+        format!("/posts/{}/comments/{}", reader_post_id, comment_id),
+    ] {
+        let response = report(
+            &client,
+            &app_address,
+            &reader.token,
+            &path,
+            serde_json::json!({ "reason": "spam" }),
+        )
+        .await;
+        assert_eq!(201, response.status().as_u16());
+    }
+
+    let (author_id,): (i64,) = sqlx::query_as("SELECT id FROM users WHERE username = ?")
+        .bind(&author.username)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let response = client
+        .delete(format!("{}/admin/users/{}", app_address, author_id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+
+    // 内容被物理删除后，举报不能留下来指向之后重新使用同一 ID 的内容
+    let (remaining,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM reports")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(0, remaining);
+}
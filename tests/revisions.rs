@@ -0,0 +1,201 @@
+mod common;
+use common::{create_authenticated_user, spawn_app};
+
+async fn create_post(client: &reqwest::Client, app_address: &str, token: &str) -> i64 {
+    let post_body = serde_json::json!({
+        "title": "Original", "content": "line one\nline two", "tags": ["t"], "copyright": "c"
+    });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn update_post(client: &reqwest::Client, app_address: &str, token: &str, post_id: i64) {
+    let post_body = serde_json::json!({
+        "title": "Edited", "content": "line one\nline 2\nline three", "tags": ["t"], "copyright": "c"
+    });
+    let response = client
+        .put(format!("{}/posts/{}", app_address, post_id))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn every_edit_is_stored_as_a_revision() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+    update_post(&client, &app_address, &user.token, post_id).await;
+
+    let response = client
+        .get(format!("{}/posts/{}/revisions", &app_address, post_id))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let revisions: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(2, revisions.len());
+    assert_eq!(2, revisions[0]["revision_number"]);
+    assert_eq!("Edited", revisions[0]["title"]);
+    assert_eq!(user.username, revisions[0]["editor"]);
+
+    let response = client
+        .get(format!("{}/posts/{}/revisions/1", &app_address, post_id))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let revision: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("line one\nline two", revision["content"]);
+}
+
+#[tokio::test]
+async fn diff_returns_line_level_changes_between_revisions() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+    update_post(&client, &app_address, &user.token, post_id).await;
+
+    let response = client
+        .get(format!(
+            "{}/posts/{}/revisions/diff?from=1&to=2",
+            &app_address, post_id
+        ))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let diff: serde_json::Value = response.json().await.unwrap();
+
+    let changes = diff["changes"].as_array().unwrap();
+    let fields: Vec<&str> = changes
+        .iter()
+        .map(|change| change["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["title", "content"], fields);
+
+    let content_lines: Vec<(String, String)> = changes[1]["lines"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|line| {
+            (
+                line["op"].as_str().unwrap().to_string(),
+                line["text"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert!(content_lines.contains(&("equal".to_string(), "line one".to_string())));
+    assert!(content_lines.contains(&("delete".to_string(), "line two".to_string())));
+    assert!(content_lines.contains(&("insert".to_string(), "line 2".to_string())));
+    assert!(content_lines.contains(&("insert".to_string(), "line three".to_string())));
+}
+
+#[tokio::test]
+async fn restore_creates_a_new_revision_from_an_old_one() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token).await;
+    update_post(&client, &app_address, &user.token, post_id).await;
+
+    let response = client
+        .post(format!(
+            "{}/posts/{}/revisions/1/restore",
+            &app_address, post_id
+        ))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    let revision: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(3, revision["revision_number"]);
+    assert_eq!(1, revision["restored_from"]);
+
+    let response = client
+        .get(format!("{}/posts/{}", &app_address, post_id))
+        .send()
+        .await
+        .unwrap();
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("Original", post["title"]);
+}
+
+#[tokio::test]
+async fn restore_keeps_tags_containing_commas() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({
+            "title": "Original", "content": "c", "tags": ["Hello, World", "rust"], "copyright": "c"
+        }))
+        .send()
+        .await
+        .unwrap();
+    let post_id = response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    update_post(&client, &app_address, &user.token, post_id).await;
+
+    let response = client
+        .post(format!(
+            "{}/posts/{}/revisions/1/restore",
+            &app_address, post_id
+        ))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    let revision: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        serde_json::json!(["Hello, World", "rust"]),
+        revision["tags"]
+    );
+
+    let response = client
+        .get(format!("{}/posts/{}", &app_address, post_id))
+        .send()
+        .await
+        .unwrap();
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(serde_json::json!(["Hello, World", "rust"]), post["tags"]);
+}
+
+#[tokio::test]
+async fn revisions_return_a_403_for_other_users() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let other = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+
+    let response = client
+        .get(format!("{}/posts/{}/revisions", &app_address, post_id))
+        .bearer_auth(&other.token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(403, response.status().as_u16());
+}
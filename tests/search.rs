@@ -0,0 +1,148 @@
+mod common;
+use common::{create_authenticated_user, spawn_app};
+
+async fn create_post(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    title: &str,
+    content: &str,
+) -> i64 {
+    let post_body =
+        serde_json::json!({ "title": title, "content": content, "tags": ["t"], "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn search(client: &reqwest::Client, app_address: &str, query: &str) -> serde_json::Value {
+    let response = client
+        .get(format!("{}/search", app_address))
+        .query(&[("q", query)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn search_returns_matching_posts_ranked_with_highlighted_snippets() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    create_post(
+        &client,
+        &app_address,
+        &user.token,
+        "Cooking",
+        "A recipe that mentions rust once",
+    )
+    .await;
+    let best_id = create_post(
+        &client,
+        &app_address,
+        &user.token,
+        "Rust ownership",
+        "Rust <b>borrowing</b> and rust lifetimes",
+    )
+    .await;
+    create_post(&client, &app_address, &user.token, "Go", "Goroutines").await;
+
+    let results = search(&client, &app_address, "rust").await;
+
+    assert_eq!(2, results["total"]);
+    assert_eq!(best_id, results["data"][0]["post_id"].as_i64().unwrap());
+    assert_eq!("post", results["data"][0]["kind"]);
+    let snippet = results["data"][0]["snippet"].as_str().unwrap();
+    assert!(snippet.contains("<mark>"), "snippet: {}", snippet);
+    assert!(!snippet.contains("<b>"), "snippet: {}", snippet);
+}
+
+#[tokio::test]
+async fn search_finds_comments_and_skips_deleted_content() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token, "Hello", "World").await;
+    let deleted_id = create_post(&client, &app_address, &user.token, "Kangaroo", "Marsupial").await;
+
+    client
+        .post(format!("{}/posts/{}/comments", &app_address, post_id))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "content": "I saw a kangaroo yesterday" }))
+        .send()
+        .await
+        .unwrap();
+    client
+        .delete(format!("{}/posts/{}", &app_address, deleted_id))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+
+    let results = search(&client, &app_address, "kangaroo").await;
+
+    assert_eq!(1, results["total"]);
+    assert_eq!("comment", results["data"][0]["kind"]);
+    assert_eq!(post_id, results["data"][0]["post_id"].as_i64().unwrap());
+}
+
+#[tokio::test]
+async fn search_reflects_post_updates_and_tolerates_query_syntax() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(&client, &app_address, &user.token, "Before", "alpha").await;
+
+    client
+        .put(format!("{}/posts/{}", &app_address, post_id))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "title": "After", "content": "beta", "tags": ["t"], "copyright": "c" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(0, search(&client, &app_address, "alpha").await["total"]);
+    assert_eq!(1, search(&client, &app_address, "beta").await["total"]);
+    assert_eq!(
+        0,
+        search(&client, &app_address, "\"beta AND (").await["total"]
+    );
+}
+
+#[tokio::test]
+async fn search_segments_chinese_text() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let post_id = create_post(
+        &client,
+        &app_address,
+        &user.token,
+        "关系型数据库设计",
+        "介绍范式与索引的取舍",
+    )
+    .await;
+    create_post(&client, &app_address, &user.token, "数据结构", "链表和树").await;
+
+    let results = search(&client, &app_address, "数据库").await;
+    assert_eq!(1, results["total"]);
+    assert_eq!(post_id, results["data"][0]["post_id"].as_i64().unwrap());
+    assert_eq!(
+        "关系型<mark>数据库</mark>设计",
+        results["data"][0]["snippet"]
+    );
+
+    // 单字查询和多个关键字同样适用
+    assert_eq!(2, search(&client, &app_address, "数").await["total"]);
+    assert_eq!(1, search(&client, &app_address, "索引 范式").await["total"]);
+    assert_eq!(0, search(&client, &app_address, "库设数").await["total"]);
+}
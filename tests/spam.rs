@@ -0,0 +1,376 @@
+mod common;
+use common::{create_authenticated_user, create_user_with_role, spawn_app_with_pool};
+use sqlx::SqlitePool;
+
+async fn create_post(client: &reqwest::Client, app_address: &str, token: &str) -> i64 {
+    let post_body = serde_json::json!({ "title": "Spam target", "content": "c", "copyright": "c" });
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap();
+    response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+/// 发表评论，返回评论 ID 和状态
+async fn comment(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+    content: &str,
+) -> (i64, String) {
+    let response = client
+        .post(format!("{}/posts/{}/comments", app_address, post_id))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    let comment: serde_json::Value = response.json().await.unwrap();
+    (
+        comment["id"].as_i64().unwrap(),
+        comment["status"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn add_to_blocklist(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    kind: &str,
+    value: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/comments/blocklist", app_address))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "kind": kind, "value": value }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn moderate(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    comment_ids: &[i64],
+    status: &str,
+) {
+    let response = client
+        .post(format!("{}/comments/moderation", app_address))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "comment_ids": comment_ids, "status": status }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+}
+
+/// 修改评论，返回修改后的状态
+async fn edit(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    post_id: i64,
+    comment_id: i64,
+    content: &str,
+) -> String {
+    let response = client
+        .put(format!(
+            "{}/posts/{}/comments/{}",
+            app_address, post_id, comment_id
+        ))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let comment: serde_json::Value = response.json().await.unwrap();
+    comment["status"].as_str().unwrap().to_string()
+}
+
+async fn training_documents(pool: &SqlitePool) -> (i64, i64) {
+    sqlx::query_as(
+        "SELECT (SELECT count FROM spam_documents WHERE label = 'spam'), \
+                (SELECT count FROM spam_documents WHERE label = 'ham')",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn links_blocklist_and_duplicates_are_scored() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+
+    let response = add_to_blocklist(&client, &app_address, &author.token, "word", "casino").await;
+    assert_eq!(403, response.status().as_u16());
+
+    let response = add_to_blocklist(
+        &client,
+        &app_address,
+        &editor.token,
+        "domain",
+        "https://www.Spam.Example/deals",
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    let entry: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("spam.example", entry["value"]);
+
+    let response = add_to_blocklist(
+        &client,
+        &app_address,
+        &editor.token,
+        "domain",
+        "spam.example",
+    )
+    .await;
+    assert_eq!(409, response.status().as_u16());
+
+    let response = add_to_blocklist(
+        &client,
+        &app_address,
+        &editor.token,
+        "domain",
+        "not a domain",
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = add_to_blocklist(&client, &app_address, &editor.token, "word", "Casino").await;
+    let word_id = response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_i64()
+        .unwrap();
+
+    // 新注册的用户本身带有少量分数，单独不足以被拦截
+    let cases = [
+        ("Nice post, thanks", "approved"),
+        ("Deals at http://shop.spam.example/today", "spam"),
+        ("Join my casino night", "pending"),
+        ("Casinos are not my thing", "approved"),
+        (
+            "See http://a.example http://b.example http://c.example",
+            "pending",
+        ),
+        ("This is a perfectly normal comment text.", "approved"),
+        ("This is a perfectly normal comment text.", "pending"),
+        ("  THIS IS A PERFECTLY NORMAL COMMENT TEXT. ", "pending"),
+    ];
+    for (content, expected) in cases {
+        let (_, status) = comment(&client, &app_address, &author.token, post_id, content).await;
+        assert_eq!(expected, status, "评论 {:?} 的状态不符合预期", content);
+    }
+
+    // 编辑和管理员的评论不经过检测
+    let (_, status) = comment(
+        &client,
+        &app_address,
+        &editor.token,
+        post_id,
+        "Editor link http://spam.example",
+    )
+    .await;
+    assert_eq!("approved", status);
+
+    let response = client
+        .delete(format!("{}/comments/blocklist/{}", app_address, word_id))
+        .bearer_auth(&editor.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+
+    let blocklist: serde_json::Value = client
+        .get(format!("{}/comments/blocklist", app_address))
+        .bearer_auth(&editor.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, blocklist.as_array().unwrap().len());
+
+    let (_, status) = comment(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        "Another casino night",
+    )
+    .await;
+    assert_eq!("approved", status);
+}
+
+#[tokio::test]
+async fn edited_comments_are_scored_like_new_ones() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+    add_to_blocklist(
+        &client,
+        &app_address,
+        &editor.token,
+        "domain",
+        "spam.example",
+    )
+    .await;
+
+    let content = "A long and perfectly ordinary comment.";
+    let (comment_id, status) =
+        comment(&client, &app_address, &author.token, post_id, content).await;
+    assert_eq!("approved", status);
+
+    // 原样保存不会被当作与自身重复
+    let status = edit(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        comment_id,
+        content,
+    )
+    .await;
+    assert_eq!("approved", status);
+
+    let status = edit(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        comment_id,
+        "See http://a.example http://b.example http://c.example",
+    )
+    .await;
+    assert_eq!("pending", status);
+
+    let status = edit(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        comment_id,
+        "Deals at http://shop.spam.example/today",
+    )
+    .await;
+    assert_eq!("spam", status);
+}
+
+#[tokio::test]
+async fn classifier_learns_from_moderation_decisions() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_authenticated_user(&client, &app_address).await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    let post_id = create_post(&client, &app_address, &author.token).await;
+
+    // 排除账户注册时间的影响，只观察分类器给出的分数
+    sqlx::query("UPDATE users SET created_at = datetime('now', '-30 days') WHERE username = ?")
+        .bind(&author.username)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let mut spam_ids = Vec::new();
+    let mut ham_ids = Vec::new();
+    for i in 0..5 {
+        let spam = format!("Buy cheap pills online, offer {}", i);
+        let ham = format!("Great article about rust ownership, part {}", i);
+        spam_ids.push(
+            comment(&client, &app_address, &author.token, post_id, &spam)
+                .await
+                .0,
+        );
+        ham_ids.push(
+            comment(&client, &app_address, &author.token, post_id, &ham)
+                .await
+                .0,
+        );
+    }
+
+    // 样本不足时分类器不参与评分
+    let (_, status) = comment(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        "cheap pills for sale",
+    )
+    .await;
+    assert_eq!("approved", status);
+
+    moderate(&client, &app_address, &editor.token, &spam_ids, "spam").await;
+    moderate(&client, &app_address, &editor.token, &ham_ids, "approved").await;
+    assert_eq!((5, 5), training_documents(&pool).await);
+
+    let (_, status) = comment(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        "pills, cheap, on sale",
+    )
+    .await;
+    assert_eq!("pending", status);
+
+    let (_, status) = comment(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        "rust ownership is great",
+    )
+    .await;
+    assert_eq!("approved", status);
+
+    // 改变审核结果时撤销之前的训练，重复的决定不会重复训练
+    moderate(
+        &client,
+        &app_address,
+        &editor.token,
+        &spam_ids[..1],
+        "approved",
+    )
+    .await;
+    moderate(&client, &app_address, &editor.token, &ham_ids, "approved").await;
+    assert_eq!((4, 6), training_documents(&pool).await);
+
+    let (spam_count, ham_count): (i64, i64) =
+        sqlx::query_as("SELECT spam_count, ham_count FROM spam_tokens WHERE token = 'pills'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!((4, 1), (spam_count, ham_count));
+
+    // 修改后的内容不再是审核员判断过的样本，撤销那次训练
+    edit(
+        &client,
+        &app_address,
+        &author.token,
+        post_id,
+        spam_ids[0],
+        "Rewritten comment about rust",
+    )
+    .await;
+    assert_eq!((4, 5), training_documents(&pool).await);
+    let ham_count: i64 =
+        sqlx::query_scalar("SELECT ham_count FROM spam_tokens WHERE token = 'pills'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(0, ham_count);
+}
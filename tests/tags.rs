@@ -0,0 +1,226 @@
+mod common;
+use common::{
+    create_authenticated_user, create_user_with_role, migrate_before, spawn_app, spawn_app_on,
+    spawn_app_with_pool,
+};
+
+async fn create_post(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    tags: serde_json::Value,
+    status: &str,
+) -> reqwest::Response {
+    let post_body = serde_json::json!({
+        "title": "Tagged", "content": "c", "tags": tags, "copyright": "c", "status": status
+    });
+    client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(token)
+        .json(&post_body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_json(client: &reqwest::Client, url: String) -> serde_json::Value {
+    let response = client.get(url).send().await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+async fn rename_tag(
+    client: &reqwest::Client,
+    app_address: &str,
+    token: &str,
+    slug: &str,
+    name: &str,
+) -> reqwest::Response {
+    client
+        .put(format!("{}/tags/{}", app_address, slug))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "name": name }))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn tags_are_normalized_counted_and_filterable() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let response = create_post(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!(["Rust", "  web   dev ", "rust"]),
+        "published",
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(serde_json::json!(["Rust", "web dev"]), post["tags"]);
+
+    let response = create_post(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!(["Web-Dev"]),
+        "published",
+    )
+    .await;
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(serde_json::json!(["web dev"]), post["tags"]);
+
+    // 草稿不计入标签的文章数量
+    create_post(
+        &client,
+        &app_address,
+        &user.token,
+        serde_json::json!(["rust"]),
+        "draft",
+    )
+    .await;
+
+    let tags = get_json(&client, format!("{}/tags", app_address)).await;
+    assert_eq!(
+        serde_json::json!([
+            { "name": "web dev", "slug": "web-dev", "post_count": 2 },
+            { "name": "Rust", "slug": "rust", "post_count": 1 }
+        ]),
+        tags
+    );
+
+    let posts = get_json(&client, format!("{}/posts?tag=web-dev", app_address)).await;
+    assert_eq!(2, posts["total"]);
+
+    let page = get_json(&client, format!("{}/tags/rust", app_address)).await;
+    assert_eq!(1, page["tag"]["post_count"]);
+    assert_eq!(1, page["posts"]["total"]);
+    assert_eq!(
+        serde_json::json!(["Rust", "web dev"]),
+        page["posts"]["data"][0]["tags"]
+    );
+
+    let response = client
+        .get(format!("{}/tags/missing", app_address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    let too_long = "x".repeat(31);
+    let too_many: Vec<String> = (0..11).map(|i| format!("tag{}", i)).collect();
+    for tags in [
+        serde_json::json!([too_long]),
+        serde_json::json!(too_many),
+        serde_json::json!(["!!!"]),
+    ] {
+        let response = create_post(&client, &app_address, &user.token, tags, "published").await;
+        assert_eq!(400, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn editors_can_rename_tags() {
+    let (app_address, pool) = spawn_app_with_pool().await;
+    let client = reqwest::Client::new();
+    let author = create_user_with_role(&client, &app_address, &pool, "author").await;
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    create_post(
+        &client,
+        &app_address,
+        &author.token,
+        serde_json::json!(["js", "Rust"]),
+        "published",
+    )
+    .await;
+
+    let response = rename_tag(&client, &app_address, &author.token, "js", "JavaScript").await;
+    assert_eq!(403, response.status().as_u16());
+
+    let response = rename_tag(&client, &app_address, &editor.token, "js", "rust").await;
+    assert_eq!(409, response.status().as_u16());
+
+    let response = rename_tag(&client, &app_address, &editor.token, "js", "JavaScript").await;
+    assert_eq!(200, response.status().as_u16());
+    let tag: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("javascript", tag["slug"]);
+
+    let posts = get_json(&client, format!("{}/posts?tag=javascript", app_address)).await;
+    assert_eq!(
+        serde_json::json!(["JavaScript", "Rust"]),
+        posts["data"][0]["tags"]
+    );
+}
+
+#[tokio::test]
+async fn legacy_tag_strings_are_split_and_slugified_by_the_migration() {
+    // 按引入标签表之前的表结构写入以逗号分隔的标签
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    migrate_before(&pool, 20250912084517).await;
+    sqlx::query("INSERT INTO users (id, username, password_hash) VALUES (1, 'veteran', 'x')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for (id, tags) in [
+        (1, "Rust, Hello_World., C++"),
+        (2, "rust!,数据库，设计,  Rust "),
+    ] {
+        sqlx::query(
+            "INSERT INTO posts (id, title, author_id, content, tags, copyright) VALUES (?, 't', 1, 'c', ?, 'c')",
+        )
+        .bind(id)
+        .bind(tags)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO post_revisions (post_id, revision_number, editor_id, title, content, tags, copyright) \
+             VALUES (?, 1, 1, 't', 'c', ?, 'c')",
+        )
+        .bind(id)
+        .bind(tags)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let (app_address, pool, _) = spawn_app_on(pool, |_| {}).await;
+    let client = reqwest::Client::new();
+
+    // slug 与新建标签时的规则相同，slug 相同的标签合并
+    let tags = get_json(&client, format!("{}/tags", app_address)).await;
+    assert_eq!(
+        serde_json::json!([
+            { "name": "Rust", "slug": "rust", "post_count": 2 },
+            { "name": "C++", "slug": "c", "post_count": 1 },
+            { "name": "Hello_World.", "slug": "hello-world", "post_count": 1 },
+            { "name": "数据库，设计", "slug": "数据库-设计", "post_count": 1 }
+        ]),
+        tags
+    );
+
+    // 修订中的标签转为 JSON 数组
+    let revision_tags: Vec<String> =
+        sqlx::query_scalar("SELECT tags FROM post_revisions ORDER BY post_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        vec![
+            r#"["Rust","Hello_World.","C++"]"#,
+            r#"["rust!","数据库，设计","Rust"]"#
+        ],
+        revision_tags
+    );
+}
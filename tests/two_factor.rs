@@ -0,0 +1,386 @@
+mod common;
+use chrono::{Duration, Utc};
+use common::{
+    TestUser, create_authenticated_user, create_user_with_role, spawn_app, spawn_app_with_config,
+};
+use inkwell::{permissions::Role, two_factor::generate_code};
+
+/// 为用户启用两步验证，返回密钥、确认时使用的验证码和恢复码
+async fn enable_two_factor(
+    client: &reqwest::Client,
+    app_address: &str,
+    user: &TestUser,
+) -> (String, String, Vec<String>) {
+    let response = client
+        .post(format!("{}/me/2fa/setup", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let setup: serde_json::Value = response.json().await.unwrap();
+    let secret = setup["secret"].as_str().unwrap().to_string();
+
+    let confirmed_code = code(&secret, 0);
+    let response = confirm(client, app_address, user, &confirmed_code).await;
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let recovery_codes = body["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+    (secret, confirmed_code, recovery_codes)
+}
+
+async fn confirm(
+    client: &reqwest::Client,
+    app_address: &str,
+    user: &TestUser,
+    code: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/me/2fa/confirm", app_address))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "code": code }))
+        .send()
+        .await
+        .unwrap()
+}
+
+/// 偏移 `offset_steps` 个时间步后的验证码
+fn code(secret: &str, offset_steps: i64) -> String {
+    generate_code(secret, Utc::now() + Duration::seconds(30 * offset_steps)).unwrap()
+}
+
+async fn login(client: &reqwest::Client, app_address: &str, username: &str) -> serde_json::Value {
+    let response = client
+        .post(format!("{}/login", app_address))
+        .json(&serde_json::json!({ "username": username, "password": "password123" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+async fn challenge(client: &reqwest::Client, app_address: &str, username: &str) -> String {
+    let body = login(client, app_address, username).await;
+    assert_eq!(true, body["two_factor_required"]);
+    assert!(body.get("token").is_none());
+    body["challenge_token"].as_str().unwrap().to_string()
+}
+
+async fn login_two_factor(
+    client: &reqwest::Client,
+    app_address: &str,
+    challenge_token: &str,
+    code: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/login/2fa", app_address))
+        .json(&serde_json::json!({ "challenge_token": challenge_token, "code": code }))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn enrollment_requires_confirming_a_code() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    // 没有生成密钥时无法确认
+    let response = confirm(&client, &app_address, &user, "123456").await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .post(format!("{}/me/2fa/setup", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    let setup: serde_json::Value = response.json().await.unwrap();
+    let secret = setup["secret"].as_str().unwrap();
+    let uri = setup["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with(&format!("otpauth://totp/Inkwell:{}?", user.username)));
+    assert!(uri.contains(&format!("secret={}", secret)));
+    assert!(uri.contains("issuer=Inkwell"));
+
+    // 确认之前登录不受影响
+    let body = login(&client, &app_address, &user.username).await;
+    assert!(body["token"].is_string());
+
+    let response = confirm(&client, &app_address, &user, "000000").await;
+    assert_eq!(400, response.status().as_u16());
+    let response = confirm(&client, &app_address, &user, &code(secret, 0)).await;
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(10, body["recovery_codes"].as_array().unwrap().len());
+
+    let me: serde_json::Value = client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(true, me["two_factor_enabled"]);
+
+    let response = client
+        .post(format!("{}/me/2fa/setup", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn login_requires_a_fresh_code_or_recovery_code() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let (secret, confirmed_code, recovery_codes) =
+        enable_two_factor(&client, &app_address, &user).await;
+
+    let challenge_token = challenge(&client, &app_address, &user.username).await;
+    let response =
+        login_two_factor(&client, &app_address, "not-a-challenge", &code(&secret, 1)).await;
+    assert_eq!(401, response.status().as_u16());
+
+    // 确认时用过的验证码不能再次使用
+    let response = login_two_factor(&client, &app_address, &challenge_token, &confirmed_code).await;
+    assert_eq!(401, response.status().as_u16());
+
+    let response =
+        login_two_factor(&client, &app_address, &challenge_token, &code(&secret, 1)).await;
+    assert_eq!(200, response.status().as_u16());
+    let tokens: serde_json::Value = response.json().await.unwrap();
+    let response = client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(tokens["token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    // 登录挑战只能使用一次
+    let response =
+        login_two_factor(&client, &app_address, &challenge_token, &recovery_codes[0]).await;
+    assert_eq!(401, response.status().as_u16());
+
+    // 恢复码不区分大小写，每个只能使用一次
+    let challenge_token = challenge(&client, &app_address, &user.username).await;
+    let response = login_two_factor(
+        &client,
+        &app_address,
+        &challenge_token,
+        &recovery_codes[0].to_uppercase(),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let challenge_token = challenge(&client, &app_address, &user.username).await;
+    let response =
+        login_two_factor(&client, &app_address, &challenge_token, &recovery_codes[0]).await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn challenges_are_invalidated_after_too_many_wrong_codes() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let (_, _, recovery_codes) = enable_two_factor(&client, &app_address, &user).await;
+
+    let challenge_token = challenge(&client, &app_address, &user.username).await;
+    for _ in 0..5 {
+        let response = login_two_factor(&client, &app_address, &challenge_token, "000000").await;
+        assert_eq!(401, response.status().as_u16());
+    }
+    let response =
+        login_two_factor(&client, &app_address, &challenge_token, &recovery_codes[0]).await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn wrong_codes_count_towards_the_login_lockout() {
+    let (app_address, pool, _) = spawn_app_with_config(|_| {}).await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let (secret, _, _) = enable_two_factor(&client, &app_address, &user).await;
+
+    // 重新输入正确的密码不会清零之前错误验证码的计数
+    let challenge_token = challenge(&client, &app_address, &user.username).await;
+    for _ in 0..3 {
+        let response = login_two_factor(&client, &app_address, &challenge_token, "000000").await;
+        assert_eq!(401, response.status().as_u16());
+    }
+    let challenge_token = challenge(&client, &app_address, &user.username).await;
+    for _ in 0..2 {
+        let response = login_two_factor(&client, &app_address, &challenge_token, "000000").await;
+        assert_eq!(401, response.status().as_u16());
+    }
+    let response =
+        login_two_factor(&client, &app_address, &challenge_token, &code(&secret, 1)).await;
+    assert_eq!(429, response.status().as_u16());
+    let response = client
+        .post(format!("{}/login", app_address))
+        .json(&serde_json::json!({ "username": user.username, "password": "password123" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(429, response.status().as_u16());
+
+    // 锁定结束后通过第二步验证才清零计数
+    sqlx::query("UPDATE login_failures SET locked_until = datetime('now', '-1 second')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let response =
+        login_two_factor(&client, &app_address, &challenge_token, &code(&secret, 1)).await;
+    assert_eq!(200, response.status().as_u16());
+    let failures: Option<(i64,)> =
+        sqlx::query_as("SELECT failures FROM login_failures WHERE scope = 'account' AND key = ?")
+            .bind(&user.username)
+            .fetch_optional(&pool)
+            .await
+            .unwrap();
+    assert!(failures.is_none());
+}
+
+#[tokio::test]
+async fn recovery_codes_can_be_regenerated_and_2fa_disabled() {
+    let app_address = spawn_app().await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+    let (secret, _, old_codes) = enable_two_factor(&client, &app_address, &user).await;
+
+    let response = client
+        .post(format!("{}/me/2fa/recovery-codes", app_address))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "code": code(&secret, 1) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let new_code = body["recovery_codes"][0].as_str().unwrap().to_string();
+
+    let disable = |password: &str, code: &str| {
+        client
+            .delete(format!("{}/me/2fa", app_address))
+            .bearer_auth(&user.token)
+            .json(&serde_json::json!({ "password": password, "code": code }))
+            .send()
+    };
+
+    // 重新生成后旧恢复码失效
+    let response = disable("password123", &old_codes[1]).await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+    let response = disable("wrong-password", &new_code).await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+    let response = disable("password123", &new_code).await.unwrap();
+    assert_eq!(204, response.status().as_u16());
+    let response = disable("password123", &new_code).await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+
+    let body = login(&client, &app_address, &user.username).await;
+    assert!(body["token"].is_string());
+}
+
+#[tokio::test]
+async fn privileged_roles_must_enable_2fa_when_required() {
+    let (app_address, pool, _) = spawn_app_with_config(|config| {
+        config.two_factor_required_roles = vec![Role::Editor, Role::Admin];
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let admin = create_user_with_role(&client, &app_address, &pool, "admin").await;
+
+    let list_users = |token: String| {
+        client
+            .get(format!("{}/admin/users", app_address))
+            .bearer_auth(token)
+            .send()
+    };
+
+    let response = list_users(admin.token.clone()).await.unwrap();
+    assert_eq!(403, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("两步验证"));
+
+    // 未启用时仍然保留作者的权限
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(&admin.token)
+        .json(&serde_json::json!({
+            "title": "管理员的文章", "content": "内容", "tags": ["t"], "copyright": "c"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+
+    // 启用后原有的令牌立即获得全部权限
+    enable_two_factor(&client, &app_address, &admin).await;
+    let response = list_users(admin.token.clone()).await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    // 管理员可以为丢失验证器的用户关闭两步验证
+    let editor = create_user_with_role(&client, &app_address, &pool, "editor").await;
+    enable_two_factor(&client, &app_address, &editor).await;
+    let editor_id: (i64,) = sqlx::query_as("SELECT id FROM users WHERE username = ?")
+        .bind(&editor.username)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let response = client
+        .delete(format!("{}/admin/users/{}/2fa", app_address, editor_id.0))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(false, body["two_factor_enabled"]);
+
+    let body = login(&client, &app_address, &editor.username).await;
+    assert!(body["token"].is_string());
+}
+
+#[tokio::test]
+async fn pending_2fa_never_grants_more_than_the_role() {
+    let (app_address, _, _) = spawn_app_with_config(|config| {
+        config.default_user_role = Role::User;
+        config.two_factor_required_roles = vec![Role::User];
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let user = create_authenticated_user(&client, &app_address).await;
+
+    // 普通用户未启用两步验证时不能获得作者发表文章的权限
+    let response = client
+        .post(format!("{}/posts", app_address))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({
+            "title": "普通用户的文章", "content": "内容", "tags": ["t"], "copyright": "c"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    // 不需要额外权限的操作不受影响
+    let response = client
+        .get(format!("{}/me", app_address))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+}